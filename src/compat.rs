@@ -0,0 +1,219 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    collections::HashSet,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Result};
+use rhai::{Array, Dynamic, Engine, EvalAltResult, Map};
+
+use crate::{
+    grade::{show_result, ByHiddenTestGrader, ByUnitTestGrader, DocsGrader, GradeResult},
+    java::Project,
+};
+
+/// Version of the scripting API `create_engine` registers.
+///
+/// * 1: free functions like `grade_docs(files, project, out_of, req_name)`
+/// * 2: builders like `new_docs_grader().files(files).run()`, and
+///   `show_results(results)`
+/// * 3: `show_results(results, gradescope_config)`
+/// * 4: `print` writes to stderr, and scores are reported with `emit_score` and
+///   `set_status`
+pub const API_VERSION: u32 = 4;
+
+/// Comment a script uses to declare the API version it was written for, as
+/// in `// umm-api: 2`
+pub const API_VERSION_DIRECTIVE: &str = "// umm-api:";
+
+/// A function from an older version of the scripting API that is still
+/// available through a compatibility shim
+#[derive(Debug, Clone, Copy)]
+pub struct Deprecation {
+    /// name of the function
+    pub name:       &'static str,
+    /// number of parameters it takes
+    pub params:     usize,
+    /// the first API version without it
+    pub removed_in: u32,
+    /// how to rewrite a call to it for the current API
+    pub hint:       &'static str,
+}
+
+/// Every function removed from the scripting API that has a shim
+pub const DEPRECATIONS: [Deprecation; 5] =
+    [Deprecation { name:       "new_project",
+                   params:     0,
+                   removed_in: 2,
+                   hint:       "use new_java_project() instead", },
+     Deprecation { name:       "grade_docs",
+                   params:     4,
+                   removed_in: 2,
+                   hint:       "use new_docs_grader().files(files).project(project).\
+                                out_of(out_of).req_name(req_name).run() instead", },
+     Deprecation { name:       "grade_by_tests",
+                   params:     5,
+                   removed_in: 2,
+                   hint:       "use new_by_unit_test_grader().test_files(test_files).\
+                                expected_tests(expected_tests).project(project).out_of(out_of).\
+                                req_name(req_name).run() instead", },
+     Deprecation { name:       "grade_by_hidden_tests",
+                   params:     4,
+                   removed_in: 2,
+                   hint:       "use new_by_hidden_test_grader().url(url).\
+                                test_class_name(test_class_name).out_of(out_of).\
+                                req_name(req_name).run() instead", },
+     Deprecation { name:       "show_results",
+                   params:     1,
+                   removed_in: 3,
+                   hint:       "pass Gradescope options as a second argument, as in \
+                                show_results(results, #{}) instead", }];
+
+/// Returns the API version a script declares with [API_VERSION_DIRECTIVE],
+/// or `None` if it doesn't declare one. Fails for versions newer than this
+/// build of umm knows about.
+///
+/// * `script`: source of the script
+pub fn declared_api_version(script: &str) -> Result<Option<u32>> {
+    let Some(value) = script.lines()
+                            .find_map(|line| line.trim().strip_prefix(API_VERSION_DIRECTIVE))
+    else {
+        return Ok(None);
+    };
+
+    let version = match value.trim().parse::<u32>() {
+        Ok(version) if (1..=API_VERSION).contains(&version) => version,
+        Ok(version) if version > API_VERSION => {
+            bail!("This script needs version {version} of the scripting API, but this version of \
+                   umm only supports up to {API_VERSION}. Please run `umm update`.")
+        }
+        _ => bail!("Invalid API version in `{API_VERSION_DIRECTIVE}{value}`, expected a number \
+                    from 1 to {API_VERSION}"),
+    };
+
+    Ok(Some(version))
+}
+
+/// Deprecated functions a script written for `version` may call. Scripts
+/// that don't declare a version may call any of them.
+///
+/// * `version`: the API version the script declares, if any
+pub fn deprecations_for(version: Option<u32>) -> impl Iterator<Item = &'static Deprecation> {
+    DEPRECATIONS.iter()
+                .filter(move |d| version.is_none_or(|v| v < d.removed_in))
+}
+
+/// Whether `print` writes to stdout for a script written for `version`, as
+/// it did before scores were reported with `emit_score`. Scripts that don't
+/// declare a version print to stdout.
+///
+/// * `version`: the API version the script declares, if any
+pub fn prints_to_stdout(version: Option<u32>) -> bool {
+    version.is_none_or(|v| v < 4)
+}
+
+/// Reads a number of points that older scripts may have written as an
+/// integer, like `grade_docs(files, project, 10, "1")`
+///
+/// * `value`: the number
+fn points(value: &Dynamic) -> Result<f64, Box<EvalAltResult>> {
+    value.as_float()
+         .or_else(|_| value.as_int().map(|i| i as f64))
+         .map_err(|t| format!("Expected a number of points, found {t}").into())
+}
+
+/// Registers shims for the deprecated functions a script written for
+/// `version` may call. Each shim logs a warning with a migration hint the
+/// first time it is called.
+///
+/// * `engine`: the engine to register them with
+/// * `version`: the API version the script declares, if any
+pub fn register_shims(engine: &mut Engine,
+                      version: Option<u32>) {
+    let warned = Arc::new(Mutex::new(HashSet::<&'static str>::new()));
+    let warn = move |name: &'static str| {
+        if warned.lock().unwrap().insert(name) {
+            if let Some(d) = DEPRECATIONS.iter().find(|d| d.name == name) {
+                tracing::warn!("{name}() was removed in version {} of the scripting API, {}",
+                               d.removed_in,
+                               d.hint);
+            }
+        }
+    };
+
+    for deprecation in deprecations_for(version) {
+        let warn = warn.clone();
+        match deprecation.name {
+            "new_project" => {
+                engine.register_fn("new_project", move || {
+                          warn("new_project");
+                          Project::new_script()
+                      });
+            }
+            "grade_docs" => {
+                engine.register_fn("grade_docs",
+                                   move |files: Array,
+                                         project: Project,
+                                         out_of: Dynamic,
+                                         req_name: &str|
+                                         -> Result<GradeResult, Box<EvalAltResult>> {
+                                       warn("grade_docs");
+                                       DocsGrader::default().set_files(files)
+                                                            .set_project(project)
+                                                            .set_out_of(points(&out_of)?)
+                                                            .set_req_name(req_name.to_string())
+                                                            .grade_docs()
+                                                            .map_err(|e| format!("{e}").into())
+                                   });
+            }
+            "grade_by_tests" => {
+                engine.register_fn("grade_by_tests",
+                                   move |test_files: Array,
+                                         expected_tests: Array,
+                                         project: Project,
+                                         out_of: Dynamic,
+                                         req_name: &str|
+                                         -> Result<GradeResult, Box<EvalAltResult>> {
+                                       warn("grade_by_tests");
+                                       ByUnitTestGrader::default()
+                        .set_test_files(test_files)
+                        .set_expected_tests(expected_tests)
+                        .set_project(project)
+                        .set_out_of(points(&out_of)?)
+                        .set_req_name(req_name.to_string())
+                        .grade_by_tests()
+                        .map_err(|e| format!("{e}").into())
+                                   });
+            }
+            "grade_by_hidden_tests" => {
+                engine.register_fn("grade_by_hidden_tests",
+                                   move |url: &str,
+                                         test_class_name: &str,
+                                         out_of: Dynamic,
+                                         req_name: &str|
+                                         -> Result<GradeResult, Box<EvalAltResult>> {
+                                       warn("grade_by_hidden_tests");
+                                       ByHiddenTestGrader::default()
+                        .set_url(url.to_string())
+                        .set_test_class_name(test_class_name.to_string())
+                        .set_out_of(points(&out_of)?)
+                        .set_req_name(req_name.to_string())
+                        .grade_by_hidden_tests()
+                        .map_err(|e| format!("{e}").into())
+                                   });
+            }
+            "show_results" => {
+                engine.register_fn("show_results",
+                                   move |results: Array| -> Result<(), Box<EvalAltResult>> {
+                                       warn("show_results");
+                                       show_result(results, Map::new()).map_err(|e| {
+                                                                           format!("{e}").into()
+                                                                       })
+                                   });
+            }
+            _ => {}
+        }
+    }
+}
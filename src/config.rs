@@ -0,0 +1,65 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+use crate::constants::{PROJECT_CONFIG, ROOT_DIR};
+
+/// The `[discovery]` table, choosing which Java files are part of the project
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiscoveryConfig {
+    /// globs of files to include, relative to the project root
+    pub include:   Vec<String>,
+    /// globs of files to leave out, relative to the project root
+    pub exclude:   Vec<String>,
+    /// how many folders deep to look for files
+    pub max_depth: Option<usize>,
+}
+
+/// The `[java]` table, choosing which versions of Java the project uses
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct JavaConfig {
+    /// the oldest major version of Java allowed
+    pub min_version: Option<u32>,
+    /// the major version passed to `javac --release`
+    pub release:     Option<u32>,
+}
+
+/// Settings for a project, read from [PROJECT_CONFIG] in the project root.
+/// Courses usually ship one with their starter code, like:
+///
+/// ```toml
+/// [discovery]
+/// exclude = ["**/old/**"]
+///
+/// [java]
+/// min_version = 17
+/// release = 17
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectConfig {
+    /// which Java files are part of the project
+    pub discovery: DiscoveryConfig,
+    /// which versions of Java the project uses
+    pub java:      JavaConfig,
+}
+
+impl ProjectConfig {
+    /// Reads [PROJECT_CONFIG] from the project root, or the defaults if there
+    /// isn't one
+    pub fn load() -> Result<Self> {
+        let path = ROOT_DIR.join(PROJECT_CONFIG);
+        if !path.is_file() {
+            return Ok(Self::default());
+        }
+
+        let source = std::fs::read_to_string(&path).with_context(|| {
+                                                       format!("Could not read {}", path.display())
+                                                   })?;
+        toml::from_str(&source).with_context(|| format!("Invalid settings in {}", path.display()))
+    }
+}
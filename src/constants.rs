@@ -11,6 +11,8 @@ use postgrest::Postgrest;
 use rhai::AST;
 use state::InitCell;
 
+use crate::{outcome::Outcome, policy::LatePolicy, starter::Starter, toolchain::Toolchain};
+
 // TODO: replace with https://lib.rs/crates/state
 lazy_static! {
     /// Path to project root
@@ -25,6 +27,8 @@ lazy_static! {
     pub static ref LIB_DIR: PathBuf = PathBuf::from(".").join("lib");
     /// Directory for `umm` artifacts
     pub static ref UMM_DIR: PathBuf = PathBuf::from(".").join(".umm");
+    /// Local registry of grading scripts, used unless another source is configured
+    pub static ref SCRIPTS_DIR: PathBuf = PathBuf::from(".").join(".umm").join("scripts");
     /// Platform specific separator character for javac paths
     pub static ref SEPARATOR: &'static str = if cfg!(windows) { ";" } else { ":" };
     /// Supabase public api key
@@ -46,6 +50,18 @@ lazy_static! {
     pub static ref RETRIEVAL_MESSAGE_OUTRO: String = include_str!("prompts/retrieval_system_message_outro.md").into();
     /// Rhai script as a AST, behind an mutex.
     pub static ref SCRIPT_AST: Arc<Mutex<AST>> = Arc::new(Mutex::new(AST::empty()));
+    /// Version of the scripting API the running script declared, behind an mutex. Engines that call back into the script are set up for it.
+    pub static ref SCRIPT_API_VERSION: Arc<Mutex<Option<u32>>> = Arc::new(Mutex::new(None));
+    /// Java installations found on this machine, and the one in use
+    pub static ref TOOLCHAIN: Toolchain = Toolchain::detect();
+    /// Starter code handed out to students, behind an mutex. Set from rhai scripts.
+    pub static ref STARTER_CODE: Arc<Mutex<Option<Starter>>> = Arc::new(Mutex::new(None));
+    /// Hex SHA-256 of the grading script or rubric being run, for attestations
+    pub static ref GRADED_SCRIPT_SHA256: Arc<Mutex<Option<String>>> = Arc::new(Mutex::new(None));
+    /// Score and status the grading script reported, written to stdout when it finishes
+    pub static ref SCRIPT_OUTCOME: Arc<Mutex<Outcome>> = Arc::new(Mutex::new(Outcome::default()));
+    /// Late policy applied to the results `show_results` reports, behind an mutex. Set from rhai scripts and rubrics.
+    pub static ref LATE_POLICY: Arc<Mutex<Option<LatePolicy>>> = Arc::new(Mutex::new(None));
     /// System Message for Algorithmic Solutions SLO
     pub static ref ALGORITHMIC_SOLUTIONS_SLO: String = format!(include_str!("prompts/slos/system_message_intro.md"), SLO_DESCRIPTION = include_str!("prompts/slos/algorithmic_solutions_quant.md"));
     /// System Message for Code Readability SLO
@@ -72,12 +88,112 @@ pub const TERM: &str = "Fall 2022";
 /// Current course. TODO: Move this to init script
 pub const COURSE: &str = "ITSC 2214";
 
+/// Environment variable choosing where `umm grade <name>` looks up grading
+/// scripts: a folder, [struct@SCRIPTS_DIR] by default, `git:<url>` for a git
+/// repository, the URL of an HTTP index, or `database` for the course
+/// database
+pub const SCRIPT_SOURCE_ENV: &str = "UMM_SCRIPT_SOURCE";
+
+/// File listing the assignments in a grading script registry, and the hashes
+/// of their scripts
+pub const REGISTRY_INDEX: &str = "index.json";
+
+/// Environment variable overriding the limits grading scripts run with,
+/// written like `operations=50000000,call_levels=128`
+pub const ENGINE_LIMITS_ENV: &str = "UMM_ENGINE_LIMITS";
+
+/// Environment variable choosing how the score and status a grading script
+/// reports are written to stdout: `legacy` (the default) or `json`
+pub const OUTCOME_FORMAT_ENV: &str = "UMM_OUTCOME_FORMAT";
+
+/// Base64 ed25519 public keys grading scripts, modules, rubrics and hidden
+/// tests must be signed with, separated by `;`. Courses building umm for
+/// their students set [TRUSTED_KEYS_ENV] when building it to build theirs in.
+pub const TRUSTED_KEYS: Option<&str> = option_env!("UMM_TRUSTED_KEYS");
+
+/// Environment variable listing trusted public keys, separated by `;`. Only
+/// used when no keys are built into umm, as on autograders students can't
+/// configure. Without trusted keys, scripts, modules and hidden tests fetched
+/// from a database, git repository or URL are refused, while local files are
+/// used unchecked.
+pub const TRUSTED_KEYS_ENV: &str = "UMM_TRUSTED_KEYS";
+
+/// Environment variable with the path to the private key graded results are
+/// attested with
+pub const ATTESTATION_KEY_ENV: &str = "UMM_ATTESTATION_KEY";
+
+/// Extension of detached signatures, as in `lab1.rhai.sig`
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Oldest major version of Java umm works with, unless a course requires a
+/// newer one with [JAVA_MIN_VERSION_ENV] or `min_version` in [PROJECT_CONFIG]
+pub const MIN_JAVA_VERSION: u32 = 11;
+
+/// Environment variable holding the oldest major version of Java a course
+/// allows, like `17`. Overrides `min_version` in [PROJECT_CONFIG].
+pub const JAVA_MIN_VERSION_ENV: &str = "UMM_JAVA_MIN_VERSION";
+
+/// Environment variable holding the major version of Java passed to
+/// `javac --release`, so code compiled with a newer JDK still runs on that
+/// one. Overrides `release` in [PROJECT_CONFIG].
+pub const JAVA_RELEASE_ENV: &str = "UMM_JAVA_RELEASE";
+
+/// File in the project root with settings for the project, see
+/// [ProjectConfig](crate::config::ProjectConfig)
+pub const PROJECT_CONFIG: &str = "umm.toml";
+
+/// Environment variable listing globs of the Java files to include in a
+/// project, separated by `;`. Overrides `include` in [PROJECT_CONFIG].
+pub const INCLUDE_ENV: &str = "UMM_INCLUDE";
+
+/// Environment variable listing globs of the Java files to leave out of a
+/// project, separated by `;`. Overrides `exclude` in [PROJECT_CONFIG].
+pub const EXCLUDE_ENV: &str = "UMM_EXCLUDE";
+
+/// Environment variable listing places grading scripts can `import` modules
+/// from, separated by `;`. Each is a folder, or a URL that `<name>.rhai` is
+/// appended to. They are searched before [COURSE_MODULES_URL].
+pub const MODULE_PATH_ENV: &str = "UMM_MODULE_PATH";
+
+/// Where the course's shared grading script modules are published
+pub const COURSE_MODULES_URL: &str =
+    "https://ummfiles.fra1.digitaloceanspaces.com/grading_scripts/modules";
+
+/// Name grading scripts call the built-in helpers module by, as in
+/// `umm::std::total(results)`
+pub const STD_MODULE_NAME: &str = "umm::std";
+
+/// Source of the built-in helpers module
+pub const STD_MODULE: &str = include_str!("rhai/std.rhai");
+
 /// Prompt truncation length
 pub const PROMPT_TRUNCATE: usize = 15000;
 
 /// file name for JUnit platform console standard jar
 pub const JUNIT_PLATFORM: &str = "junit-platform-console-standalone-1.9.0-RC1.jar";
 
+/// Jar files downloaded into [struct@LIB_DIR] for projects that use JUnit,
+/// as (file name, url) pairs
+pub const LIBRARIES: [(&str, &str); 8] =
+    [(JUNIT_PLATFORM,
+      "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/junit-platform-console-standalone-1.10.2.jar"),
+     ("junit-4.13.2.jar",
+      "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/junit-4.13.2.jar"),
+     ("pitest.jar", "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/pitest-1.16.1.jar"),
+     ("pitest-command-line.jar",
+      "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/pitest-command-line-1.16.1.jar"),
+     ("pitest-entry.jar",
+      "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/pitest-entry-1.16.1.jar"),
+     ("pitest-junit5-plugin.jar",
+      "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/pitest-junit5-plugin-1.2.1.jar"),
+     ("commons-text-1.12.0.jar",
+      "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/commons-text-1.12.0.jar"),
+     ("commons-lang3-3.14.0.jar",
+      "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/commons-lang3-3.14.0.jar")];
+
+/// Port the JVM listens on for a debugger when debugging tests
+pub const JDWP_PORT: u16 = 5005;
+
 /// Tree-sitter query that returns imports made
 /// * `path`: java name of the import as it appears in the source code.
 /// * `asterisk`: true if the import path ends in an asterisk
@@ -105,12 +221,19 @@ pub const MAIN_METHOD_QUERY: &str = include_str!("queries/main_method.scm");
 /// Tree-sitter query that returns class declaration statements
 /// * `className`: class name
 /// * `typeParameters`: type parameters
+/// * `superclass`: superclass
 /// * `interfaces`: interfaces
 pub const CLASS_DECLARATION_QUERY: &str = include_str!("queries/class_declaration.scm");
 
 /// * `field`: entire field declaration
 pub const CLASS_FIELDS_QUERY: &str = include_str!("queries/class_fields.scm");
 
+/// Tree-sitter query that returns class field signatures, one per declarator
+/// * `modifier`: field modifiers
+/// * `type`: field type
+/// * `identifier`: field identifier
+pub const CLASS_FIELD_SIGNATURES_QUERY: &str = include_str!("queries/class_field_signatures.scm");
+
 /// Tree-sitter query that returns class constructor signatures
 /// * `modifier`: constructor modifiers
 /// * `annotation`: constructor annotations
@@ -142,9 +265,24 @@ pub const INTERFACE_CONSTANTS_QUERY: &str = include_str!("queries/interface_cons
 /// * `signature`: entire method signature
 pub const INTERFACE_METHODS_QUERY: &str = include_str!("queries/interface_methods.scm");
 
+/// Tree-sitter query that returns interface method signatures, split into
+/// their parts
+/// * `modifier`: method modifiers
+/// * `typeParameters`: method type parameters
+/// * `returnType`: method return type
+/// * `identifier`: method identifier
+/// * `parameters`: method parameters
+/// * `throws`: method throws
+pub const INTERFACE_METHOD_SIGNATURES_QUERY: &str =
+    include_str!("queries/interface_method_signatures.scm");
+
 /// Tree-sitter query that returns method call identifiers
 /// * `name`: method call identifier
 pub const METHOD_CALL_QUERY: &str = include_str!("queries/method_invocation.scm");
 
 /// Whether to use active retrieval or heuristic based retrieval
 pub static USE_ACTIVE_RETRIEVAL: InitCell<bool> = InitCell::new();
+
+/// Metadata Gradescope writes about the submission being graded, including
+/// when it was made and when the assignment is due
+pub const SUBMISSION_METADATA: &str = "/autograder/submission_metadata.json";
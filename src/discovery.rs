@@ -0,0 +1,412 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    collections::BTreeMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Serialize};
+use typed_builder::TypedBuilder;
+use walkdir::{DirEntry, WalkDir};
+
+use crate::{
+    config::ProjectConfig,
+    constants::{BUILD_DIR, EXCLUDE_ENV, INCLUDE_ENV, LIB_DIR, ROOT_DIR, SOURCE_DIR, TEST_DIR},
+    java::File,
+};
+
+/// Names of ignore files read from the project root, in order of precedence
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ummignore"];
+
+/// Folders that never contain student code
+const IGNORED_DIRS: [&str; 2] = ["__MACOSX", "node_modules"];
+
+/// Files that mark a folder as a separate project
+const PROJECT_MARKERS: [&str; 4] = [".git", "pom.xml", "build.gradle", "build.gradle.kts"];
+
+/// Glob options for paths, `*` does not match across `/`
+const MATCH_OPTIONS: MatchOptions = MatchOptions { case_sensitive:              true,
+                                                   require_literal_separator:   true,
+                                                   require_literal_leading_dot: false, };
+
+/// A single line of a `.gitignore` style file
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    /// the glob to match against
+    pattern:  Pattern,
+    /// whether the line started with `!`, re-including what it matches
+    negated:  bool,
+    /// whether the line ended with `/`, only matching folders
+    dir_only: bool,
+    /// whether the glob is matched against the whole path from the root, as
+    /// opposed to just the file name
+    anchored: bool,
+}
+
+impl IgnoreRule {
+    /// Parses a line of an ignore file, `None` for blank lines and comments
+    ///
+    /// * `line`: the line to parse
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+
+        Some(Self { pattern: Pattern::new(line).ok()?,
+                    negated,
+                    dir_only,
+                    anchored })
+    }
+
+    /// Whether the rule matches a path
+    ///
+    /// * `relative`: path from the project root, with `/` separators
+    /// * `is_dir`: whether the path is a folder
+    fn matches(&self,
+               relative: &str,
+               is_dir: bool)
+               -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+
+        if self.anchored {
+            self.pattern.matches_with(relative, MATCH_OPTIONS)
+        } else {
+            let name = relative.rsplit('/').next().unwrap_or(relative);
+            self.pattern.matches_with(name, MATCH_OPTIONS)
+        }
+    }
+}
+
+/// Kinds of problems found while discovering a project's files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    /// a file could not be read or parsed, and was left out
+    Unreadable,
+    /// more than one file declares the same fully qualified name
+    DuplicateClass,
+    /// classes with the same name in different packages
+    AmbiguousName,
+    /// a package declaration that doesn't match the file's folder
+    PackageMismatch,
+}
+
+/// A problem found while discovering a project's files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveryWarning {
+    /// what kind of problem this is
+    pub kind:    WarningKind,
+    /// the files involved
+    pub files:   Vec<PathBuf>,
+    /// a description of the problem
+    pub message: String,
+}
+
+impl Display for DiscoveryWarning {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+/// Options controlling which Java files are part of a project
+#[derive(Debug, Clone, TypedBuilder)]
+pub struct DiscoveryOptions {
+    /// how many folders deep to look for files
+    #[builder(default = 15)]
+    max_depth: usize,
+    /// globs relative to the project root, e.g. `src/**/*.java`. When not
+    /// empty, only files matching one of these are included.
+    #[builder(default, setter(into))]
+    include:   Vec<String>,
+    /// globs relative to the project root, e.g. `**/old/**`. Files matching
+    /// any of these are left out.
+    #[builder(default, setter(into))]
+    exclude:   Vec<String>,
+}
+
+impl Default for DiscoveryOptions {
+    fn default() -> Self {
+        Self::builder().build()
+    }
+}
+
+impl DiscoveryOptions {
+    /// Options for the project, from the `[discovery]` table of `umm.toml`,
+    /// with globs in [INCLUDE_ENV] and [EXCLUDE_ENV]
+    /// taking precedence
+    pub fn configured() -> Result<Self> {
+        let config = ProjectConfig::load()?.discovery;
+        let globs = |name: &str| {
+            std::env::var(name).ok().map(|value| {
+                                        value.split(';')
+                                             .map(str::trim)
+                                             .filter(|g| !g.is_empty())
+                                             .map(String::from)
+                                             .collect::<Vec<_>>()
+                                    })
+        };
+
+        Ok(Self { max_depth: config.max_depth
+                                   .unwrap_or_else(|| Self::default().max_depth),
+                  include:   globs(INCLUDE_ENV).unwrap_or(config.include),
+                  exclude:   globs(EXCLUDE_ENV).unwrap_or(config.exclude), })
+    }
+}
+
+/// Returns `path` relative to `root`, with `/` separators
+///
+/// * `root`: the project root
+/// * `path`: a path under `root`
+fn relative_path(root: &Path,
+                 path: &Path)
+                 -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Whether `name` is a valid Java identifier, such as a class or package
+/// name segment
+///
+/// * `name`: the name to check
+pub fn is_java_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next()
+         .is_some_and(|c| c.is_alphabetic() || c == '_' || c == '$')
+    && chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+}
+
+/// Whether a file name could belong to a Java class, which rules out backup
+/// copies like `Main copy.java` or `Main (1).java`
+///
+/// * `path`: path to the file
+fn is_java_source(path: &Path) -> bool {
+    path.extension().and_then(|e| e.to_str()) == Some("java")
+    && path.file_stem()
+           .and_then(|s| s.to_str())
+           .is_some_and(is_java_identifier)
+}
+
+/// Decides what is left out when walking a project.
+///
+/// Hidden folders, the build and library folders, `__MACOSX` folders and
+/// nested projects (folders with their own `.git`, `pom.xml` or
+/// `build.gradle`) are skipped, as is anything matched by a `.gitignore` or
+/// `.ummignore` file in the project root. Rules in `.ummignore` are applied
+/// after those in `.gitignore`, so `!pattern` there re-includes files git
+/// ignores.
+#[derive(Debug, Clone)]
+pub struct ProjectFilter {
+    /// the project root
+    root:  PathBuf,
+    /// rules from the ignore files, in the order they were read
+    rules: Vec<IgnoreRule>,
+}
+
+impl ProjectFilter {
+    /// Reads the ignore files in the project root
+    ///
+    /// * `root`: the project root
+    pub fn new(root: &Path) -> Self {
+        let mut rules = vec![];
+        for name in IGNORE_FILES {
+            if let Ok(source) = std::fs::read_to_string(root.join(name)) {
+                rules.extend(source.lines().filter_map(IgnoreRule::parse));
+            }
+        }
+
+        Self { root: root.to_path_buf(),
+               rules }
+    }
+
+    /// Whether a folder never contains project code, such as a hidden folder
+    /// or a nested project
+    ///
+    /// * `entry`: an entry found walking the project root
+    pub fn skipped_dir(&self,
+                       entry: &DirEntry)
+                       -> bool {
+        if entry.depth() == 0 || !entry.file_type().is_dir() {
+            return false;
+        }
+
+        let name = entry.file_name().to_string_lossy();
+        name.starts_with('.')
+        || IGNORED_DIRS.contains(&name.as_ref())
+        || entry.path() == BUILD_DIR.as_path()
+        || entry.path() == LIB_DIR.as_path()
+        || PROJECT_MARKERS.iter()
+                          .any(|marker| entry.path().join(marker).exists())
+    }
+
+    /// Whether a file or folder is matched by the ignore files
+    ///
+    /// * `entry`: an entry found walking the project root
+    pub fn ignored(&self,
+                   entry: &DirEntry)
+                   -> bool {
+        let relative = relative_path(&self.root, entry.path());
+        let is_dir = entry.file_type().is_dir();
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.matches(&relative, is_dir))
+            .is_some_and(|rule| !rule.negated)
+    }
+
+    /// Whether a file or folder is left out, along with everything inside it
+    ///
+    /// * `entry`: an entry found walking the project root
+    pub fn skips(&self,
+                 entry: &DirEntry)
+                 -> bool {
+        self.skipped_dir(entry) || self.ignored(entry)
+    }
+}
+
+/// Finds the Java files that make up the project rooted at `root`, leaving
+/// out what [ProjectFilter] skips.
+///
+/// * `root`: the project root
+/// * `options`: depth limit and include/exclude globs
+pub fn find_java_files(root: &Path,
+                       options: &DiscoveryOptions)
+                       -> Result<Vec<PathBuf>> {
+    let filter = ProjectFilter::new(root);
+
+    let compile = |globs: &[String]| {
+        globs.iter()
+             .map(|g| Pattern::new(g).with_context(|| format!("Invalid glob: {g}")))
+             .collect::<Result<Vec<_>>>()
+    };
+    let include = compile(&options.include)?;
+    let exclude = compile(&options.exclude)?;
+
+    let files = WalkDir::new(root).max_depth(options.max_depth + 1)
+                                  .into_iter()
+                                  .filter_entry(|e| !filter.skips(e))
+                                  .filter_map(|e| e.ok())
+                                  .filter(|e| e.file_type().is_file() && is_java_source(e.path()))
+                                  .filter(|e| {
+                                      let relative = relative_path(root, e.path());
+                                      (include.is_empty()
+                                       || include.iter()
+                                                 .any(|p| p.matches_with(&relative, MATCH_OPTIONS)))
+                                      && !exclude.iter()
+                                                 .any(|p| p.matches_with(&relative, MATCH_OPTIONS))
+                                  })
+                                  .map(|e| e.into_path())
+                                  .collect();
+
+    Ok(files)
+}
+
+/// Looks for naming problems among discovered files, and describes each one.
+///
+/// * Two files declaring the same fully qualified name, usually a copy a
+///   student made. Only one of them can be compiled.
+/// * Two classes with the same name in different packages, which can only be
+///   told apart by their fully qualified names.
+/// * A package declaration that doesn't match the folder the file is in, or a
+///   file without one that isn't directly in a source folder.
+///
+/// * `files`: the discovered files
+pub fn check_names(files: &[File]) -> Vec<DiscoveryWarning> {
+    let mut warnings = vec![];
+
+    let mut by_proper_name = BTreeMap::<String, Vec<&File>>::new();
+    let mut by_name = BTreeMap::<String, Vec<&File>>::new();
+    for file in files {
+        by_proper_name.entry(file.proper_name())
+                      .or_default()
+                      .push(file);
+        by_name.entry(file.file_name().replace(".java", ""))
+               .or_default()
+               .push(file);
+    }
+
+    for (proper_name, files) in by_proper_name.iter().filter(|(_, f)| f.len() > 1) {
+        let paths = files.iter().map(|f| f.path().clone()).collect::<Vec<_>>();
+        let message = format!("{} is declared in more than one file: {}",
+                              proper_name,
+                              paths.iter()
+                                   .map(|p| p.display().to_string())
+                                   .collect::<Vec<_>>()
+                                   .join(", "));
+        warnings.push(DiscoveryWarning { kind: WarningKind::DuplicateClass,
+                                         files: paths,
+                                         message });
+    }
+
+    for (name, files) in by_name.iter() {
+        let mut proper_names = files.iter().map(|f| f.proper_name()).collect::<Vec<_>>();
+        proper_names.sort();
+        proper_names.dedup();
+        if proper_names.len() > 1 {
+            let message = format!("{} is ambiguous, refer to it by its fully qualified name: {}",
+                                  name,
+                                  proper_names.join(", "));
+            warnings.push(DiscoveryWarning { kind: WarningKind::AmbiguousName,
+                                             files: files.iter()
+                                                         .map(|f| f.path().clone())
+                                                         .collect(),
+                                             message });
+        }
+    }
+
+    for file in files {
+        let folder = file.path().parent().unwrap_or(ROOT_DIR.as_path());
+        let message = match file.package_name() {
+            Some(package) => {
+                let expected = package.split('.').collect::<PathBuf>();
+                if folder.ends_with(&expected) {
+                    continue;
+                }
+                format!("{} declares package {}, but is not in a {} folder",
+                        file.path().display(),
+                        package,
+                        expected.display())
+            }
+            None => {
+                if [ROOT_DIR.as_path(), SOURCE_DIR.as_path(), TEST_DIR.as_path()].contains(&folder)
+                {
+                    continue;
+                }
+                format!("{} does not declare a package, but is not directly inside {}, {} or the \
+                         project root",
+                        file.path().display(),
+                        SOURCE_DIR.display(),
+                        TEST_DIR.display())
+            }
+        };
+        warnings.push(DiscoveryWarning { kind: WarningKind::PackageMismatch,
+                                         files: vec![file.path().clone()],
+                                         message });
+    }
+
+    warnings
+}
@@ -0,0 +1,330 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use anyhow::{bail, Result};
+
+use crate::constants::{BUILD_DIR, ROOT_DIR, TEST_DIR};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// An editor or IDE that `umm init` can generate project files for
+pub enum Editor {
+    /// `.vscode/settings.json`, `tasks.json`, `launch.json` and friends
+    VsCode,
+    /// `.idea/modules.xml`, `.idea/misc.xml` and a `.iml` module file
+    IntelliJ,
+    /// `.project` and `.classpath`
+    Eclipse,
+}
+
+impl Editor {
+    /// All supported editors
+    pub const ALL: [Editor; 3] = [Editor::VsCode, Editor::IntelliJ, Editor::Eclipse];
+}
+
+impl Display for Editor {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        let name = match self {
+            Editor::VsCode => "vscode",
+            Editor::IntelliJ => "intellij",
+            Editor::Eclipse => "eclipse",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for Editor {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match Editor::ALL.iter()
+                         .find(|e| e.to_string() == s.to_lowercase())
+        {
+            Some(editor) => Ok(*editor),
+            None => bail!("Unknown editor: {}. Expected one of: {}",
+                          s,
+                          Editor::ALL.map(|e| e.to_string()).join(", ")),
+        }
+    }
+}
+
+/// Escapes a string for use inside an XML attribute or text node
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+     .replace('<', "&lt;")
+     .replace('>', "&gt;")
+     .replace('"', "&quot;")
+}
+
+/// Returns `path` relative to the project root, with forward slashes and no
+/// trailing slash. The root itself is an empty string.
+fn relative(path: &Path) -> String {
+    let path = path.strip_prefix(ROOT_DIR.as_path()).unwrap_or(path);
+    path.display()
+        .to_string()
+        .replace('\\', "/")
+        .trim_end_matches('/')
+        .to_string()
+}
+
+/// Source folders, libraries and output folder of a project, relative to the
+/// project root, in the shape IDE project files want them.
+#[derive(Debug, Clone)]
+pub struct ProjectLayout {
+    /// name of the project, taken from the root folder
+    name:     String,
+    /// folders containing non-test sources
+    sources:  Vec<String>,
+    /// folders containing test sources
+    tests:    Vec<String>,
+    /// folders whose jar files are all on the classpath
+    jar_dirs: Vec<String>,
+    /// individual jar files on the classpath
+    jars:     Vec<String>,
+    /// folder compiled classes are written to
+    output:   String,
+}
+
+impl ProjectLayout {
+    /// Computes a layout from the sourcepath and classpath of a project.
+    /// Source folders that don't exist are left out, and wildcard classpath
+    /// entries like `lib/*.jar` are expanded.
+    ///
+    /// * `sourcepath`: sourcepath entries, as computed by `Project::new`
+    /// * `classpath`: classpath entries, as computed by `Project::new`
+    pub fn new(sourcepath: &[String],
+               classpath: &[String])
+               -> Self {
+        let name = ROOT_DIR.canonicalize()
+                           .ok()
+                           .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+                           .unwrap_or_else(|| String::from("project"));
+
+        let mut sources = vec![];
+        let mut tests = vec![];
+        for entry in sourcepath {
+            let path = Path::new(entry);
+            if !path.is_dir() {
+                continue;
+            }
+
+            if path == TEST_DIR.as_path() {
+                tests.push(relative(path));
+            } else {
+                sources.push(relative(path));
+            }
+        }
+
+        let mut jar_dirs = vec![];
+        let mut jars = vec![];
+        for entry in classpath {
+            if entry.contains('*') {
+                if let Some(parent) = Path::new(entry).parent() {
+                    jar_dirs.push(relative(parent));
+                }
+                if let Ok(paths) = glob::glob(entry) {
+                    jars.extend(paths.filter_map(|p| p.ok()).map(|p| relative(&p)));
+                }
+            } else {
+                jars.push(relative(Path::new(entry)));
+            }
+        }
+
+        Self { name,
+               sources,
+               tests,
+               jar_dirs,
+               jars,
+               output: relative(BUILD_DIR.as_path()) }
+    }
+
+    /// Name of the project
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Contents of the `.iml` module file for IntelliJ IDEA
+    pub fn intellij_module(&self) -> String {
+        /// Turns a root-relative path into a module-relative URL
+        fn url(path: &str) -> String {
+            if path.is_empty() {
+                String::from("file://$MODULE_DIR$")
+            } else {
+                format!("file://$MODULE_DIR$/{}", escape(path))
+            }
+        }
+
+        let mut folders = String::new();
+        for source in &self.sources {
+            folders.push_str(&format!("      <sourceFolder url=\"{}\" isTestSource=\"false\" \
+                                       />\n",
+                                      url(source)));
+        }
+        for test in &self.tests {
+            folders.push_str(&format!("      <sourceFolder url=\"{}\" isTestSource=\"true\" />\n",
+                                      url(test)));
+        }
+
+        let mut libraries = String::new();
+        for dir in &self.jar_dirs {
+            libraries.push_str(&format!(
+                r#"    <orderEntry type="module-library">
+      <library>
+        <CLASSES>
+          <root url="{0}" />
+        </CLASSES>
+        <JAVADOC />
+        <SOURCES />
+        <jarDirectory url="{0}" recursive="false" />
+      </library>
+    </orderEntry>
+"#,
+                url(dir)
+            ));
+        }
+        for jar in &self.jars {
+            if self.jar_dirs
+                   .iter()
+                   .any(|dir| Path::new(jar).parent() == Some(Path::new(dir)))
+            {
+                continue;
+            }
+            libraries.push_str(&format!(
+                r#"    <orderEntry type="module-library">
+      <library>
+        <CLASSES>
+          <root url="jar://$MODULE_DIR$/{}!/" />
+        </CLASSES>
+        <JAVADOC />
+        <SOURCES />
+      </library>
+    </orderEntry>
+"#,
+                escape(jar)
+            ));
+        }
+
+        format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<module type="JAVA_MODULE" version="4">
+  <component name="NewModuleRootManager" inherit-compiler-output="false">
+    <output url="{output}" />
+    <output-test url="{output}" />
+    <exclude-output />
+    <content url="file://$MODULE_DIR$">
+{folders}      <excludeFolder url="{output}" />
+    </content>
+    <orderEntry type="inheritedJdk" />
+    <orderEntry type="sourceFolder" forTests="false" />
+{libraries}  </component>
+</module>
+"#,
+                output = url(&self.output)
+        )
+    }
+
+    /// Contents of `.idea/modules.xml` for IntelliJ IDEA
+    pub fn intellij_modules(&self) -> String {
+        format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<project version="4">
+  <component name="ProjectModuleManager">
+    <modules>
+      <module fileurl="file://$PROJECT_DIR$/{0}.iml" filepath="$PROJECT_DIR$/{0}.iml" />
+    </modules>
+  </component>
+</project>
+"#,
+                escape(&self.name)
+        )
+    }
+
+    /// Contents of `.idea/misc.xml` for IntelliJ IDEA
+    pub fn intellij_misc(&self) -> String {
+        format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<project version="4">
+  <component name="ProjectRootManager" version="2" project-jdk-type="JavaSDK">
+    <output url="file://$PROJECT_DIR$/{}" />
+  </component>
+</project>
+"#,
+                escape(&self.output)
+        )
+    }
+
+    /// Contents of `.project` for Eclipse
+    pub fn eclipse_project(&self) -> String {
+        format!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>
+<projectDescription>
+  <name>{}</name>
+  <comment></comment>
+  <projects>
+  </projects>
+  <buildSpec>
+    <buildCommand>
+      <name>org.eclipse.jdt.core.javabuilder</name>
+      <arguments>
+      </arguments>
+    </buildCommand>
+  </buildSpec>
+  <natures>
+    <nature>org.eclipse.jdt.core.javanature</nature>
+  </natures>
+</projectDescription>
+"#,
+                escape(&self.name)
+        )
+    }
+
+    /// Contents of `.classpath` for Eclipse
+    pub fn eclipse_classpath(&self) -> String {
+        // Eclipse does not allow nested source folders, so when the root is a
+        // source folder everything else has to be excluded from it
+        let nested = self.sources
+                         .iter()
+                         .chain(self.tests.iter())
+                         .chain(self.jar_dirs.iter())
+                         .chain(std::iter::once(&self.output))
+                         .filter(|p| !p.is_empty())
+                         .map(|p| format!("{}/", escape(p)))
+                         .collect::<Vec<_>>()
+                         .join("|");
+
+        let mut entries = String::new();
+        for source in &self.sources {
+            if source.is_empty() {
+                entries.push_str(&format!("  <classpathentry kind=\"src\" path=\"\" \
+                                           excluding=\"{nested}\"/>\n"));
+            } else {
+                entries.push_str(&format!("  <classpathentry kind=\"src\" path=\"{}\"/>\n",
+                                          escape(source)));
+            }
+        }
+        for test in &self.tests {
+            entries.push_str(&format!(
+                r#"  <classpathentry kind="src" path="{}">
+    <attributes>
+      <attribute name="test" value="true"/>
+    </attributes>
+  </classpathentry>
+"#,
+                escape(test)
+            ));
+        }
+        entries.push_str("  <classpathentry kind=\"con\" \
+                          path=\"org.eclipse.jdt.launching.JRE_CONTAINER\"/>\n");
+        for jar in &self.jars {
+            entries.push_str(&format!("  <classpathentry kind=\"lib\" path=\"{}\"/>\n",
+                                      escape(jar)));
+        }
+        entries.push_str(&format!("  <classpathentry kind=\"output\" path=\"{}\"/>\n",
+                                  escape(&self.output)));
+
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<classpath>\n{entries}</classpath>\n")
+    }
+}
@@ -2,15 +2,16 @@
 #![warn(clippy::missing_docs_in_private_items)]
 
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     fmt::Display,
     fs,
     io::{BufRead, BufReader, Write},
     ops::RangeInclusive,
     process::Command,
+    sync::{Arc, Mutex},
 };
 
-use anyhow::{anyhow, ensure, Context, Result};
+use anyhow::{anyhow, bail, ensure, Context, Result};
 use async_openai::{
     config::OpenAIConfig,
     error::OpenAIError,
@@ -43,9 +44,15 @@ use crate::{
         POSTGREST_CLIENT, PROMPT_TRUNCATE, RETRIEVAL_MESSAGE_INTRO, ROOT_DIR, RUNTIME, SCRIPT_AST,
         SOURCE_DIR, SYNTAX_SLO, SYSTEM_MESSAGE, TESTING_SLO, USE_ACTIVE_RETRIEVAL,
     },
-    create_engine,
+    create_callback_engine,
     java::{File, FileType, JavaFileError, Parser, Project},
+    metrics::MetricsViolation,
     parsers::parser,
+    policy::late_policy,
+    signing::{attest, verify_download, write_attestation},
+    spec::{check_class, ApiCheck, ApiSpec, ClassSpec},
+    starter::{starter_code, Change, Starter},
+    style::{lint, StyleOptions, StyleRule},
     util::{classpath, java_path},
     Dict,
 };
@@ -242,6 +249,21 @@ impl JavacDiagnostic {
     pub fn file_name(&self) -> &str {
         self.file_name.as_ref()
     }
+
+    /// Returns the line number
+    pub fn line_number(&self) -> u32 {
+        self.line_number
+    }
+
+    /// Returns true if the diagnostic is an error, false if it is a warning
+    pub fn is_error(&self) -> bool {
+        self.is_error
+    }
+
+    /// Returns the diagnostic message
+    pub fn message(&self) -> &str {
+        self.message.as_ref()
+    }
 }
 
 impl From<JavacDiagnostic> for LineRef {
@@ -405,6 +427,73 @@ pub fn get_active_retrieval_context(proj: &Project,
                                                         .into())
 }
 
+/// Returns the source code of every method in `file` that is unchanged from
+/// the starter code, if starter code was registered.
+///
+/// * `starter`: the registered starter code, if any
+/// * `file`: the student's file
+fn unchanged_methods(starter: &Option<Starter>,
+                     file: &File)
+                     -> Vec<String> {
+    match starter {
+        Some(starter) => starter.method_changes(file)
+                                .into_iter()
+                                .filter(|m| m.change == Change::Unchanged)
+                                .map(|m| m.code)
+                                .collect(),
+        None => vec![],
+    }
+}
+
+/// Drops diagnostics about code in `file` that is unchanged from the starter
+/// code, if starter code was registered. That is every diagnostic if the whole
+/// file is unchanged, otherwise those in an unchanged method or its doc
+/// comment.
+///
+/// * `starter`: the registered starter code, if any
+/// * `file`: the student's file the diagnostics are about
+/// * `diags`: the diagnostics
+fn without_starter_code<T>(starter: &Option<Starter>,
+                           file: &File,
+                           diags: Vec<T>)
+                           -> Vec<T>
+    where T: Clone + Into<LineRef>
+{
+    let starter = match starter {
+        Some(s) => s,
+        None => return diags,
+    };
+    if starter.file_change(file) == Change::Unchanged {
+        return vec![];
+    }
+
+    let parser = file.parser();
+    let lines = parser.source().lines().collect::<Vec<_>>();
+    let unchanged = starter.method_changes(file)
+                           .into_iter()
+                           .filter(|m| m.change == Change::Unchanged)
+                           .map(|m| {
+                               let above = &lines[..m.start_line - 1];
+                               let doc_start = match above.last() {
+                                   Some(l) if l.trim_end().ends_with("*/") => {
+                                       above.iter()
+                                            .rposition(|l| l.contains("/*"))
+                                            .filter(|i| above[*i].trim_start().starts_with("/**"))
+                                   }
+                                   _ => None,
+                               };
+                               doc_start.map(|i| i + 1).unwrap_or(m.start_line)..=m.end_line
+                           })
+                           .collect::<Vec<RangeInclusive<usize>>>();
+
+    diags.into_iter()
+         .filter(|d| {
+             let line_number = d.clone().into().line_number;
+             !unchanged.iter().any(|r| r.contains(&line_number))
+         })
+         .collect()
+}
+
 /// Returns a ChatCompletionRequestMessage with the given line references that
 /// include contextual lines of code from the source
 ///
@@ -466,6 +555,7 @@ pub fn get_source_context<T: Into<LineRef>>(line_refs: Vec<T>,
     );
     let end_ticks = "\n```\n".to_string();
     let mut methods: HashSet<String> = HashSet::new();
+    let starter = starter_code();
 
     line_refs.into_iter()
              .coalesce(|lhs, rhs| {
@@ -544,9 +634,13 @@ pub fn get_source_context<T: Into<LineRef>>(line_refs: Vec<T>,
                                      let res = f.query(&query)
                                                 .or_else(|_| Ok::<Vec<Dict>, anyhow::Error>(vec![]))
                                                 .unwrap();
+                                     let unchanged = unchanged_methods(&starter, f);
 
                                      for r in res {
                                          let body = r.get("body").unwrap().to_string();
+                                         if unchanged.contains(&body) {
+                                             continue;
+                                         }
                                          let body_lines =
                                              body.lines().map(String::from).collect::<Vec<_>>();
                                          if body_lines.first().is_some() {
@@ -702,6 +796,7 @@ impl DocsGrader {
                 })
                 .try_collect()?;
         let out_of = self.out_of;
+        let starter = starter_code();
         let mut outputs = vec![];
         for name in &files {
             let file = self.project.identify(name)?;
@@ -748,18 +843,20 @@ impl DocsGrader {
                 }
             };
             outputs.push(output.clone());
+            let mut file_diags = vec![];
             for line in output.lines() {
                 let result = parser::parse_diag(line);
                 match result {
                     Ok(res) => {
                         if file.file_name() == res.file_name {
-                            diags.push(res.clone());
+                            file_diags.push(res.clone());
                         }
                         all_diags.push(res);
                     }
                     Err(_) => continue,
                 }
             }
+            diags.extend(without_starter_code(&starter, &file, file_diags));
         }
 
         let penalty = diags.len() as f64 * self.penalty;
@@ -823,6 +920,641 @@ impl DocsGrader {
     }
 }
 
+#[derive(Clone, Default)]
+/// What graders that deduct for every nit found in some files, like
+/// [StyleGrader] and [MetricsGrader], are set up with
+pub struct NitGraderBase {
+    /// * `project`: the project to grade
+    pub project:  Project,
+    /// * `files`: the files to grade
+    pub files:    Array,
+    /// * `out_of`: the total points for the requirement
+    pub out_of:   f64,
+    /// * `req_name`: the name of the requirement
+    pub req_name: String,
+}
+
+impl NitGraderBase {
+    /// The names in `files`
+    fn file_names(&self) -> Result<Vec<String>> {
+        self.files
+            .iter()
+            .map(|f| match f.clone().into_string() {
+                Ok(n) => Ok(n),
+                Err(e) => Err(anyhow!("files array has something that's not a string: {}", e)),
+            })
+            .try_collect()
+    }
+}
+
+/// Getters and setters for graders that deduct for every nit found in some
+/// files
+pub trait NitGrader: Sized {
+    /// What the grader is set up with
+    fn base(&mut self) -> &mut NitGraderBase;
+
+    /// Getter for project
+    fn project(&mut self) -> Project {
+        self.base().project.clone()
+    }
+
+    /// Setter for project
+    fn set_project(mut self,
+                   project: Project)
+                   -> Self {
+        self.base().project = project;
+        self
+    }
+
+    /// Getter for files
+    fn files(&mut self) -> Array {
+        self.base().files.clone()
+    }
+
+    /// Setter for files
+    fn set_files(mut self,
+                 files: Array)
+                 -> Self {
+        self.base().files = files;
+        self
+    }
+
+    /// Getter for out_of
+    fn out_of(&mut self) -> f64 {
+        self.base().out_of
+    }
+
+    /// Setter for out_of
+    fn set_out_of(mut self,
+                  out_of: f64)
+                  -> Self {
+        self.base().out_of = out_of;
+        self
+    }
+
+    /// Getter for req_name
+    fn req_name(&mut self) -> String {
+        self.base().req_name.clone()
+    }
+
+    /// Setter for req_name
+    fn set_req_name(mut self,
+                    req_name: String)
+                    -> Self {
+        self.base().req_name = req_name;
+        self
+    }
+}
+
+/// Prints a table of the nits a grader found, and deducts `penalty` for them.
+/// When there are any, the result has a prompt asking for help fixing them.
+///
+/// * `base`: what the grader is set up with
+/// * `title`: what was checked, for the table's heading, like `style`
+/// * `files`: names of the files checked
+/// * `diags`: the nits
+/// * `penalty`: the total deduction for them
+/// * `describe`: describes a nit to the model
+/// * `instructions`: the prompt explaining how to help with them
+fn grade_nits<T>(base: NitGraderBase,
+                 title: &str,
+                 files: &[String],
+                 diags: Vec<T>,
+                 penalty: f64,
+                 describe: impl Fn(&T) -> String,
+                 instructions: &str)
+                 -> Result<GradeResult>
+    where T: Tabled + Into<LineRef>
+{
+    let out_of = base.out_of;
+    let grade = if out_of - penalty > 0.0 {
+        out_of - penalty
+    } else {
+        0.0
+    };
+
+    let num_diags = diags.len();
+    eprintln!("{}",
+              diags.iter()
+                   .table()
+                   .with(Panel::header(format!("Check {title} for {}", files.join(", "))))
+                   .with(Panel::footer(format!("-{penalty} due to {num_diags} nits")))
+                   .with(Modify::new(Rows::new(1..)).with(Width::wrap(24).keep_words()))
+                   .with(Modify::new(Rows::first()).with(Alignment::center())
+                                                   .with(Alignment::center_vertical()),)
+                   .with(Modify::new(Rows::last()).with(Alignment::center())
+                                                  .with(Alignment::center_vertical()),)
+                   .with(tabled::Style::modern()));
+
+    let prompt = if num_diags > 0 {
+        let mut violations = diags.iter()
+                                  .map(describe)
+                                  .collect::<Vec<String>>()
+                                  .join("\n");
+
+        if violations.len() > PROMPT_TRUNCATE {
+            violations.truncate(PROMPT_TRUNCATE);
+            violations.push_str("...[TRUNCATED]");
+        }
+
+        let context = get_source_context(diags, base.project, 1, 3, 6, false, None)?;
+
+        Some(vec![
+            ChatCompletionRequestSystemMessageArgs::default().content(SYSTEM_MESSAGE.to_string())
+                                                             .name("Instructor".to_string())
+                                                             .build()?
+                                                             .into(),
+            ChatCompletionRequestUserMessageArgs::default().content(format!(
+                "```\n{violations}\n```"
+            ))
+                                                           .name("Student".to_string())
+                                                           .build()?
+                                                           .into(),
+            context,
+            ChatCompletionRequestSystemMessageArgs::default().content(instructions.to_string())
+                                                             .name("Instructor".to_string())
+                                                             .build()?
+                                                             .into(),
+        ])
+    } else {
+        None
+    };
+    Ok(GradeResult { requirement: base.req_name,
+                     grade: Grade::new(grade, out_of),
+                     reason: String::from("See above."),
+                     prompt })
+}
+
+#[derive(Clone, Default)]
+/// A struct representing arguments to grade_style function
+pub struct StyleGrader {
+    /// * `base`: the project, files, points and name of the requirement
+    pub base:    NitGraderBase,
+    /// * `rules`: the rules to check, along with the penalty for each violation
+    ///   and the maximum total penalty for the rule. If empty, all rules are
+    ///   checked with a penalty of 1, each capped at `out_of`
+    pub rules:   Vec<(StyleRule, f64, f64)>,
+    /// * `options`: thresholds for the method length and nesting depth rules
+    pub options: StyleOptions,
+}
+
+impl NitGrader for StyleGrader {
+    fn base(&mut self) -> &mut NitGraderBase {
+        &mut self.base
+    }
+}
+
+impl StyleGrader {
+    /// Getter for the names of enabled rules
+    pub fn rules(&mut self) -> Array {
+        self.rules
+            .iter()
+            .map(|(rule, _, _)| rule.to_string().into())
+            .collect()
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Enables a rule.
+    ///
+    /// * `rule`: name of the rule, e.g. "naming" or "magic_numbers"
+    /// * `penalty`: the penalty to apply for each violation of the rule
+    /// * `cap`: the maximum total penalty for the rule
+    pub fn rule(#[allow(unused_mut)] mut self,
+                rule: String,
+                penalty: f64,
+                cap: f64)
+                -> Result<Self> {
+        let rule = rule.parse::<StyleRule>()?;
+        self.rules.retain(|(r, _, _)| *r != rule);
+        self.rules.push((rule, penalty, cap));
+        Ok(self)
+    }
+
+    /// Getter for max_method_length
+    pub fn max_method_length(&mut self) -> i64 {
+        self.options.max_method_length as i64
+    }
+
+    /// Setter for max_method_length
+    pub fn set_max_method_length(mut self,
+                                 max_method_length: i64)
+                                 -> Self {
+        self.options.max_method_length = max_method_length.max(0) as usize;
+        self
+    }
+
+    /// Getter for max_nesting_depth
+    pub fn max_nesting_depth(&mut self) -> i64 {
+        self.options.max_nesting_depth as i64
+    }
+
+    /// Setter for max_nesting_depth
+    pub fn set_max_nesting_depth(mut self,
+                                 max_nesting_depth: i64)
+                                 -> Self {
+        self.options.max_nesting_depth = max_nesting_depth.max(0) as usize;
+        self
+    }
+
+    /// Grades style by linting files with the enabled built-in rules.
+    /// Each rule deducts its penalty per violation, up to its cap.
+    #[generate_rhai_variant(Fallible)]
+    pub fn grade_style(self) -> Result<GradeResult> {
+        let files = self.base.file_names()?;
+        let out_of = self.base.out_of;
+        let rules = if self.rules.is_empty() {
+            StyleRule::ALL.iter().map(|r| (*r, 1.0, out_of)).collect()
+        } else {
+            self.rules.clone()
+        };
+        let enabled: Vec<StyleRule> = rules.iter().map(|(r, _, _)| *r).collect();
+
+        let starter = starter_code();
+        let mut diags = vec![];
+        for name in &files {
+            let file = self.base.project.identify(name)?;
+            let file_diags = lint(&file, &enabled, self.options)?;
+            diags.extend(without_starter_code(&starter, &file, file_diags));
+        }
+
+        let penalty: f64 = rules.iter()
+                                .map(|(rule, penalty, cap)| {
+                                    let count = diags.iter().filter(|d| d.rule == *rule).count();
+                                    (count as f64 * penalty).min(*cap)
+                                })
+                                .sum();
+
+        grade_nits(self.base,
+                   "style",
+                   &files,
+                   diags,
+                   penalty,
+                   |d| format!("{}:{} [{}] {}", d.path, d.line_number, d.rule, d.message),
+                   include_str!("prompts/style.md"))
+    }
+}
+
+#[derive(Clone)]
+/// A struct representing arguments to grade_metrics function
+pub struct MetricsGrader {
+    /// * `base`: the project, files, points and name of the requirement
+    pub base:              NitGraderBase,
+    /// * `penalty`: the penalty to apply for each metric over its threshold.
+    ///   Optional, default is 1
+    pub penalty:           f64,
+    /// * `max_complexity`: maximum cyclomatic complexity of a method. Optional,
+    ///   default is 10
+    pub max_complexity:    usize,
+    /// * `max_loc`: maximum lines of code in a method. Optional, default is 30
+    pub max_loc:           usize,
+    /// * `max_parameters`: maximum number of parameters of a method. Optional,
+    ///   default is 5
+    pub max_parameters:    usize,
+    /// * `max_nesting_depth`: maximum nesting depth inside a method. Optional,
+    ///   default is 3
+    pub max_nesting_depth: usize,
+    /// * `max_fan_out`: maximum number of types a class refers to. Optional,
+    ///   default is 10
+    pub max_fan_out:       usize,
+}
+
+impl Default for MetricsGrader {
+    fn default() -> Self {
+        Self { base:              NitGraderBase::default(),
+               penalty:           1.0,
+               max_complexity:    10,
+               max_loc:           30,
+               max_parameters:    5,
+               max_nesting_depth: 3,
+               max_fan_out:       10, }
+    }
+}
+
+impl NitGrader for MetricsGrader {
+    fn base(&mut self) -> &mut NitGraderBase {
+        &mut self.base
+    }
+}
+
+impl MetricsGrader {
+    /// Getter for penalty
+    pub fn penalty(&mut self) -> f64 {
+        self.penalty
+    }
+
+    /// Setter for penalty
+    pub fn set_penalty(mut self,
+                       penalty: f64)
+                       -> Self {
+        self.penalty = penalty;
+        self
+    }
+
+    /// Getter for max_complexity
+    pub fn max_complexity(&mut self) -> i64 {
+        self.max_complexity as i64
+    }
+
+    /// Setter for max_complexity
+    pub fn set_max_complexity(mut self,
+                              max_complexity: i64)
+                              -> Self {
+        self.max_complexity = max_complexity.max(0) as usize;
+        self
+    }
+
+    /// Getter for max_loc
+    pub fn max_loc(&mut self) -> i64 {
+        self.max_loc as i64
+    }
+
+    /// Setter for max_loc
+    pub fn set_max_loc(mut self,
+                       max_loc: i64)
+                       -> Self {
+        self.max_loc = max_loc.max(0) as usize;
+        self
+    }
+
+    /// Getter for max_parameters
+    pub fn max_parameters(&mut self) -> i64 {
+        self.max_parameters as i64
+    }
+
+    /// Setter for max_parameters
+    pub fn set_max_parameters(mut self,
+                              max_parameters: i64)
+                              -> Self {
+        self.max_parameters = max_parameters.max(0) as usize;
+        self
+    }
+
+    /// Getter for max_nesting_depth
+    pub fn max_nesting_depth(&mut self) -> i64 {
+        self.max_nesting_depth as i64
+    }
+
+    /// Setter for max_nesting_depth
+    pub fn set_max_nesting_depth(mut self,
+                                 max_nesting_depth: i64)
+                                 -> Self {
+        self.max_nesting_depth = max_nesting_depth.max(0) as usize;
+        self
+    }
+
+    /// Getter for max_fan_out
+    pub fn max_fan_out(&mut self) -> i64 {
+        self.max_fan_out as i64
+    }
+
+    /// Setter for max_fan_out
+    pub fn set_max_fan_out(mut self,
+                           max_fan_out: i64)
+                           -> Self {
+        self.max_fan_out = max_fan_out.max(0) as usize;
+        self
+    }
+
+    /// Grades code metrics against thresholds.
+    /// Deducts the penalty for every metric that exceeds its threshold.
+    #[generate_rhai_variant(Fallible)]
+    pub fn grade_metrics(self) -> Result<GradeResult> {
+        let files = self.base.file_names()?;
+
+        let starter = starter_code();
+        let mut diags = vec![];
+        for name in &files {
+            let file = self.base.project.identify(name)?;
+            let mut file_diags = vec![];
+            let violation =
+                |line_number: usize, unit: &str, metric: &str, value: usize, threshold: usize| {
+                    MetricsViolation { path: file.path().display().to_string(),
+                                       file_name: file.file_name().to_string(),
+                                       line_number,
+                                       unit: unit.to_string(),
+                                       metric: metric.to_string(),
+                                       value,
+                                       threshold }
+                };
+
+            for class in file.metrics() {
+                if class.fan_out > self.max_fan_out {
+                    file_diags.push(violation(class.line,
+                                              &class.name,
+                                              "fan-out",
+                                              class.fan_out,
+                                              self.max_fan_out));
+                }
+
+                for method in &class.methods {
+                    let checks = [("cyclomatic complexity",
+                                   method.cyclomatic_complexity,
+                                   self.max_complexity),
+                                  ("lines of code", method.loc, self.max_loc),
+                                  ("parameters", method.parameters, self.max_parameters),
+                                  ("nesting depth", method.nesting_depth, self.max_nesting_depth)];
+
+                    for (metric, value, threshold) in checks {
+                        if value > threshold {
+                            file_diags.push(violation(method.line,
+                                                      &format!("{}#{}", class.name, method.name),
+                                                      metric,
+                                                      value,
+                                                      threshold));
+                        }
+                    }
+                }
+            }
+            diags.extend(without_starter_code(&starter, &file, file_diags));
+        }
+
+        let penalty = diags.len() as f64 * self.penalty;
+        grade_nits(self.base,
+                   "metrics",
+                   &files,
+                   diags,
+                   penalty,
+                   |d| {
+                       format!("{}:{} {} has {} {}, maximum is {}",
+                               d.path, d.line_number, d.unit, d.metric, d.value, d.threshold)
+                   },
+                   include_str!("prompts/metrics.md"))
+    }
+}
+
+#[derive(Clone, Default)]
+/// Grades the structure of classes and interfaces against an API spec.
+/// Each expected declaration, field, constructor, and method is worth an equal
+/// share of the grade.
+pub struct ApiGrader {
+    /// * `project`: the project to grade
+    pub project:  Project,
+    /// * `spec`: the expected classes and interfaces
+    pub spec:     ApiSpec,
+    /// * `out_of`: the total points for the requirement
+    pub out_of:   f64,
+    /// * `req_name`: the name of the requirement
+    pub req_name: String,
+}
+
+impl ApiGrader {
+    /// Getter for project
+    pub fn project(&mut self) -> Project {
+        self.project.clone()
+    }
+
+    /// Setter for project
+    pub fn set_project(mut self,
+                       project: Project)
+                       -> Self {
+        self.project = project;
+        self
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Getter for spec, as an object map
+    pub fn spec(&mut self) -> Result<Dynamic> {
+        rhai::serde::to_dynamic(&self.spec).map_err(|e| anyhow!("Could not convert spec: {e}"))
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Setter for spec, from an object map with a `classes` array
+    pub fn set_spec(#[allow(unused_mut)] mut self,
+                    spec: rhai::Map)
+                    -> Result<Self> {
+        self.spec =
+            rhai::serde::from_dynamic(&spec.into()).map_err(|e| anyhow!("Invalid API spec: {e}"))?;
+        Ok(self)
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Setter for spec, from a JSON file
+    pub fn set_spec_file(#[allow(unused_mut)] mut self,
+                         path: String)
+                         -> Result<Self> {
+        self.spec = ApiSpec::from_file(&ROOT_DIR.join(path))?;
+        Ok(self)
+    }
+
+    /// Getter for out_of
+    pub fn out_of(&mut self) -> f64 {
+        self.out_of
+    }
+
+    /// Setter for out_of
+    pub fn set_out_of(mut self,
+                      out_of: f64)
+                      -> Self {
+        self.out_of = out_of;
+        self
+    }
+
+    /// Getter for req_name
+    pub fn req_name(&mut self) -> String {
+        self.req_name.clone()
+    }
+
+    /// Setter for req_name
+    pub fn set_req_name(mut self,
+                        req_name: String)
+                        -> Self {
+        self.req_name = req_name;
+        self
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Checks each class in the spec against the project, and awards partial
+    /// credit for each expectation met.
+    pub fn grade_api(self) -> Result<GradeResult> {
+        let mut checks = vec![];
+        for expected in &self.spec.classes {
+            // Only a missing class fails its checks, an ambiguous name is an error
+            let found = if self.project.contains(&expected.name) {
+                Some(ClassSpec::from_file(&self.project.identify(&expected.name)?)?)
+            } else {
+                None
+            };
+            checks.extend(check_class(expected, found.as_ref()));
+        }
+
+        let out_of = self.out_of;
+        let num_checks = checks.len();
+        let failed: Vec<ApiCheck> = checks.into_iter().filter(|c| !c.passed).collect();
+        let num_failed = failed.len();
+        let grade = if num_checks == 0 {
+            out_of
+        } else {
+            out_of * (num_checks - num_failed) as f64 / num_checks as f64
+        };
+
+        eprintln!("{}",
+                  failed.iter()
+                        .table()
+                        .with(Panel::header(format!("Check API for {}",
+                                                    self.spec
+                                                        .classes
+                                                        .iter()
+                                                        .map(|c| c.name.clone())
+                                                        .collect::<Vec<String>>()
+                                                        .join(", "))))
+                        .with(Panel::footer(format!("{num_failed} of {num_checks} expectations \
+                                                     not met")))
+                        .with(Modify::new(Rows::new(1..)).with(Width::wrap(24).keep_words()))
+                        .with(Modify::new(Rows::first()).with(Alignment::center())
+                                                        .with(Alignment::center_vertical()),)
+                        .with(Modify::new(Rows::last()).with(Alignment::center())
+                                                       .with(Alignment::center_vertical()),)
+                        .with(tabled::Style::modern()));
+
+        let prompt = if num_failed > 0 {
+            let mut mismatches = failed.iter()
+                                       .map(|c| {
+                                           format!("{}: {}\n  expected: {}\n  found: {}",
+                                                   c.class, c.member, c.expected, c.found)
+                                       })
+                                       .collect::<Vec<String>>()
+                                       .join("\n");
+
+            if mismatches.len() > PROMPT_TRUNCATE {
+                mismatches.truncate(PROMPT_TRUNCATE);
+                mismatches.push_str("...[TRUNCATED]");
+            }
+
+            Some(vec![
+                ChatCompletionRequestSystemMessageArgs::default().content(
+                    SYSTEM_MESSAGE.to_string(),
+                )
+                                                                 .name("Instructor".to_string())
+                                                                 .build()?
+                                                                 .into(),
+                ChatCompletionRequestUserMessageArgs::default().content(format!(
+                    "```\n{mismatches}\n```"
+                ))
+                                                               .name("Student".to_string())
+                                                               .build()?
+                                                               .into(),
+                ChatCompletionRequestSystemMessageArgs::default().content(self.project.describe())
+                                                                 .name("Instructor".to_string())
+                                                                 .build()?
+                                                                 .into(),
+                ChatCompletionRequestSystemMessageArgs::default().content(include_str!(
+                    "prompts/api.md"
+                ).to_string())
+                                                                 .name("Instructor".to_string())
+                                                                 .build()?
+                                                                 .into(),
+            ])
+        } else {
+            None
+        };
+
+        Ok(GradeResult { requirement: self.req_name,
+                         grade: Grade::new(grade, out_of),
+                         reason: String::from("See above."),
+                         prompt })
+    }
+}
+
 #[derive(Clone, Default)]
 /// Grades by running tests, and reports how many tests pass.
 /// Final grade is the same percentage of maximum grade as the number of tests
@@ -1506,6 +2238,7 @@ impl ByHiddenTestGrader {
                                         .bytes()
                                         .context(format!("Failed to get response as bytes: \
                                                           {url}"))?;
+        verify_download(&url, &test_source)?;
 
         let path = ROOT_DIR.join(format!("{test_class_name}.java"));
         let mut file = fs::File::create(&path)?;
@@ -1557,7 +2290,7 @@ pub enum GradescopeOutputFormat {
 }
 
 /// Represents visibility settings for Gradescope submissions and test cases.
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum GradescopeVisibility {
     /// Hidden from students.
@@ -1848,6 +2581,7 @@ async fn generate_slo_responses(
                     ("slo_testing", "Testing", TESTING_SLO.as_str(), SLOFileType::Test),];
 
     let mut slo_requests = Vec::new();
+    let starter = starter_code();
 
     for (slo_key, slo_name, slo_system_message, slo_file_type) in slos {
         if !enabled_slos.contains(slo_key) {
@@ -1867,8 +2601,19 @@ async fn generate_slo_responses(
                                                       .collect(),
         };
 
-        let relevant_file_codes: Vec<String> =
-            relevant_files.iter().map(|x| x.parser().code()).collect();
+        let (relevant_files, relevant_file_codes): (Vec<File>, Vec<String>) = match &starter {
+            Some(starter) => {
+                relevant_files.into_iter()
+                              .filter_map(|x| starter.student_written(&x).map(|code| (x, code)))
+                              .unzip()
+            }
+            None => relevant_files.into_iter()
+                                  .map(|x| {
+                                      let code = x.parser().code();
+                                      (x, code)
+                                  })
+                                  .unzip(),
+        };
 
         ensure!(!relevant_file_codes.is_empty(),
                 "No relevant files ({:?}) with source code found for SLO {}",
@@ -1879,7 +2624,12 @@ async fn generate_slo_responses(
             vec![format!("# Submission for {project_title}\n\nDescription: {project_description}")];
 
         for (file, code) in relevant_files.iter().zip(relevant_file_codes.iter()) {
-            student_message.push(format!("\n\n## Contents of {file_name}\n\n```java\n{code}\n```",
+            let heading = if starter.is_some() {
+                "Code written by the student in"
+            } else {
+                "Contents of"
+            };
+            student_message.push(format!("\n\n## {heading} {file_name}\n\n```java\n{code}\n```",
                                          file_name = file.proper_name(),
                                          code = code));
         }
@@ -1956,6 +2706,10 @@ async fn generate_slo_responses(
 ///       relevant SLO feedback. Defaults to false.
 ///     - `debug`: whether to write gradescope JSON within the current
 ///       directory. Defaults to false.
+///     - `visibility`: map from requirement names to when their results are
+///       shown to students, one of "hidden", "after_due_date",
+///       "after_published" or "visible". Requirements not in it follow the
+///       submission, which is always visible.
 ///     - `slo_algorithmic_solutions`: whether to provide feedback on
 ///       Algorithmic Solutions SLO. Defaults to false.
 ///     - `slo_code_readability`: whether to provide feedback on Code
@@ -2019,6 +2773,11 @@ pub fn show_result(results: Array,
     let gradescope_json = get_or_default("results_json", false);
     let gradescope_feedback = get_or_default("feedback", false);
     let gradescope_debug = get_or_default("debug", false);
+    let mut visibility = match gradescope_config.get("visibility") {
+        Some(v) => rhai::serde::from_dynamic::<HashMap<String, GradescopeVisibility>>(v)
+            .map_err(|e| anyhow!("Invalid visibility: {e}"))?,
+        None => HashMap::new(),
+    };
 
     let enabled_slos: HashSet<String> =
         vec!["slo_algorithmic_solutions",
@@ -2034,24 +2793,33 @@ pub fn show_result(results: Array,
                             .map(String::from)
                             .collect();
 
-    let (grade, out_of) = results.iter().fold((0f64, 0f64), |acc, r| {
-                                            (acc.0 + r.grade.grade, acc.1 + r.grade.out_of)
-                                        });
+    let late_penalty = match late_policy() {
+        Some(policy) => policy.penalty(&results)?,
+        None => None,
+    };
+    let mut reported = results.clone();
+    reported.extend(late_penalty.clone());
+
+    let (grade, out_of) = reported.iter().fold((0f64, 0f64), |acc, r| {
+                                             (acc.0 + r.grade.grade, acc.1 + r.grade.out_of)
+                                         });
 
     if show_table {
         eprintln!("{}",
-                  results.clone()
-                         .table()
-                         .with(Panel::header("Grading Overview"))
-                         .with(Panel::footer(format!("Total: {grade:.2}/{out_of:.2}")))
-                         .with(Modify::new(Rows::new(1..)).with(Width::wrap(24).keep_words()))
-                         .with(Modify::new(Rows::first()).with(Alignment::center())
+                  reported.clone()
+                          .table()
+                          .with(Panel::header("Grading Overview"))
+                          .with(Panel::footer(format!("Total: {grade:.2}/{out_of:.2}")))
+                          .with(Modify::new(Rows::new(1..)).with(Width::wrap(24).keep_words()))
+                          .with(Modify::new(Rows::first()).with(Alignment::center())
+                                                          .with(Alignment::center_vertical()),)
+                          .with(Modify::new(Rows::last()).with(Alignment::center())
                                                          .with(Alignment::center_vertical()),)
-                         .with(Modify::new(Rows::last()).with(Alignment::center())
-                                                        .with(Alignment::center_vertical()),)
-                         .with(tabled::Style::modern()));
+                          .with(tabled::Style::modern()));
     }
 
+    let attestation = attest(&reported)?;
+
     if gradescope_json {
         let project = Project::new()?;
         let mut test_cases = vec![];
@@ -2064,6 +2832,7 @@ pub fn show_result(results: Array,
                 String::new()
             };
 
+            let shown = visibility.remove(&result.requirement());
             let test_case = GradescopeTestCase::builder().name(result.requirement())
                                                          .name_format(GradescopeOutputFormat::Text)
                                                          .max_score(result.out_of())
@@ -2078,11 +2847,23 @@ pub fn show_result(results: Array,
                                                                  })
                                                          .output(feedback)
                                                          .output_format(GradescopeOutputFormat::Md)
+                                                         .visibility(shown)
                                                          .build();
 
             test_cases.push(test_case);
         }
 
+        // The deduction explains itself, rather than asking for feedback
+        if let Some(mut penalty) = late_penalty {
+            test_cases.push(GradescopeTestCase::builder().name(penalty.requirement())
+                                                         .name_format(GradescopeOutputFormat::Text)
+                                                         .max_score(0f64)
+                                                         .score(penalty.grade())
+                                                         .output(penalty.reason())
+                                                         .output_format(GradescopeOutputFormat::Text)
+                                                         .build());
+        }
+
         if grade > pass_threshold * out_of && !enabled_slos.is_empty() {
             let runtime = RUNTIME.handle().clone();
 
@@ -2113,12 +2894,14 @@ pub fn show_result(results: Array,
                                                          .score(0f64)
                                                          .build());
         }
+        let extra_data = attestation.map(|a| serde_json::json!({ "attestation": a }));
         let submission =
             GradescopeSubmission::builder().tests(Some(test_cases))
                                            .test_output_format(GradescopeOutputFormat::Md)
                                            .test_name_format(GradescopeOutputFormat::Text)
                                            .stdout_visibility(GradescopeVisibility::Visible)
                                            .visibility(GradescopeVisibility::Visible)
+                                           .extra_data(extra_data)
                                            .build();
 
         let mut file = fs::File::create(if gradescope_debug {
@@ -2127,6 +2910,10 @@ pub fn show_result(results: Array,
                                             "/autograder/results/results.json"
                                         })?;
         file.write_all(serde_json::to_string_pretty(&submission)?.as_bytes())?;
+    } else if let Some(attestation) = attestation {
+        let path = write_attestation(&attestation)?;
+        eprintln!("Wrote a signed attestation of these results to {}",
+                  path.display());
     }
 
     Ok(())
@@ -2435,6 +3222,257 @@ impl DiffGrader {
     }
 }
 
+#[derive(Clone, Default)]
+/// What a [`CustomGrader`]'s logic gets to work with: the project, helpers
+/// to run, test and query its files, and a place to leave feedback
+pub struct GraderContext {
+    /// the project being graded
+    project:  Project,
+    /// feedback left so far, shared between clones passed to the script
+    feedback: Arc<Mutex<Vec<String>>>,
+}
+
+impl GraderContext {
+    /// creates a context for grading `project`
+    pub fn new(project: Project) -> Self {
+        Self { project,
+               feedback: Arc::new(Mutex::new(vec![])) }
+    }
+
+    /// gets the project being graded
+    pub fn project(&mut self) -> Project {
+        self.project.clone()
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Finds a file in the project by class name
+    ///
+    /// * `name`: name of the class, or its fully qualified name
+    pub fn file(&mut self,
+                name: String)
+                -> Result<File> {
+        self.project.identify(&name)
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Runs a file with some input, and returns its output. Fails if it
+    /// doesn't compile, or exits with an error.
+    ///
+    /// * `name`: name of the class to run
+    /// * `input`: what to write to its standard input
+    pub fn run(&mut self,
+               name: String,
+               input: String)
+               -> Result<String> {
+        let file = self.project.identify(&name)?;
+        Ok(file.run(Some(input))?)
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Runs some of a test file's JUnit tests, or all of them when `tests` is
+    /// empty, and returns JUnit's output
+    ///
+    /// * `name`: name of the test class
+    /// * `tests`: names of the tests to run
+    pub fn test(&mut self,
+                name: String,
+                tests: Array)
+                -> Result<String> {
+        let file = self.project.identify(&name)?;
+        let tests = tests.into_iter()
+                         .map(|t| t.cast::<String>())
+                         .collect::<Vec<_>>();
+        Ok(file.test(tests.iter().map(|t| t.as_str()).collect(),
+                     Some(&self.project))?)
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Runs a tree-sitter query on a file, and returns what it captured
+    ///
+    /// * `name`: name of the class to query
+    /// * `query`: the query to run
+    pub fn query(&mut self,
+                 name: String,
+                 query: String)
+                 -> Result<Array> {
+        let file = self.project.identify(&name)?;
+        Ok(file.query(&query)?.into_iter().map(|d| d.into()).collect())
+    }
+
+    /// Leaves feedback for the student, which is shown after grading and
+    /// used to prompt for help when the requirement doesn't get full marks
+    ///
+    /// * `feedback`: what went wrong
+    pub fn add_feedback(&mut self,
+                        feedback: String) {
+        self.feedback.lock().unwrap().push(feedback);
+    }
+
+    /// gets the feedback left so far
+    pub fn feedback(&mut self) -> Array {
+        self.feedback
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|f| f.into())
+            .collect()
+    }
+}
+
+#[derive(Clone, Default)]
+/// A grader whose logic is a closure written in the grading script. The
+/// closure is called with a [`GraderContext`], and returns the points earned,
+/// a map like `#{ grade: 8.0, reason: "..." }`, or a `GradeResult`.
+pub struct CustomGrader {
+    /// name of requirement
+    pub req_name: String,
+    /// points possible
+    pub out_of:   f64,
+    /// the project to grade
+    pub project:  Project,
+    /// the closure that grades the project
+    pub logic:    Option<FnPtr>,
+}
+
+impl CustomGrader {
+    /// gets the `req_name` field
+    pub fn req_name(&mut self) -> String {
+        self.req_name.clone()
+    }
+
+    /// sets the `req_name` field
+    pub fn set_req_name(mut self,
+                        req_name: String)
+                        -> Self {
+        self.req_name = req_name;
+        self
+    }
+
+    /// gets the `out_of` field
+    pub fn out_of(&mut self) -> f64 {
+        self.out_of
+    }
+
+    /// sets the `out_of` field
+    pub fn set_out_of(mut self,
+                      out_of: f64)
+                      -> Self {
+        self.out_of = out_of;
+        self
+    }
+
+    /// gets the `project` field
+    pub fn project(&mut self) -> Project {
+        self.project.clone()
+    }
+
+    /// sets the `project` field
+    pub fn set_project(mut self,
+                       project: Project)
+                       -> Self {
+        self.project = project;
+        self
+    }
+
+    /// sets the `logic` field
+    pub fn set_logic(mut self,
+                     logic: FnPtr)
+                     -> Self {
+        self.logic = Some(logic);
+        self
+    }
+
+    /// Reads the points earned and the reason for them from what the logic
+    /// returned
+    ///
+    /// * `value`: the value the closure returned
+    fn outcome(&self,
+               value: Dynamic)
+               -> Result<(f64, Option<String>)> {
+        if value.is::<GradeResult>() {
+            let mut result = value.cast::<GradeResult>();
+            return Ok((result.grade(), Some(result.reason())));
+        }
+        if let Ok(grade) = value.as_float() {
+            return Ok((grade, None));
+        }
+        if let Ok(grade) = value.as_int() {
+            return Ok((grade as f64, None));
+        }
+        if let Some(map) = value.read_lock::<rhai::Map>() {
+            let grade = map.get("grade")
+                           .and_then(|g| g.as_float().ok().or(g.as_int().ok().map(|i| i as f64)))
+                           .context("The map a custom grader returns needs a numeric `grade`")?;
+            let reason = map.get("reason").map(|r| r.to_string());
+            return Ok((grade, reason));
+        }
+
+        bail!("A custom grader must return a number, a map with a `grade`, or a GradeResult, but \
+               returned a {}",
+              value.type_name())
+    }
+
+    #[generate_rhai_variant(Fallible)]
+    /// Grades by calling `logic` with a [`GraderContext`] for the project.
+    /// Errors thrown while it runs, such as from running a file that doesn't
+    /// compile, give no points and are passed on as feedback.
+    pub fn grade_custom(&mut self) -> Result<GradeResult> {
+        let logic =
+            self.logic
+                .clone()
+                .context("A custom grader needs logic to run, set it with `.logic(|ctx| ...)`")?;
+
+        let engine = create_callback_engine();
+        // Cloned so the logic can run graders that lock it too
+        let ast = SCRIPT_AST.lock().unwrap().clone();
+
+        let mut context = GraderContext::new(self.project.clone());
+        let (grade, reason) = match logic.call::<Dynamic>(&engine, &ast, (context.clone(),)) {
+            Ok(value) => self.outcome(value)?,
+            Err(e) => {
+                context.add_feedback(format!("Error while grading -\n```\n{e}\n```"));
+                (0.0, Some("Error while grading.".to_string()))
+            }
+        };
+        let grade = grade.max(0.0).min(self.out_of);
+
+        let feedback = context.feedback.lock().unwrap().clone();
+        for f in &feedback {
+            eprintln!("{f}");
+        }
+
+        let reason = reason.unwrap_or_else(|| {
+                               if grade < self.out_of {
+                                   "See above.".to_string()
+                               } else {
+                                   "All checks passed.".to_string()
+                               }
+                           });
+
+        let prompt = if grade < self.out_of && !feedback.is_empty() {
+            Some(vec![ChatCompletionRequestSystemMessageArgs::default()
+                        .content(SYSTEM_MESSAGE.to_string())
+                        .name("Instructor".to_string())
+                        .build()
+                        .context("Failed to build system message")?
+                        .into(),
+                      ChatCompletionRequestUserMessageArgs::default()
+                        .content(feedback.join("\n\n"))
+                        .name("Student".to_string())
+                        .build()
+                        .context("Failed to build user message")?
+                        .into(),])
+        } else {
+            None
+        };
+
+        Ok(GradeResult { requirement: self.req_name.clone(),
+                         grade: Grade::new(grade, self.out_of),
+                         reason,
+                         prompt })
+    }
+}
+
 /// Schema for `prompts` table
 #[derive(Serialize, Debug)]
 pub struct PromptRow {
@@ -2625,19 +3663,21 @@ pub enum QueryConstraint {
 /// A struct to represent a query grader.
 pub struct QueryGrader {
     /// The name of the requirement.
-    req_name:   String,
+    req_name:              String,
     /// The grade for the requirement.
-    out_of:     f64,
+    out_of:                f64,
     /// The queries to run.
-    queries:    Vec<Query>,
+    queries:               Vec<Query>,
     /// The input to run the queries on.
-    project:    Project,
+    project:               Project,
     /// The file to run the query on.
-    file:       String,
+    file:                  String,
     /// The constraint of the query.
-    constraint: QueryConstraint,
+    constraint:            QueryConstraint,
     /// The reason to share with the student.
-    reason:     String,
+    reason:                String,
+    /// Whether to only match within methods the student added or modified.
+    only_modified_methods: bool,
 }
 
 impl QueryGrader {
@@ -2728,6 +3768,14 @@ impl QueryGrader {
         self
     }
 
+    /// Restricts matching to methods the student added or modified, ignoring
+    /// methods that are unchanged from the starter code. Requires starter code
+    /// to be registered with `use_starter_code`.
+    pub fn only_modified_methods(mut self) -> Self {
+        self.only_modified_methods = true;
+        self
+    }
+
     /// Gets the reason to share with the student.
     pub fn reason(&self) -> &str {
         &self.reason
@@ -2946,7 +3994,7 @@ impl QueryGrader {
     /// out the irrelevant line ranges. This performs better but more
     /// importantly is more accurate.
     pub fn run_query(&self) -> Result<Dynamic, QueryError> {
-        let engine = create_engine();
+        let engine = create_callback_engine();
         let ast = std::sync::Arc::clone(&SCRIPT_AST);
         let ast = ast.lock().unwrap();
 
@@ -2959,7 +4007,16 @@ impl QueryGrader {
                        .identify(self.file())
                        .map_err(|_| QueryError::FileNotFound(self.file().to_string()))?;
 
-        let mut matches: Vec<String> = match file.query(&first.query()) {
+        let parser = if self.only_modified_methods {
+            let starter = starter_code().context("Starter code must be registered with \
+                                                  `use_starter_code` to only match modified \
+                                                  methods")?;
+            Parser::new(starter.student_code(&file))?
+        } else {
+            file.parser()
+        };
+
+        let mut matches: Vec<String> = match parser.query(&first.query()) {
             Ok(m) => {
                 if first.capture().is_empty() {
                     return Err(QueryError::NoCaptureSelected(format!("{:#?}", first)));
@@ -3219,12 +4276,15 @@ impl QueryGrader {
 impl CustomType for Grade {
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("Grade")
+               .with_comments(&["/// A grade, as points earned out of points possible"])
                .with_fn("grade", Self::grade)
                .with_fn("grade", Self::set_grade)
                .with_fn("out_of", Self::out_of)
                .with_fn("out_of", Self::set_out_of)
                .with_fn("new_grade", Self::new)
+               .and_comments(&["/// Creates a grade of `grade` points out of `out_of`"])
                .with_fn("from_string", Self::grade_from_string_script)
+               .and_comments(&["/// Parses a grade written like `8/10`"])
                .with_fn("to_string", Self::to_string);
     }
 }
@@ -3235,6 +4295,8 @@ impl CustomType for Grade {
 impl CustomType for GradeResult {
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("GradeResult")
+               .with_comments(&["/// The result of grading a requirement, shown to students by",
+                                "/// `show_results`"])
                .with_fn("requirement", Self::requirement)
                .with_fn("requirement", Self::set_requirement)
                .with_fn("grade", Self::grade)
@@ -3243,7 +4305,8 @@ impl CustomType for GradeResult {
                .with_fn("out_of", Self::set_out_of)
                .with_fn("reason", Self::reason)
                .with_fn("reason", Self::set_reason)
-               .with_fn("new_grade_result", Self::default);
+               .with_fn("new_grade_result", Self::default)
+               .and_comments(&["/// Creates an empty result, for graders written in the script"]);
     }
 }
 
@@ -3254,6 +4317,8 @@ impl CustomType for DocsGrader {
     /// Builds a custom type to be registered with Rhai
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("DocsGrader")
+               .with_comments(&["/// Grades the javadoc of some files, deducting a penalty per",
+                                "/// missing or malformed comment"])
                .with_fn("req_name", Self::req_name)
                .with_fn("req_name", Self::set_req_name)
                .with_fn("project", Self::project)
@@ -3265,7 +4330,112 @@ impl CustomType for DocsGrader {
                .with_fn("penalty", Self::penalty)
                .with_fn("penalty", Self::set_penalty)
                .with_fn("new_docs_grader", Self::default)
-               .with_fn("run", Self::grade_docs_script);
+               .and_comments(&["/// Creates a docs grader; set its project, files, out_of and",
+                               "/// req_name before running it"])
+               .with_fn("run", Self::grade_docs_script)
+               .and_comments(&["/// Runs javadoc's lint on the files, and returns the result"]);
+    }
+}
+
+// Allowed because CustomType is not deprecated, just volatile
+#[allow(deprecated)]
+/// Allows registering custom types with Rhai
+impl CustomType for StyleGrader {
+    /// Builds a custom type to be registered with Rhai
+    fn build(mut builder: rhai::TypeBuilder<Self>) {
+        builder.with_name("StyleGrader")
+               .with_comments(&["/// Grades code style with rules like naming and magic numbers,",
+                                "/// each with a penalty and a cap"])
+               .with_fn("req_name", Self::req_name)
+               .with_fn("req_name", Self::set_req_name)
+               .with_fn("project", Self::project)
+               .with_fn("project", Self::set_project)
+               .with_fn("files", Self::files)
+               .with_fn("files", Self::set_files)
+               .with_fn("out_of", Self::out_of)
+               .with_fn("out_of", Self::set_out_of)
+               .with_fn("rules", Self::rules)
+               .with_fn("rule", Self::rule_script)
+               .and_comments(&["/// Enables a rule, with a penalty per violation and a cap on the",
+                               "/// total penalty"])
+               .with_fn("max_method_length", Self::max_method_length)
+               .with_fn("max_method_length", Self::set_max_method_length)
+               .with_fn("max_nesting_depth", Self::max_nesting_depth)
+               .with_fn("max_nesting_depth", Self::set_max_nesting_depth)
+               .with_fn("new_style_grader", Self::default)
+               .and_comments(&["/// Creates a style grader. Until rules are enabled with `rule`,",
+                               "/// every rule is checked, deducting 1 point per violation up to",
+                               "/// `out_of` per rule"])
+               .with_fn("run", Self::grade_style_script)
+               .and_comments(&["/// Checks the files against the enabled rules, and returns the",
+                               "/// result"]);
+    }
+}
+
+// Allowed because CustomType is not deprecated, just volatile
+#[allow(deprecated)]
+/// Allows registering custom types with Rhai
+impl CustomType for MetricsGrader {
+    /// Builds a custom type to be registered with Rhai
+    fn build(mut builder: rhai::TypeBuilder<Self>) {
+        builder.with_name("MetricsGrader")
+               .with_comments(&["/// Grades code metrics like cyclomatic complexity and method",
+                                "/// length against limits"])
+               .with_fn("req_name", Self::req_name)
+               .with_fn("req_name", Self::set_req_name)
+               .with_fn("project", Self::project)
+               .with_fn("project", Self::set_project)
+               .with_fn("files", Self::files)
+               .with_fn("files", Self::set_files)
+               .with_fn("out_of", Self::out_of)
+               .with_fn("out_of", Self::set_out_of)
+               .with_fn("penalty", Self::penalty)
+               .with_fn("penalty", Self::set_penalty)
+               .with_fn("max_complexity", Self::max_complexity)
+               .with_fn("max_complexity", Self::set_max_complexity)
+               .with_fn("max_loc", Self::max_loc)
+               .with_fn("max_loc", Self::set_max_loc)
+               .with_fn("max_parameters", Self::max_parameters)
+               .with_fn("max_parameters", Self::set_max_parameters)
+               .with_fn("max_nesting_depth", Self::max_nesting_depth)
+               .with_fn("max_nesting_depth", Self::set_max_nesting_depth)
+               .with_fn("max_fan_out", Self::max_fan_out)
+               .with_fn("max_fan_out", Self::set_max_fan_out)
+               .with_fn("new_metrics_grader", Self::default)
+               .and_comments(&["/// Creates a metrics grader; limits that aren't set are not",
+                               "/// checked"])
+               .with_fn("run", Self::grade_metrics_script)
+               .and_comments(&["/// Measures the files, deducts the penalty for each limit",
+                               "/// exceeded, and returns the result"]);
+    }
+}
+
+// Allowed because CustomType is not deprecated, just volatile
+#[allow(deprecated)]
+/// Allows registering custom types with Rhai
+impl CustomType for ApiGrader {
+    /// Builds a custom type to be registered with Rhai
+    fn build(mut builder: rhai::TypeBuilder<Self>) {
+        builder.with_name("ApiGrader")
+               .with_comments(&["/// Grades whether classes match an API spec of constructors,",
+                                "/// fields and methods"])
+               .with_fn("req_name", Self::req_name)
+               .with_fn("req_name", Self::set_req_name)
+               .with_fn("project", Self::project)
+               .with_fn("project", Self::set_project)
+               .with_fn("spec", Self::spec_script)
+               .with_fn("spec", Self::set_spec_script)
+               .with_fn("spec_file", Self::set_spec_file_script)
+               .and_comments(&["/// Reads the spec from a JSON file written by",
+                               "/// `umm spec-extract`"])
+               .with_fn("out_of", Self::out_of)
+               .with_fn("out_of", Self::set_out_of)
+               .with_fn("new_api_grader", Self::default)
+               .and_comments(&["/// Creates an API grader; set its spec or spec_file before",
+                               "/// running it"])
+               .with_fn("run", Self::grade_api_script)
+               .and_comments(&["/// Compares the project's classes to the spec, and returns the",
+                               "/// result"]);
     }
 }
 
@@ -3276,6 +4446,8 @@ impl CustomType for ByUnitTestGrader {
     /// Builds a custom type to be registered with Rhai
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("ByUnitTestGrader")
+               .with_comments(&["/// Grades by running JUnit tests, awarding points in proportion",
+                                "/// to the tests that pass"])
                .with_fn("test_files", Self::test_files)
                .with_fn("test_files", Self::set_test_files)
                .with_fn("project", Self::project)
@@ -3287,7 +4459,10 @@ impl CustomType for ByUnitTestGrader {
                .with_fn("req_name", Self::req_name)
                .with_fn("req_name", Self::set_req_name)
                .with_fn("new_by_unit_test_grader", Self::default)
-               .with_fn("run", Self::grade_by_tests_script);
+               .and_comments(&["/// Creates a grader that runs JUnit tests"])
+               .with_fn("run", Self::grade_by_tests_script)
+               .and_comments(&["/// Runs the test files, checks the expected tests exist, and",
+                               "/// returns the result"]);
     }
 }
 
@@ -3298,6 +4473,8 @@ impl CustomType for UnitTestGrader {
     /// Builds a custom type to be registered with Rhai
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("UnitTestGrader")
+               .with_comments(&["/// Grades a student's own unit tests by mutation testing the",
+                                "/// class they test"])
                .with_fn("req_name", Self::get_req_name)
                .with_fn("req_name", Self::set_req_name)
                .with_fn("out_of", Self::get_out_of)
@@ -3311,7 +4488,10 @@ impl CustomType for UnitTestGrader {
                .with_fn("avoid_calls_to", Self::get_avoid_calls_to)
                .with_fn("avoid_calls_to", Self::set_avoid_calls_to)
                .with_fn("new_unit_test_grader", Self::default)
-               .with_fn("run", Self::grade_unit_tests_script);
+               .and_comments(&["/// Creates a mutation testing grader"])
+               .with_fn("run", Self::grade_unit_tests_script)
+               .and_comments(&["/// Runs PIT on the target class with the target test, and",
+                               "/// returns the result"]);
     }
 }
 
@@ -3322,6 +4502,8 @@ impl CustomType for ByHiddenTestGrader {
     /// Builds a custom type to be registered with Rhai.
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("ByHiddenTestGrader")
+               .with_comments(&["/// Grades by downloading JUnit tests students don't have, and",
+                                "/// running them"])
                .with_fn("url", Self::url)
                .with_fn("url", Self::set_url)
                .with_fn("test_class_name", Self::test_class_name)
@@ -3331,7 +4513,9 @@ impl CustomType for ByHiddenTestGrader {
                .with_fn("req_name", Self::req_name)
                .with_fn("req_name", Self::set_req_name)
                .with_fn("new_by_hidden_test_grader", Self::default)
-               .with_fn("run", Self::grade_by_hidden_tests_script);
+               .and_comments(&["/// Creates a grader that runs tests downloaded from a URL"])
+               .with_fn("run", Self::grade_by_hidden_tests_script)
+               .and_comments(&["/// Downloads the test class, runs it, and returns the result"]);
     }
 }
 
@@ -3342,6 +4526,8 @@ impl CustomType for DiffGrader {
     /// Builds a custom type to be registered with Rhai.
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("DiffGrader")
+               .with_comments(&["/// Grades by running a file with some input, and comparing its",
+                                "/// output to what is expected"])
                .with_fn("req_name", Self::req_name)
                .with_fn("req_name", Self::set_req_name)
                .with_fn("out_of", Self::out_of)
@@ -3357,7 +4543,62 @@ impl CustomType for DiffGrader {
                .with_fn("ignore_case", Self::ignore_case)
                .with_fn("ignore_case", Self::set_ignore_case)
                .with_fn("new_diff_grader", Self::default)
-               .with_fn("run", Self::grade_by_diff_script);
+               .and_comments(&["/// Creates a grader that compares program output"])
+               .with_fn("run", Self::grade_by_diff_script)
+               .and_comments(&["/// Runs the file with each input, compares its output to what",
+                               "/// is expected, and returns the result"]);
+    }
+}
+
+// Allowed because CustomType is not deprecated, just volatile
+#[allow(deprecated)]
+/// Allows registering custom types with Rhai.
+impl CustomType for GraderContext {
+    /// Builds a custom type to be registered with Rhai.
+    fn build(mut builder: rhai::TypeBuilder<Self>) {
+        builder.with_name("GraderContext")
+               .with_comments(&["/// What a custom grader's logic gets to work with"])
+               .with_fn("project", Self::project)
+               .with_fn("file", Self::file_script)
+               .and_comments(&["/// Finds a file in the project by class name"])
+               .with_fn("run", Self::run_script)
+               .and_comments(&["/// Runs a file with some input, and returns its output"])
+               .with_fn("test", Self::test_script)
+               .and_comments(&["/// Runs some of a test file's tests, or all of them when none",
+                               "/// are given, and returns JUnit's output"])
+               .with_fn("query", Self::query_script)
+               .and_comments(&["/// Runs a tree-sitter query on a file, and returns what it",
+                               "/// captured"])
+               .with_fn("feedback", Self::add_feedback)
+               .and_comments(&["/// Leaves feedback for the student"])
+               .with_fn("feedback", Self::feedback)
+               .and_comments(&["/// Gets the feedback left so far"]);
+    }
+}
+
+// Allowed because CustomType is not deprecated, just volatile
+#[allow(deprecated)]
+/// Allows registering custom types with Rhai.
+impl CustomType for CustomGrader {
+    /// Builds a custom type to be registered with Rhai.
+    fn build(mut builder: rhai::TypeBuilder<Self>) {
+        builder.with_name("CustomGrader")
+               .with_comments(&["/// Grades with logic written as a closure in the grading script"])
+               .with_fn("req_name", Self::req_name)
+               .with_fn("req_name", Self::set_req_name)
+               .with_fn("out_of", Self::out_of)
+               .with_fn("out_of", Self::set_out_of)
+               .with_fn("project", Self::project)
+               .with_fn("project", Self::set_project)
+               .with_fn("logic", Self::set_logic)
+               .and_comments(&["/// Sets the closure that grades the project. It is called with",
+                               "/// a GraderContext, and returns the points earned, a map like",
+                               "/// `#{ grade: 8.0, reason: \"...\" }`, or a GradeResult"])
+               .with_fn("new_custom_grader", Self::default)
+               .and_comments(&["/// Creates a grader that runs logic from the script"])
+               .with_fn("run", Self::grade_custom_script)
+               .and_comments(&["/// Calls the logic with a GraderContext for the project, and",
+                               "/// returns the result"]);
     }
 }
 
@@ -3368,7 +4609,10 @@ impl CustomType for Query {
     /// Builds a custom type to be registered with Rhai.
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("Query")
+               .with_comments(&["/// A tree-sitter query, with the capture to return from its",
+                                "/// matches"])
                .with_fn("new_query", Self::new)
+               .and_comments(&["/// Creates an empty query"])
                .with_fn("query", Self::query)
                .with_fn("query", Self::set_query)
                .with_fn("capture", Self::capture)
@@ -3383,6 +4627,8 @@ impl CustomType for QueryGrader {
     /// Builds a custom type to be registered with Rhai.
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("QueryGrader")
+               .with_comments(&["/// Grades by running tree-sitter queries against a file, and",
+                                "/// checking how often they match"])
                .with_fn("req_name", Self::req_name)
                .with_fn("req_name", Self::set_req_name)
                .with_fn("out_of", Self::out_of)
@@ -3393,13 +4639,16 @@ impl CustomType for QueryGrader {
                .with_fn("project", Self::set_project)
                .with_fn("queries", Self::queries)
                .with_fn("query", Self::query_script)
+               .and_comments(&["/// Adds a query to run"])
                .with_fn("capture", Self::capture_script)
+               .and_comments(&["/// Sets the capture the last query returns"])
                .with_fn("reason", Self::reason)
                .with_fn("reason", Self::set_reason)
                .with_fn("must_match_at_least_once", Self::must_match_at_least_once)
                .with_fn("must_match_exactly_n_times",
                         Self::must_match_exactly_n_times)
                .with_fn("must_not_match", Self::must_not_match)
+               .with_fn("only_modified_methods", Self::only_modified_methods)
                .with_fn("method_body_with_name", Self::method_body_with_name)
                .with_fn("method_body_with_return_type",
                         Self::method_body_with_return_type)
@@ -3419,8 +4668,15 @@ impl CustomType for QueryGrader {
                .with_fn("method_invocations_with_object",
                         Self::method_invocations_with_object)
                .with_fn("filter", Self::filter_script)
+               .and_comments(&["/// Keeps only the matches of the last query a closure returns",
+                               "/// true for"])
                .with_fn("run_query", Self::run_query_script)
+               .and_comments(&["/// Runs the queries, and returns what they captured"])
                .with_fn("run", Self::grade_by_query_script)
-               .with_fn("new_query_grader", Self::default);
+               .and_comments(&["/// Runs the queries, checks the constraint, and returns the",
+                               "/// result"])
+               .with_fn("new_query_grader", Self::default)
+               .and_comments(&["/// Creates a query grader; set its project and file before",
+                               "/// adding queries"]);
     }
 }
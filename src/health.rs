@@ -1,144 +1,691 @@
-// TODO: make recommendations for the above
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    ffi::OsString,
+    fmt::Display,
+    io::Write,
+    path::{Path, PathBuf},
+    process::Command,
+};
 
 use anyhow::{Context, Result};
-use futures::{future::try_join_all, stream::FuturesUnordered};
-use tokio::{fs::OpenOptions, task::JoinError};
-use walkdir::WalkDir;
+use serde::{Deserialize, Serialize};
+use tabled::{object::Rows, Modify, TableIteratorExt, Tabled, Width};
+use walkdir::{DirEntry, WalkDir};
 
 use crate::{
-    constants::{BUILD_DIR, LIB_DIR, ROOT_DIR, RUNTIME, SOURCE_DIR, TEST_DIR},
-    java::{FileType, Project},
+    constants::{BUILD_DIR, LIBRARIES, LIB_DIR, ROOT_DIR, RUNTIME, SOURCE_DIR, TEST_DIR},
+    discovery::{is_java_identifier, ProjectFilter, WarningKind},
+    java::{File, Parser, Project},
+    util::{download, java_path, javac_path, umm_path},
 };
 
+/// How serious a health finding is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    /// worth knowing about, but nothing is broken
+    Info,
+    /// likely to cause confusing behavior
+    Warning,
+    /// will stop the project from compiling or running
+    Error,
+}
+
+impl Display for Severity {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        let name = match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Displays an optional table cell, empty when `None`
+fn display_option(value: &Option<String>) -> String {
+    value.clone().unwrap_or_default()
+}
+
+#[derive(Tabled, Serialize, Deserialize, Clone, Debug)]
+/// A single problem found when checking the health of a project
+pub struct HealthFinding {
+    /// * `id`: kind of problem, such as `invalid_jar` or `duplicate_class`
+    #[tabled(rename = "Id")]
+    pub id:       String,
+    /// * `severity`: how serious the problem is
+    #[tabled(rename = "Severity")]
+    pub severity: Severity,
+    /// * `file`: the file the problem is in, if any
+    #[tabled(rename = "File", display_with = "display_option")]
+    pub file:     Option<String>,
+    /// * `message`: a description of the problem
+    #[tabled(rename = "Message")]
+    pub message:  String,
+    /// * `fix`: what to do about it, if we know
+    #[tabled(rename = "Suggested fix", display_with = "display_option")]
+    pub fix:      Option<String>,
+}
+
+impl HealthFinding {
+    /// Creates a finding without a suggested fix
+    ///
+    /// * `id`: kind of problem
+    /// * `severity`: how serious the problem is
+    /// * `file`: the file the problem is in
+    /// * `message`: a description of the problem
+    fn new(id: &str,
+           severity: Severity,
+           file: Option<&Path>,
+           message: String)
+           -> Self {
+        Self { id: id.to_string(),
+               severity,
+               file: file.map(|f| f.display().to_string()),
+               message,
+               fix: None }
+    }
+}
+
+/// The machine a health report was made on
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Environment {
+    /// operating system, as reported by Rust
+    pub os:          String,
+    /// CPU architecture, as reported by Rust
+    pub arch:        String,
+    /// version of umm
+    pub umm_version: String,
+    /// where umm is installed
+    pub umm_path:    String,
+    /// output of `java -version`, or why it could not be run
+    pub java:        String,
+    /// output of `javac -version`, or why it could not be run
+    pub javac:       String,
+}
+
+impl Environment {
+    /// Describes the machine umm is running on
+    pub fn current() -> Self {
+        Self { os:          std::env::consts::OS.to_string(),
+               arch:        std::env::consts::ARCH.to_string(),
+               umm_version: env!("CARGO_PKG_VERSION").to_string(),
+               umm_path:    umm_path(),
+               java:        tool_version(java_path()),
+               javac:       tool_version(javac_path()), }
+    }
+}
+
+/// Runs `<tool> -version` and returns what it printed. Older JDKs print the
+/// version to stderr, newer ones to stdout, so both are included.
+///
+/// * `path`: path to the tool, or why it could not be found
+fn tool_version(path: Result<OsString>) -> String {
+    let output = path.and_then(|p| {
+                         Command::new(p).arg("-version")
+                                        .output()
+                                        .context("Failed to run it")
+                     });
+
+    match output {
+        Ok(output) => format!("{}{}",
+                              String::from_utf8_lossy(&output.stdout),
+                              String::from_utf8_lossy(&output.stderr)).trim()
+                                                                      .to_string(),
+        Err(e) => format!("{e:#}"),
+    }
+}
+
+/// Checks that a file is a zip archive that can be read, such as a jar
+///
+/// * `path`: path to the file
+fn check_zip(path: &Path) -> Result<()> {
+    let file = std::fs::File::open(path)?;
+    zip::ZipArchive::new(file)?;
+    Ok(())
+}
+
 impl Project {
-    /// Checks the project for common CodingRooms errors
-    pub fn check_health(&self) -> Result<()> {
-        tracing::info!("Checking Project Health...");
-        let project = Project::new()?;
-
-        let rt = RUNTIME.handle().clone();
-        let _guard = rt.enter();
-
-        let handle1 = rt.spawn(async {
-                            let files = WalkDir::new(ROOT_DIR.as_path())
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().is_file())
-                .map(|e| e.path().to_path_buf())
-                .map(|path| {
-                    tokio::spawn(async move {
-                        match tokio::fs::metadata(path.clone()).await {
-                            Ok(m) => {
-                                if m.len() == 0 {
-                                    tracing::warn!("File {}\n\tis empty", &path.display())
-                                }
-                                if let Err(e) =
-                                    OpenOptions::new().read(true).write(true).open(&path).await
-                                {
-                                    tracing::warn!(
-                                        "File {}\n\tcould not be opened (read + write): {}",
-                                        &path.display(),
-                                        e
-                                    )
-                                }
-                            }
-                            Err(e) => {
-                                tracing::warn!("Could not read file {}: {}", path.display(), e)
-                            }
-                        };
-
-                        if path.extension().unwrap_or_default() == "jar" {
-                            let output = tokio::process::Command::new("zip")
-                                .arg("-T")
-                                .arg(&path)
-                                .output()
-                                .await
-                                .unwrap_or_else(|_| {
-                                    panic!("Could not run zip -T on {}", &path.display())
-                                });
-
-                            if !output.status.success() {
-                                tracing::warn!(
-                                    "File {}\n\tis not a valid zip file: {}",
-                                    &path.display(),
-                                    String::from_utf8_lossy(&output.stderr)
-                                )
-                            }
-                        }
-                    })
-                })
-                .collect::<FuturesUnordered<_>>();
-
-                            try_join_all(files).await
-                        });
-
-        let handle2 =
-            rt.spawn(async move {
-                  let files =
-                      project.files()
-                             .iter()
-                             .map(|file| {
-                                 let file = file.clone();
-                                 tokio::spawn(async move {
-                                     if file.package_name().is_none() {
-                                         tracing::warn!("File {}\n\tdoesn't belong to any package",
-                                                        file.path().display());
-                                     } else {
-                                         let expected_path = if let FileType::Test = file.kind() {
-                                             TEST_DIR.join(file.package_name().unwrap())
-                                         } else {
-                                             SOURCE_DIR.join(file.package_name().unwrap())
-                                         };
-                                         if file.path().parent().unwrap_or(&ROOT_DIR)
-                                            != expected_path.as_path()
-                                         {
-                                             tracing::warn!("File {}\n\tis in the wrong \
-                                                             directory.\n\t\tExpected: \
-                                                             {}\n\t\tFound: {}",
-                                                            file.path().display(),
-                                                            expected_path.display(),
-                                                            file.path()
-                                                                .parent()
-                                                                .unwrap_or(&ROOT_DIR)
-                                                                .to_string_lossy());
-                                         }
-                                     }
-                                 })
-                             })
-                             .collect::<FuturesUnordered<_>>();
-                  try_join_all(files).await
-              });
-
-        rt.block_on(async {
-              if BUILD_DIR.join(".vscode").exists() {
-                  tokio::fs::remove_dir_all(BUILD_DIR.join(".vscode").as_path())
-                    .await
-                    .with_context(|| {
-                        format!("Could not delete {}", BUILD_DIR.join(".vscode").display())
-                    })
-                    .unwrap();
-              }
-
-              if BUILD_DIR.join(LIB_DIR.display().to_string()).exists() {
-                  tokio::fs::remove_dir_all(BUILD_DIR.join(LIB_DIR.display().to_string())
-                                                     .as_path()).await
-                                                                .with_context(|| {
-                                                                    format!(
-                            "Could not delete {}",
-                            BUILD_DIR.join(LIB_DIR.display().to_string()).display()
-                        )
-                                                                })
-                                                                .unwrap();
-              }
-              let handles = FuturesUnordered::from_iter(vec![handle1, handle2]);
-              try_join_all(handles).await
-          })?
-          .into_iter()
-          .collect::<Result<Vec<Vec<()>>, JoinError>>()?;
-
-        tracing::info!("This is information an instructor can use to help you, please don't try \
-                        to interpret it yourself or make any changes to your submission based on \
-                        it.");
+    /// Checks the project for common problems, such as empty or read-only
+    /// files, corrupted libraries, and classes in the wrong place. Problems
+    /// `umm doctor --fix` can take care of say so in their suggested fix.
+    /// Errors come first.
+    pub fn health_findings(&self) -> Result<Vec<HealthFinding>> {
+        let mut findings = vec![];
+
+        let filter = ProjectFilter::new(&ROOT_DIR);
+        for entry in WalkDir::new(ROOT_DIR.as_path()).into_iter()
+                                                     .filter_entry(|e| !skipped_folder(&filter, e))
+                                                     .filter_map(|e| e.ok())
+                                                     .filter(|e| e.file_type().is_file())
+        {
+            let path = entry.path();
+            match std::fs::metadata(path) {
+                Ok(m) if m.len() == 0 => {
+                    findings.push(HealthFinding { fix: Some(String::from("add the missing code, \
+                                                                          or delete the file if \
+                                                                          it isn't needed")),
+                                                  ..HealthFinding::new("empty_file",
+                                                                       Severity::Warning,
+                                                                       Some(path),
+                                                                       String::from("File is \
+                                                                                     empty")) });
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    findings.push(HealthFinding::new("unreadable_file",
+                                                     Severity::Error,
+                                                     Some(path),
+                                                     format!("Could not read file: {e}")));
+                    continue;
+                }
+            }
+
+            if let Err(e) = std::fs::OpenOptions::new().read(true)
+                                                       .write(true)
+                                                       .open(path)
+            {
+                findings.push(HealthFinding { fix: Some(String::from("make the file writable")),
+                                              ..HealthFinding::new("read_only_file",
+                                                                   Severity::Warning,
+                                                                   Some(path),
+                                                                   format!("Could not be \
+                                                                            opened for \
+                                                                            writing: {e}")) });
+            }
+
+            if path.extension().unwrap_or_default() == "jar" {
+                if let Err(e) = check_zip(path) {
+                    findings.push(HealthFinding::new("invalid_jar",
+                                                     Severity::Error,
+                                                     Some(path),
+                                                     format!("Not a valid jar file: {e}")));
+                }
+            }
+        }
+
+        for warning in self.warnings() {
+            let (id, severity) = match warning.kind {
+                WarningKind::Unreadable => ("unparsable_file", Severity::Error),
+                WarningKind::DuplicateClass => ("duplicate_class", Severity::Error),
+                WarningKind::AmbiguousName => ("ambiguous_name", Severity::Info),
+                WarningKind::PackageMismatch => ("package_mismatch", Severity::Warning),
+            };
+            findings.push(HealthFinding::new(id,
+                                             severity,
+                                             warning.files.first().map(PathBuf::as_path),
+                                             warning.message.clone()));
+        }
+
+        // Attach fixes to the findings they solve, and report the rest on their own
+        for fix in self.structure_fixes()? {
+            let (id, path) = match &fix {
+                Fix::Delete { path, .. } => ("stray_file", path),
+                Fix::SetPackage { path, .. } => ("package_mismatch", path),
+                Fix::Move { from, .. } => ("package_mismatch", from),
+                Fix::Redownload { path, .. } => ("invalid_jar", path),
+            };
+            let file = Some(path.display().to_string());
+            let suggestion = format!("run `umm doctor --fix` to {fix}");
+
+            match findings.iter_mut().find(|f| {
+                                         f.file == file
+                                         && f.fix.is_none()
+                                         && f.id != "ambiguous_name"
+                                     }) {
+                Some(finding) => finding.fix = Some(suggestion),
+                None => {
+                    let message = match &fix {
+                        Fix::Delete { reason, .. } => format!("Should not be here: {reason}"),
+                        _ => format!("Needs fixing: {fix}"),
+                    };
+                    findings.push(HealthFinding { fix: Some(suggestion),
+                                                  ..HealthFinding::new(id,
+                                                                       Severity::Warning,
+                                                                       Some(path),
+                                                                       message) });
+                }
+            }
+        }
+
+        findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+        Ok(findings)
+    }
+
+    /// Checks the project for common problems, and prints them as a table,
+    /// or as JSON for instructors and scripts.
+    ///
+    /// * `json`: whether to print JSON instead of a table
+    pub fn check_health(&self,
+                        json: bool)
+                        -> Result<()> {
+        let findings = self.health_findings()?;
+
+        if json {
+            println!("{}", serde_json::to_string_pretty(&findings)?);
+        } else if findings.is_empty() {
+            println!("No problems found.");
+        } else {
+            println!("{}",
+                     findings.table()
+                             .with(Modify::new(Rows::new(1..)).with(Width::wrap(40).keep_words()))
+                             .with(tabled::Style::modern()));
+        }
+
+        Ok(())
+    }
+
+    /// Writes a zip file to share when asking for help, containing the health
+    /// findings, the output of `umm info`, and the versions of umm, java and
+    /// javac in use. Returns the path to the zip file.
+    pub fn health_bundle(&self) -> Result<PathBuf> {
+        let findings = self.health_findings()?;
+        let path = ROOT_DIR.join(format!("health-report-{}.zip",
+                                         chrono::offset::Local::now().format("%Y-%m-%d-%H-%M-%S")));
+
+        let mut zip = zip::ZipWriter::new(std::fs::File::create(&path).with_context(|| {
+                                              format!("Could not create {}", path.display())
+                                          })?);
+        let options =
+            zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+
+        let contents = [("findings.json", serde_json::to_string_pretty(&findings)?),
+                        ("info.json", serde_json::to_string_pretty(self)?),
+                        ("environment.json",
+                         serde_json::to_string_pretty(&Environment::current())?)];
+        for (name, content) in contents {
+            zip.start_file(name, options)?;
+            zip.write_all(content.as_bytes())?;
+        }
+        zip.finish()?;
+
+        Ok(path)
+    }
+}
+
+/// A change `umm doctor --fix` makes to fix a project structure problem
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Fix {
+    /// Delete a file that shouldn't be there, such as a stray `.class` file
+    /// or an identical copy of another file
+    Delete {
+        /// the file to delete
+        path:   PathBuf,
+        /// why it is being deleted
+        reason: String,
+    },
+    /// Add a package declaration, or change the existing one, to match the
+    /// folder a file is in
+    SetPackage {
+        /// the file to edit
+        path:    PathBuf,
+        /// the package it should declare
+        package: String,
+    },
+    /// Move a file into the folder that matches its package declaration
+    Move {
+        /// where the file is
+        from: PathBuf,
+        /// where it should be
+        to:   PathBuf,
+    },
+    /// Download a corrupted library again
+    Redownload {
+        /// the jar file to replace
+        path: PathBuf,
+        /// where to download it from
+        url:  String,
+    },
+}
+
+impl Display for Fix {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        match self {
+            Fix::Delete { path, reason } => write!(f, "delete {} ({reason})", path.display()),
+            Fix::SetPackage { path, package } => {
+                write!(f, "set the package of {} to {package}", path.display())
+            }
+            Fix::Move { from, to } => write!(f, "move {} to {}", from.display(), to.display()),
+            Fix::Redownload { path, url } => {
+                write!(f, "download {} again from {url}", path.display())
+            }
+        }
+    }
+}
+
+impl Fix {
+    /// Makes the change on disk
+    pub fn apply(&self) -> Result<()> {
+        match self {
+            Fix::Delete { path, .. } => {
+                if path.is_dir() {
+                    std::fs::remove_dir_all(path)
+                } else {
+                    std::fs::remove_file(path)
+                }.with_context(|| format!("Could not delete {}", path.display()))
+            }
+            Fix::SetPackage { path, package } => {
+                let source = std::fs::read_to_string(path).with_context(|| {
+                                                              format!("Could not read {}",
+                                                                      path.display())
+                                                          })?;
+                std::fs::write(path, with_package(&source, package)?).with_context(|| {
+                                                                         format!("Could not write \
+                                                                                  {}",
+                                                                                 path.display())
+                                                                     })
+            }
+            Fix::Move { from, to } => {
+                if let Some(parent) = to.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::rename(from, to).with_context(|| {
+                                             format!("Could not move {} to {}",
+                                                     from.display(),
+                                                     to.display())
+                                         })
+            }
+            Fix::Redownload { path, url } => {
+                let rt = RUNTIME.handle().clone();
+                rt.block_on(download(url, path, true))
+            }
+        }
+    }
+}
+
+/// Returns `source` with its package declaration replaced with `package
+/// {package};`, or with one added ahead of the first import or type if it
+/// has none. Comments at the top of the file stay where they are.
+///
+/// * `source`: Java source code
+/// * `package`: the package to declare
+fn with_package(source: &str,
+                package: &str)
+                -> Result<String> {
+    let parser = Parser::new(source.to_string())?;
+    let tree = parser.tree().context("Treesitter could not parse code")?;
+    let root = tree.root_node();
+    let mut cursor = root.walk();
+    let children = root.named_children(&mut cursor).collect::<Vec<_>>();
+    let declaration = format!("package {package};");
+
+    match children.iter().find(|n| n.kind() == "package_declaration") {
+        Some(existing) => Ok(format!("{}{}{}",
+                                     &source[..existing.start_byte()],
+                                     declaration,
+                                     &source[existing.end_byte()..])),
+        None => {
+            let at = children.iter()
+                             .find(|n| !n.kind().ends_with("comment"))
+                             .map_or(source.len(), |n| n.start_byte());
+            Ok(format!("{}{}\n\n{}",
+                       &source[..at],
+                       declaration,
+                       &source[at..]))
+        }
+    }
+}
+
+/// Returns the source folder a file is in, which is the project root for
+/// files outside both [struct@SOURCE_DIR] and [struct@TEST_DIR]
+///
+/// * `path`: path to a Java file
+fn source_root(path: &Path) -> &'static Path {
+    [SOURCE_DIR.as_path(), TEST_DIR.as_path()].into_iter()
+                                              .find(|root| path.starts_with(root))
+                                              .unwrap_or(ROOT_DIR.as_path())
+}
+
+/// Returns the package a file's folder corresponds to, relative to its
+/// source folder. `Some("")` for files directly in a source folder, `None`
+/// if the folder names aren't valid package names.
+///
+/// * `path`: path to a Java file
+fn folder_package(path: &Path) -> Option<String> {
+    let folder = path.parent().unwrap_or(ROOT_DIR.as_path());
+    let segments = folder.strip_prefix(source_root(path))
+                         .unwrap_or(folder)
+                         .components()
+                         .map(|c| c.as_os_str().to_string_lossy().to_string())
+                         .collect::<Vec<_>>();
+
+    if segments.iter().all(|s| is_java_identifier(s)) {
+        Some(segments.join("."))
+    } else {
+        None
+    }
+}
+
+/// Whether two Java files are the same, ignoring whitespace
+///
+/// * `a`: path to one file
+/// * `b`: path to the other
+fn same_contents(a: &Path,
+                 b: &Path)
+                 -> bool {
+    let read =
+        |p: &Path| std::fs::read_to_string(p).map(|s| s.split_whitespace().collect::<String>());
+    matches!((read(a), read(b)), (Ok(a), Ok(b)) if a == b)
+}
+
+/// Returns the name of the file a backup copy like `Main copy.java`,
+/// `Main copy 2.java` or `Main (1).java` was made from, if it looks like
+/// one.
+///
+/// * `stem`: file name without `.java`
+fn backup_of(stem: &str) -> Option<&str> {
+    let base = if let Some(i) = stem.find(" copy") {
+        &stem[..i]
+    } else if stem.ends_with(')') {
+        stem[..stem.rfind(" (")?].trim_end()
+    } else {
+        return None;
+    };
+
+    is_java_identifier(base).then_some(base)
+}
+
+/// Whether a folder is skipped when looking for stray files. These are the
+/// folders discovery leaves out, but ignored files are still looked at, as
+/// `.gitignore` files often list `*.class`.
+///
+/// * `filter`: discovery's filter for the project
+/// * `entry`: an entry found walking the project root
+fn skipped_folder(filter: &ProjectFilter,
+                  entry: &DirEntry)
+                  -> bool {
+    entry.file_type().is_dir() && filter.skips(entry)
+}
+
+impl Project {
+    /// Works out the changes that fix common project structure problems.
+    /// Problems that can't be fixed safely, like two different files
+    /// declaring the same class, are logged as warnings instead.
+    ///
+    /// * files declaring a package that doesn't match their folder are moved,
+    ///   or have their package declaration fixed when the folder they are in is
+    ///   a valid package
+    /// * files in a sub-folder without a package declaration get one
+    /// * `.class` files outside [struct@BUILD_DIR] are deleted, as are folders
+    ///   older versions of umm left inside it
+    /// * identical copies of a file, such as `Main copy.java`, are deleted
+    /// * libraries that aren't valid jar files are downloaded again
+    pub fn structure_fixes(&self) -> Result<Vec<Fix>> {
+        let mut deletes = vec![];
+        let mut edits = vec![];
+        let mut moves = vec![];
+        let mut downloads = vec![];
+        let mut deleted = HashSet::new();
+
+        // Duplicate declarations of a class, keeping the one in the right place
+        let mut by_proper_name = BTreeMap::<String, Vec<&File>>::new();
+        for file in self.files() {
+            by_proper_name.entry(file.proper_name())
+                          .or_default()
+                          .push(file);
+        }
+        for files in by_proper_name.values().filter(|f| f.len() > 1) {
+            // Prefer files whose package matches their folder, then files inside
+            // the source or test folder over ones in the project root
+            let keeper = files.iter()
+                              .max_by_key(|f| {
+                                  let declared = f.package_name().cloned().unwrap_or_default();
+                                  (folder_package(f.path()) == Some(declared),
+                                   source_root(f.path()) != ROOT_DIR.as_path())
+                              })
+                              .unwrap_or(&files[0]);
+
+            for file in files.iter().filter(|f| f.path() != keeper.path()) {
+                if same_contents(file.path(), keeper.path()) {
+                    deleted.insert(file.path().clone());
+                    deletes.push(Fix::Delete { path:   file.path().clone(),
+                                               reason: format!("copy of {}",
+                                                               keeper.path().display()), });
+                } else {
+                    tracing::warn!("{} and {} both declare {}, but are different. Please delete \
+                                    the one you don't need.",
+                                   file.path().display(),
+                                   keeper.path().display(),
+                                   file.proper_name());
+                }
+            }
+        }
+
+        // Packages that don't match folders
+        for file in self.files().iter().filter(|f| !deleted.contains(f.path())) {
+            let declared = file.package_name().cloned().unwrap_or_default();
+            let folder = folder_package(file.path());
+            if folder.as_ref() == Some(&declared) {
+                continue;
+            }
+
+            match folder {
+                Some(folder) if !folder.is_empty() => {
+                    edits.push(Fix::SetPackage { path:    file.path().clone(),
+                                                 package: folder, });
+                }
+                _ => {
+                    let mut to = PathBuf::from(source_root(file.path()));
+                    to.extend(declared.split('.').filter(|s| !s.is_empty()));
+                    to.push(file.file_name());
+
+                    if to.exists() {
+                        tracing::warn!("{} should be moved to {}, but a file already exists there",
+                                       file.path().display(),
+                                       to.display());
+                    } else {
+                        moves.push(Fix::Move { from: file.path().clone(),
+                                               to });
+                    }
+                }
+            }
+        }
+
+        // Stray class files and backup copies
+        let filter = ProjectFilter::new(&ROOT_DIR);
+        for entry in WalkDir::new(ROOT_DIR.as_path()).into_iter()
+                                                     .filter_entry(|e| !skipped_folder(&filter, e))
+                                                     .filter_map(|e| e.ok())
+                                                     .filter(|e| e.file_type().is_file())
+        {
+            let path = entry.path();
+            let extension = path.extension().unwrap_or_default();
+            let stem = path.file_stem()
+                           .unwrap_or_default()
+                           .to_string_lossy()
+                           .to_string();
+
+            if extension == "class" {
+                let reason = format!("compiled class file outside {}", BUILD_DIR.display());
+                deletes.push(Fix::Delete { path: path.to_path_buf(),
+                                           reason });
+                continue;
+            }
+
+            let Some(base) = backup_of(&stem).filter(|_| extension == "java") else {
+                continue;
+            };
+            let original = path.with_file_name(format!("{base}.java"));
+            if !original.exists() {
+                continue;
+            }
+
+            if same_contents(path, &original) {
+                let reason = format!("copy of {}", original.display());
+                deletes.push(Fix::Delete { path: path.to_path_buf(),
+                                           reason });
+            } else {
+                tracing::warn!("{} looks like a copy of {}, but is different. Please delete the \
+                                one you don't need.",
+                               path.display(),
+                               original.display());
+            }
+        }
+
+        // Folders left in the build folder by older versions of umm
+        for folder in [BUILD_DIR.join(".vscode"), BUILD_DIR.join("lib")] {
+            if folder.is_dir() {
+                let reason = String::from("left behind by an older version of umm");
+                deletes.push(Fix::Delete { path: folder,
+                                           reason });
+            }
+        }
+
+        // Corrupted libraries
+        for (name, url) in LIBRARIES {
+            let path = LIB_DIR.join(name);
+            if !path.exists() {
+                continue;
+            }
+
+            let valid = std::fs::File::open(&path).map_err(anyhow::Error::from)
+                                                  .and_then(|f| {
+                                                      zip::ZipArchive::new(f)?;
+                                                      Ok(())
+                                                  })
+                                                  .is_ok();
+            if !valid {
+                downloads.push(Fix::Redownload { path,
+                                                 url: url.to_string() });
+            }
+        }
+
+        Ok([deletes, edits, moves, downloads].concat())
+    }
+
+    /// Finds and optionally fixes common project structure problems. Without
+    /// `fix`, the changes are only listed.
+    ///
+    /// * `fix`: whether to make the changes
+    pub fn doctor(&self,
+                  fix: bool)
+                  -> Result<()> {
+        let fixes = self.structure_fixes()?;
+
+        if fixes.is_empty() {
+            println!("No problems found that umm doctor can fix.");
+            return Ok(());
+        }
+
+        if fix {
+            for f in &fixes {
+                f.apply()?;
+                println!("Done: {f}");
+            }
+        } else {
+            println!("umm doctor --fix would make these changes:");
+            for f in &fixes {
+                println!("- {f}");
+            }
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,196 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{Context, Result};
+use rhai::{
+    module_resolvers::ModuleResolver, Engine, EvalAltResult, Module, Position, Scope, Shared,
+};
+
+use crate::{
+    constants::{COURSE_MODULES_URL, MODULE_PATH_ENV, STD_MODULE, STD_MODULE_NAME},
+    signing::verify_module,
+};
+
+/// A place grading script modules can be imported from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ModuleSource {
+    /// a local folder, containing `<name>.rhai` files
+    Dir(PathBuf),
+    /// a URL that `<name>.rhai` is appended to
+    Url(String),
+}
+
+impl ModuleSource {
+    /// Parses an entry of [MODULE_PATH_ENV]. Entries starting with `http://`
+    /// or `https://` are URLs, anything else is a folder.
+    ///
+    /// * `entry`: the entry to parse
+    pub fn parse(entry: &str) -> Self {
+        if entry.starts_with("http://") || entry.starts_with("https://") {
+            Self::Url(entry.trim_end_matches('/').to_string())
+        } else {
+            Self::Dir(PathBuf::from(entry))
+        }
+    }
+
+    /// Path or URL a module would be at in this place
+    ///
+    /// * `name`: the path given to `import`, without the `.rhai` extension
+    fn location(&self,
+                name: &str)
+                -> String {
+        match self {
+            Self::Dir(dir) => dir.join(format!("{name}.rhai")).display().to_string(),
+            Self::Url(url) => format!("{url}/{name}.rhai"),
+        }
+    }
+
+    /// Fetches the source of a module, `None` if this place doesn't have it
+    ///
+    /// * `name`: the path given to `import`, without the `.rhai` extension
+    fn fetch(&self,
+             name: &str)
+             -> Result<Option<String>> {
+        let location = self.location(name);
+        match self {
+            Self::Dir(_) => {
+                let path = Path::new(&location);
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                std::fs::read_to_string(path).with_context(|| format!("Could not read {location}"))
+                                             .map(Some)
+            }
+            Self::Url(_) => {
+                let url = location;
+                let response =
+                    reqwest::blocking::get(&url).with_context(|| format!("Cannot get url: {url}"))?;
+                if !response.status().is_success() {
+                    return Ok(None);
+                }
+                response.text()
+                        .with_context(|| {
+                            format!("Could not parse the response from {url} to text.")
+                        })
+                        .map(Some)
+            }
+        }
+    }
+}
+
+impl Display for ModuleSource {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        match self {
+            Self::Dir(dir) => write!(f, "{}", dir.display()),
+            Self::Url(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Places modules are imported from, in order: the entries of
+/// [MODULE_PATH_ENV] separated by `;`, then the course's script repo
+pub fn module_path() -> Vec<ModuleSource> {
+    let mut sources = std::env::var(MODULE_PATH_ENV).unwrap_or_default()
+                                                    .split(';')
+                                                    .map(str::trim)
+                                                    .filter(|e| !e.is_empty())
+                                                    .map(ModuleSource::parse)
+                                                    .collect::<Vec<_>>();
+    sources.push(ModuleSource::Url(COURSE_MODULES_URL.to_string()));
+    sources
+}
+
+/// Compiles the built-in [STD_MODULE] into a module that can be registered
+/// as [STD_MODULE_NAME]
+///
+/// * `engine`: engine with the types and functions the module uses
+pub fn std_module(engine: &Engine) -> Result<Shared<Module>> {
+    let mut ast = engine.compile(STD_MODULE)
+                        .map_err(|e| anyhow::anyhow!("{e}"))
+                        .context("Could not compile the built-in module")?;
+    ast.set_source(STD_MODULE_NAME);
+    let module = Module::eval_ast_as_new(Scope::new(), &ast, engine);
+    let module = module.map_err(|e| anyhow::anyhow!("{e}"))
+                       .context("Could not load the built-in module")?;
+    Ok(module.into())
+}
+
+/// Resolves `import "name" as alias;` in grading scripts. A module is
+/// looked for next to the script importing it first, when that script is a
+/// local file, and then in each place of [module_path]. Modules must be
+/// signed like grading scripts, when there are trusted keys. Modules are only
+/// loaded once per engine.
+#[derive(Debug)]
+pub struct ScriptModuleResolver {
+    /// places to look for modules in, after the importing script's folder
+    sources: Vec<ModuleSource>,
+    /// modules loaded so far, by the path they were imported with
+    cache:   Mutex<HashMap<String, Shared<Module>>>,
+}
+
+impl ScriptModuleResolver {
+    /// Creates a resolver that looks in `sources`
+    ///
+    /// * `sources`: places to look for modules in
+    pub fn new(sources: Vec<ModuleSource>) -> Self {
+        Self { sources,
+               cache: Mutex::new(HashMap::new()) }
+    }
+}
+
+impl ModuleResolver for ScriptModuleResolver {
+    fn resolve(&self,
+               engine: &Engine,
+               source: Option<&str>,
+               path: &str,
+               pos: Position)
+               -> Result<Shared<Module>, Box<EvalAltResult>> {
+        if let Some(module) = self.cache.lock().unwrap().get(path) {
+            return Ok(module.clone());
+        }
+
+        let script_dir = source.map(Path::new)
+                               .filter(|s| s.is_file())
+                               .and_then(Path::parent)
+                               .map(|dir| ModuleSource::Dir(dir.to_path_buf()));
+
+        for place in script_dir.iter().chain(self.sources.iter()) {
+            let code = match place.fetch(path) {
+                Ok(Some(code)) => code,
+                Ok(None) => continue,
+                Err(e) => {
+                    tracing::warn!("Could not look for module {path} in {place}: {e:#}");
+                    continue;
+                }
+            };
+
+            let in_module =
+                |e: Box<EvalAltResult>| EvalAltResult::ErrorInModule(path.to_string(), e, pos);
+            let fetched = matches!(place, ModuleSource::Url(_));
+            verify_module(&place.location(path), &code, fetched).map_err(|e| {
+                                                                    in_module(format!("{e:#}").into())
+                                                                })?;
+            let mut ast = engine.compile(code).map_err(|e| in_module(e.into()))?;
+            ast.set_source(path);
+            let module = Module::eval_ast_as_new(Scope::new(), &ast, engine).map_err(in_module)?;
+            let module: Shared<Module> = module.into();
+
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), module.clone());
+            return Ok(module);
+        }
+
+        Err(EvalAltResult::ErrorModuleNotFound(path.to_string(), pos).into())
+    }
+}
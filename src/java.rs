@@ -2,6 +2,7 @@
 #![warn(clippy::missing_docs_in_private_items)]
 
 use std::{
+    collections::HashMap,
     fmt::Formatter,
     hash::{Hash, Hasher},
     io::Write,
@@ -11,7 +12,7 @@ use std::{
 
 use anyhow::{anyhow, bail, Context, Result};
 use futures::{
-    future::{join_all, try_join_all},
+    future::join_all,
     stream::FuturesUnordered,
 };
 use rhai::Array;
@@ -20,14 +21,17 @@ use rhai::Array;
 use rhai::{CustomType, EvalAltResult};
 use serde::{Deserialize, Serialize};
 use snailquote::unescape;
-use tokio::io::AsyncWriteExt;
 use tree_sitter::{Query, QueryCursor, Tree};
 use umm_derive::generate_rhai_variant;
 
 use crate::{
     constants::*,
+    discovery::{check_names, find_java_files, DiscoveryOptions, DiscoveryWarning, WarningKind},
+    editors::{Editor, ProjectLayout},
     grade::{JavacDiagnostic, LineRef},
+    metrics::{self, ClassMetrics},
     parsers::parser,
+    starter::{starter_code, Change},
     util::*,
     vscode::{self},
     Dict,
@@ -70,6 +74,8 @@ pub struct File {
     parser:       Parser,
     /// Concise description of the file
     description:  String,
+    /// Code metrics for each class in the file
+    metrics:      Vec<ClassMetrics>,
 }
 
 /// Two `File`s are equal if their paths are equal
@@ -107,6 +113,9 @@ pub struct Project {
     sourcepath: Vec<String>,
     /// Root directory
     root_dir:   String,
+    /// Problems found while discovering files
+    #[serde(default)]
+    warnings:   Vec<DiscoveryWarning>,
 }
 
 #[derive(Clone)]
@@ -172,6 +181,16 @@ impl Parser {
         self.code = code;
     }
 
+    /// A getter for the parse tree, if tree-sitter could parse the code
+    pub fn tree(&self) -> Option<&Tree> {
+        self._tree.as_ref()
+    }
+
+    /// A borrowing getter for parser's source code
+    pub fn source(&self) -> &str {
+        &self.code
+    }
+
     #[generate_rhai_variant(Fallible, Mut)]
     /// Applies a tree sitter query and returns the result as a collection of
     /// HashMaps
@@ -229,10 +248,13 @@ impl Parser {
 impl CustomType for Parser {
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("JavaParser")
+               .with_comments(&["/// A tree-sitter parser for Java source code"])
                .with_fn("new_java_parser", Parser::new_script)
+               .and_comments(&["/// Parses some Java source code"])
                .with_fn("code", Parser::code)
                .with_fn("set_code", Parser::set_code)
-               .with_fn("query", Parser::query_mut_script);
+               .with_fn("query", Parser::query_mut_script)
+               .and_comments(&["/// Runs a query, and returns what it captured"]);
     }
 }
 
@@ -495,6 +517,11 @@ impl File {
             }
         };
 
+        let metrics = match parser.tree() {
+            Some(tree) => metrics::compute(&parser.code, tree),
+            None => vec![],
+        };
+
         Ok(Self { path: path.to_owned(),
                   file_name: path.file_name().unwrap().to_str().unwrap().to_string(),
                   package_name,
@@ -504,7 +531,8 @@ impl File {
                   kind,
                   proper_name,
                   parser,
-                  description })
+                  description,
+                  metrics })
     }
 
     /// Returns the inner doc check of this [`File`].
@@ -527,6 +555,7 @@ impl File {
                                           "-Xdiags:verbose",
                                           "-Xdoclint"
                                           /* "-Xlint", */])
+                                   .args(TOOLCHAIN.javac_args())
                                    .output()
                                    .context("Failed to spawn javac process.")
     }
@@ -608,6 +637,7 @@ impl File {
                                           "-Xdiags:verbose",
                                           // "-Xlint",
                                           "-Xprefer:source"])
+                                   .args(TOOLCHAIN.javac_args())
                                    .output()
                                    .context("Failed to spawn javac process.")
     }
@@ -849,9 +879,23 @@ impl File {
                 tests: Vec<&str>,
                 project: Option<&Project>)
                 -> Result<String, JavaFileError> {
+        self.test_with_stdin(tests, project, Stdio::inherit())
+    }
+
+    /// Like [File::test], but gives the JUnit process `stdin` instead of
+    /// umm's own stdin, for callers that use theirs for something else.
+    ///
+    /// * `tests`: names of the test methods to run
+    /// * `project`: the project, to keep only its frames in stack traces
+    /// * `stdin`: stdin for the JUnit process
+    pub fn test_with_stdin(&self,
+                           tests: Vec<&str>,
+                           project: Option<&Project>,
+                           stdin: Stdio)
+                           -> Result<String, JavaFileError> {
         self.check()?;
 
-        match self.inner_test(tests, Stdio::piped(), Stdio::piped(), Stdio::inherit()) {
+        match self.inner_test(tests, Stdio::piped(), Stdio::piped(), stdin) {
             Ok(out) => {
                 let output = unescape(
                     &[
@@ -1024,6 +1068,20 @@ impl File {
     pub fn proper_name(&self) -> String {
         self.proper_name.clone()
     }
+
+    /// Get a reference to the file's code metrics.
+    pub fn metrics(&self) -> &[ClassMetrics] {
+        &self.metrics
+    }
+
+    /// Get the file's code metrics as an array of object maps.
+    pub fn metrics_mut_script(&mut self) -> Result<Array, Box<EvalAltResult>> {
+        let mut arr = Array::new();
+        for class in &self.metrics {
+            arr.push(rhai::serde::to_dynamic(class)?);
+        }
+        Ok(arr)
+    }
 }
 
 // Allowed because CustomType is not deprecated, just volatile
@@ -1031,17 +1089,28 @@ impl File {
 impl CustomType for File {
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("JavaFile")
+               .with_comments(&["/// A Java source file in the project"])
                .with_fn("new_java_file", File::new_script)
+               .and_comments(&["/// Reads and parses a Java file"])
                .with_fn("check", File::check_mut_script)
+               .and_comments(&["/// Compiles the file, and returns javac's output"])
                .with_fn("doc_check", File::doc_check_mut_script)
+               .and_comments(&["/// Runs javadoc's lint on the file, and returns its output"])
                .with_fn("run", File::run_mut_script)
+               .and_comments(&["/// Runs the file with some input, and returns its output"])
                .with_fn("test", File::test_mut_script)
+               .and_comments(&["/// Runs the file's JUnit tests, optionally only some of them,",
+                               "/// and returns the output"])
                .with_fn("kind", File::kind)
                .with_fn("file_name", File::file_name)
                .with_fn("test_methods", File::test_methods_mut_script)
+               .and_comments(&["/// Names of the file's JUnit tests"])
                .with_fn("query", File::query_mut_script)
+               .and_comments(&["/// Runs a query on the file, and returns what it captured"])
                .with_fn("package_name", File::package_name)
                .with_fn("path", File::path_mut_script)
+               .with_fn("metrics", File::metrics_mut_script)
+               .and_comments(&["/// Complexity and size metrics for each class in the file"])
                .with_fn("parser", File::parser);
     }
 }
@@ -1049,32 +1118,55 @@ impl CustomType for File {
 impl Project {
     #[generate_rhai_variant(Impl, Fallible)]
     /// Initializes a Project, by discovering java files in the
-    /// [struct@UMM_DIR] directory. Also downloads some `jar`
-    /// files required for unit testing and mutation testing.
+    /// [struct@UMM_DIR] directory. Nothing is downloaded or written, see
+    /// [`Project::init`] for downloading libraries and writing editor files.
+    /// Which files are included can be configured, see
+    /// [`DiscoveryOptions::configured`].
     pub fn new() -> Result<Self> {
+        Self::with_options(&DiscoveryOptions::configured()?)
+    }
+
+    /// Initializes a Project like [`Project::new`], with control over which
+    /// files are discovered. Files that can't be read or parsed are left out
+    /// and reported in [`Project::warnings`] instead.
+    ///
+    /// * `options`: depth limit and include/exclude globs
+    pub fn with_options(options: &DiscoveryOptions) -> Result<Self> {
         let mut files = vec![];
         let mut names = vec![];
+        let mut warnings = vec![];
 
         let rt = RUNTIME.handle().clone();
         let handles = FuturesUnordered::new();
 
-        let results = rt.block_on(async {
-                            let found_files = match find_files("java", 15, &ROOT_DIR) {
-                                Ok(f) => f,
-                                Err(e) => panic!("Could not find java files: {e}"),
-                            };
-
-                            for path in found_files {
-                                handles.push(rt.spawn_blocking(|| File::new(path)))
-                            }
+        let found_files = find_java_files(&ROOT_DIR, options)?;
+        let results =
+            rt.block_on(async {
+                  for path in found_files {
+                      handles.push(rt.spawn_blocking(move || (File::new(path.clone()), path)))
+                  }
 
-                            join_all(handles).await
-                        });
+                  join_all(handles).await
+              });
 
         for result in results {
-            let file = result??;
-            names.push(file.proper_name.clone());
-            files.push(file);
+            match result? {
+                (Ok(file), _) => {
+                    names.push(file.proper_name.clone());
+                    files.push(file);
+                }
+                (Err(e), path) => {
+                    warnings.push(DiscoveryWarning { kind:    WarningKind::Unreadable,
+                                                     message: format!("Skipping {}: {e:#}",
+                                                                      path.display()),
+                                                     files:   vec![path], });
+                }
+            }
+        }
+
+        warnings.extend(check_names(&files));
+        for warning in &warnings {
+            tracing::warn!("{warning}");
         }
 
         let classpath = vec![LIB_DIR.join("*.jar").display().to_string()];
@@ -1082,7 +1174,9 @@ impl Project {
         let mut sourcepath = vec![SOURCE_DIR.join("").display().to_string(),
                                   TEST_DIR.join("").display().to_string(),];
 
-        if !find_files("java", 0, &ROOT_DIR)?.is_empty() {
+        if files.iter()
+                .any(|f| f.path.parent() == Some(ROOT_DIR.as_path()))
+        {
             sourcepath.push(ROOT_DIR.join("").display().to_string());
         }
 
@@ -1090,65 +1184,75 @@ impl Project {
                           names,
                           classpath,
                           sourcepath,
-                          root_dir: ROOT_DIR.display().to_string() };
-
-        let _guard = rt.enter();
-        rt.block_on(async {
-              let handles = FuturesUnordered::new();
-              let (proj1, proj2, proj3) = (proj.clone(), proj.clone(), proj.clone());
+                          root_dir: ROOT_DIR.display().to_string(),
+                          warnings };
 
-              handles.push(tokio::spawn(async move { proj1.download_libraries_if_needed().await }));
-              handles.push(tokio::spawn(async move { proj2.update_vscode_settings().await }));
-              handles.push(tokio::spawn(async move { proj3.update_vscode_tasks().await }));
+        Ok(proj)
+    }
 
-              try_join_all(handles).await
-          })?
-          .into_iter()
-          .collect::<Result<Vec<()>>>()?;
+    /// Problems found while discovering the project's files, such as files
+    /// that could not be parsed.
+    pub fn warnings(&self) -> &[DiscoveryWarning] {
+        &self.warnings
+    }
 
-        Ok(proj)
+    /// Returns the files a partial or fully formed name could refer to. Names
+    /// are tried against, in order, the fully qualified name, the path, the
+    /// file name with and without `.java`, and the class name. The first of
+    /// these with any match decides the candidates.
+    ///
+    /// * `name`: partial/fully formed name of the Java file to look for.
+    fn candidates(&self,
+                  name: &str)
+                  -> Vec<&File> {
+        let criteria: [fn(&File, &str) -> bool; 5] =
+            [|f, name| f.proper_name == name,
+             |f, name| f.path.display().to_string() == name,
+             |f, name| f.file_name == name,
+             |f, name| f.file_name.replace(".java", "") == name,
+             |f, name| f.name == name];
+
+        criteria.iter()
+                .map(|matches| self.files.iter().filter(|f| matches(f, name)).collect())
+                .find(|candidates: &Vec<&File>| !candidates.is_empty())
+                .unwrap_or_default()
     }
 
     #[generate_rhai_variant(Impl, Mut, Fallible)]
     /// Attempts to identify the correct file from the project from a partial or
     /// fully formed name as expected by a java compiler.
     ///
-    /// Returns a reference to the identified file, if any.
+    /// Returns a reference to the identified file, if any. If the name could
+    /// refer to more than one file, an error listing all of them is returned
+    /// instead.
     ///
     /// * `name`: partial/fully formed name of the Java file to look for.
     pub fn identify(&self,
                     name: &str)
                     -> Result<File> {
-        let name: String = name.into();
-
-        if let Some(i) = self.names.iter().position(|n| *n == name) {
-            Ok(self.files[i].clone())
-        } else if let Some(i) = self.files.iter().position(|n| n.file_name == name) {
-            Ok(self.files[i].clone())
-        } else if let Some(i) = self.files
-                                    .iter()
-                                    .position(|n| n.file_name.replace(".java", "") == name)
-        {
-            Ok(self.files[i].clone())
-        } else if let Some(i) = self.files.iter().position(|n| n.name.clone() == name) {
-            Ok(self.files[i].clone())
-        } else if let Some(i) = self.files
-                                    .iter()
-                                    .position(|n| n.path.display().to_string() == name)
-        {
-            Ok(self.files[i].clone())
-        } else if let Some(i) = self.files.iter().position(|n| n.proper_name == name) {
-            Ok(self.files[i].clone())
-        } else {
-            bail!("Could not find {} in the project", name)
+        match self.candidates(name).as_slice() {
+            [] => bail!("Could not find {} in the project", name),
+            [file] => Ok((*file).clone()),
+            candidates => {
+                let candidates =
+                    candidates.iter()
+                              .map(|f| format!("  - {} ({})", f.proper_name, f.path.display()))
+                              .collect::<Vec<_>>()
+                              .join("\n");
+                bail!("{} is ambiguous, it could refer to any of:\n{}\nPlease use a fully \
+                       qualified name or a path instead.",
+                      name,
+                      candidates)
+            }
         }
     }
 
-    /// Returns true if project contains a file with the given name.
+    /// Returns true if project contains a file with the given name, even if
+    /// the name is ambiguous.
     pub fn contains(&self,
                     name: &str)
                     -> bool {
-        self.identify(name).is_ok()
+        !self.candidates(name).is_empty()
     }
 
     /// Downloads certain libraries like JUnit if found in imports.
@@ -1174,139 +1278,60 @@ impl Project {
                 std::fs::create_dir(LIB_DIR.as_path()).unwrap();
             }
 
-            let handle1 = tokio::spawn(async {
-                download(
-                    "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/junit-platform-console-standalone-1.10.2.jar",
-                    &LIB_DIR.join(JUNIT_PLATFORM),
-                false
-                        )
-                        .await
-            });
-
-            let handle2 = tokio::spawn(async {
-                download("https://ummfiles.fra1.digitaloceanspaces.com/jar_files/junit-4.13.2.jar",
-                         &LIB_DIR.join("junit-4.13.2.jar"),
-                         false).await
-            });
-
-            let handle3 = tokio::spawn(async {
-                download("https://ummfiles.fra1.digitaloceanspaces.com/jar_files/pitest-1.16.1.jar",
-                         &LIB_DIR.join("pitest.jar"),
-                         false).await
-            });
-
-            let handle4 = tokio::spawn(async {
-                download(
-                        "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/pitest-command-line-1.16.1.jar",
-                        &LIB_DIR.join("pitest-command-line.jar"),
-                        false,
-                    )
-                    .await
-            });
-
-            let handle5 = tokio::spawn(async {
-                download(
-                    "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/pitest-entry-1.16.1.jar",
-                    &LIB_DIR.join("pitest-entry.jar"),
-                    false,
-                )
-                .await
-            });
-
-            let handle6 = tokio::spawn(async {
-                download(
-                        "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/pitest-junit5-plugin-1.2.1.jar",
-                        &LIB_DIR.join("pitest-junit5-plugin.jar"),
-                        false,
-                    )
-                    .await
-            });
-
-            let handle7 = tokio::spawn(async {
-                download(
-                        "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/commons-text-1.12.0.jar",
-                        &LIB_DIR.join("commons-text-1.12.0.jar"),
-                        false,
-                    )
-                    .await
-            });
-
-            let handle8 = tokio::spawn(async {
-                download(
-                        "https://ummfiles.fra1.digitaloceanspaces.com/jar_files/commons-lang3-3.14.0.jar",
-                        &LIB_DIR.join("commons-lang3-3.14.0.jar"),
-                        false,
-                    )
-                    .await
-            });
-
-            let handles = FuturesUnordered::from_iter([handle1, handle2, handle3, handle4,
-                                                       handle5, handle6, handle7, handle8]);
+            let handles = LIBRARIES.iter()
+                                   .map(|(name, url)| {
+                                       tokio::spawn(async move {
+                                           download(url, &LIB_DIR.join(name), false).await
+                                       })
+                                   })
+                                   .collect::<FuturesUnordered<_>>();
 
             futures::future::try_join_all(handles).await?;
         }
         Ok(())
     }
 
-    /// Creates a vscode settings.json file for the project.
-    pub async fn update_vscode_settings(&self) -> Result<()> {
-        // TODO: Move this to an init function that takes CONSTANTS into account
-        if !ROOT_DIR.join(".vscode").as_path().is_dir() {
-            tokio::fs::create_dir(ROOT_DIR.join(".vscode").as_path()).await
-                                                                     .unwrap();
-        }
+    /// Blocking version of [`Project::download_libraries_if_needed`].
+    pub fn ensure_libraries(&self) -> Result<()> {
+        let rt = RUNTIME.handle().clone();
+        rt.block_on(self.download_libraries_if_needed())
+    }
 
-        if !ROOT_DIR.join(".vscode/settings.json").as_path().exists() {
-            let mut file = tokio::fs::OpenOptions::new().write(true)
-                                                        .truncate(true)
-                                                        .create(true)
-                                                        .open(ROOT_DIR.join(".vscode")
-                                                                      .join("settings.json")
-                                                                      .as_path())
-                                                        .await?;
-
-            let settings =
-                vscode::SettingsFile::builder().java_source_path(self.sourcepath.clone())
-                                               .java_output_path(BUILD_DIR.join("")
-                                                                          .display()
-                                                                          .to_string())
-                                               .java_referenced_libs(self.classpath.clone())
-                                               .umm_binary_path(umm_path())
-                                               .build();
-
-            file.write_all(serde_json::to_string_pretty(&settings)?.as_bytes())
-                .await?;
-        }
+    /// Creates or updates the vscode settings.json and extensions.json files
+    /// for the project. Settings umm manages are overwritten, anything else
+    /// the user has set is kept.
+    pub async fn update_vscode_settings(&self) -> Result<()> {
+        let dir = ROOT_DIR.join(".vscode");
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let settings =
+            vscode::SettingsFile::builder().java_source_path(self.sourcepath.clone())
+                                           .java_output_path(BUILD_DIR.join("")
+                                                                      .display()
+                                                                      .to_string())
+                                           .java_referenced_libs(self.classpath.clone())
+                                           .umm_binary_path(umm_path())
+                                           .build();
+
+        let path = dir.join("settings.json");
+        let existing = vscode::read_jsonc(&path).await?;
+        let merged = vscode::merge_objects(existing.clone(), serde_json::to_value(&settings)?);
+        vscode::write_json(&path, existing.as_ref(), &merged).await?;
 
         // Do the same for extensions.json
-        if !ROOT_DIR.join(".vscode/extensions.json").as_path().exists() {
-            let mut file = tokio::fs::OpenOptions::new().write(true)
-                                                        .truncate(true)
-                                                        .create(true)
-                                                        .open(ROOT_DIR.join(".vscode")
-                                                                      .join("extensions.json")
-                                                                      .as_path())
-                                                        .await?;
-
-            let extensions = r#"
-{
-	// See https://go.microsoft.com/fwlink/?LinkId=827846 to learn about workspace recommendations.
-	// Extension identifier format: ${publisher}.${name}. Example: vscode.csharp
-
-	// List of extensions which should be recommended for users of this workspace.
-	"recommendations": [
-        "vscjava.vscode-java-pack",
-        "ms-vsliveshare.vsliveshare"
-	],
-	// List of extensions recommended by VS Code that should not be recommended for users of this workspace.
-	"unwantedRecommendations": [
-		
-	]
-}
-            "#;
-
-            file.write_all(extensions.as_bytes()).await?;
-        }
+        let path = dir.join("extensions.json");
+        let existing = vscode::read_jsonc(&path).await?;
+        let recommendations =
+            vscode::merge_entries(existing.as_ref().and_then(|e| e.get("recommendations")),
+                                  vec![serde_json::json!("vscjava.vscode-java-pack"),
+                                       serde_json::json!("ms-vsliveshare.vsliveshare")],
+                                  |e| e.as_str().map(String::from),
+                                  |_| false);
+        let merged = vscode::merge_objects(existing.clone(),
+                                           serde_json::json!({
+                                               "recommendations": recommendations
+                                           }));
+        vscode::write_json(&path, existing.as_ref(), &merged).await?;
 
         Ok(())
     }
@@ -1330,10 +1355,38 @@ impl Project {
         result.push_str("> What follows is a summary of the student's submission's files, their \
                          fields and methods generated via treesitter queries.\n\n");
 
+        let starter = starter_code();
+
         for f in self.files.iter() {
             if f.proper_name.contains("Hidden") {
                 continue;
             }
+
+            if let Some(starter) = &starter {
+                match starter.file_change(f) {
+                    Change::Unchanged => {
+                        result.push_str(format!("`{}` is unchanged from the starter code.\n\n",
+                                                f.proper_name).as_str());
+                        continue;
+                    }
+                    Change::Modified => {
+                        let methods = starter.modified_methods(f)
+                                             .into_iter()
+                                             .map(|m| format!("- `{}` ({})", m.name, m.change))
+                                             .collect::<Vec<String>>();
+                        result.push_str(f.description().as_str());
+                        if !methods.is_empty() {
+                            result.push_str("\n\nMethods the student added or modified in the \
+                                             starter code:\n");
+                            result.push_str(methods.join("\n").as_str());
+                        }
+                        result.push_str("\n\n");
+                        continue;
+                    }
+                    Change::Added => {}
+                }
+            }
+
             result.push_str(f.description().as_str());
             result.push_str("\n\n");
         }
@@ -1425,6 +1478,48 @@ impl Project {
                                           .build(),
         );
 
+        // Runs `umm` with `args`, with JUnit waiting for a debugger to attach on
+        // JDWP_PORT. JDK_JAVA_OPTIONS is only read by the `java` launcher, so
+        // javac still compiles normally first.
+        let debug_task = |label: String, args: Vec<&str>| {
+            let args = args.into_iter()
+                           .map(|arg| {
+                               vscode::Args::builder().value(arg)
+                                                      .quoting(vscode::ArgQuoting::Escape)
+                                                      .build()
+                           })
+                           .collect::<Vec<_>>();
+            let env = HashMap::from([("JDK_JAVA_OPTIONS".to_string(),
+                                      format!("-agentlib:jdwp=transport=dt_socket,server=y,\
+                                               suspend=y,address=localhost:{JDWP_PORT}"))]);
+            let pattern =
+                vscode::Pattern::builder().regexp(r"^(.*\.java):(\d+): (error|warning): (.*)$")
+                                          .file(1)
+                                          .line(2)
+                                          .severity(3)
+                                          .message(4)
+                                          .build();
+            let background =
+                vscode::Background::builder().active_begin("Listening for transport dt_socket")
+                                             .active_end("Listening for transport dt_socket")
+                                             .build();
+            let matcher = vscode::ProblemMatcher::builder().owner("umm".to_string())
+                                                           .pattern(pattern)
+                                                           .background(background)
+                                                           .build();
+
+            vscode::Task::builder().label(label)
+                                   .r#type(vscode::Type::Shell)
+                                   .command("${config:ummBinaryPath}")
+                                   .args(args)
+                                   .options(vscode::TaskOptions::builder().env(env).build())
+                                   .is_background(true)
+                                   .problem_matcher(Some(vec![matcher]))
+                                   .depends_on(default_depends_on.clone())
+                                   .depends_order(default_depends_order)
+                                   .build()
+        };
+
         for file in self.files().iter() {
             match file.kind() {
                 FileType::ClassWithMain => {
@@ -1515,6 +1610,37 @@ impl Project {
                                                       .depends_order(default_depends_order)
                                                       .build(),
                     );
+
+                    for method in &test_methods {
+                        tasks.push(
+                                   vscode::Task::builder().label(format!(
+                            "Run test {} from {}",
+                            method, file.name
+                        ))
+                                                          .r#type(vscode::Type::Shell)
+                                                          .command("${config:ummBinaryPath}")
+                                                          .args(vec![
+                            vscode::Args::builder().value("test")
+                                                   .quoting(vscode::ArgQuoting::Escape)
+                                                   .build(),
+                            vscode::Args::builder().value(&file.proper_name)
+                                                   .quoting(vscode::ArgQuoting::Escape)
+                                                   .build(),
+                            vscode::Args::builder().value(method)
+                                                   .quoting(vscode::ArgQuoting::Escape)
+                                                   .build(),
+                        ])
+                                                          .group("test".to_string())
+                                                          .depends_on(default_depends_on.clone())
+                                                          .depends_order(default_depends_order)
+                                                          .build(),
+                        );
+                        tasks.push(debug_task(format!("Debug test {} from {}", method, file.name),
+                                              vec!["test", &file.proper_name, method]));
+                    }
+
+                    tasks.push(debug_task(format!("Debug tests for {}", file.name),
+                                          vec!["test", &file.proper_name]));
                 }
                 _ => {}
             };
@@ -1553,6 +1679,28 @@ impl Project {
             );
         }
 
+        for (label, command) in [("Run current file", "run"),
+                                 ("Test current file", "test"),
+                                 ("Check current file", "check")]
+        {
+            tasks.push(
+                       vscode::Task::builder().label(label.to_string())
+                                              .r#type(vscode::Type::Shell)
+                                              .command("${config:ummBinaryPath}")
+                                              .args(vec![
+                vscode::Args::builder().value(command)
+                                       .quoting(vscode::ArgQuoting::Escape)
+                                       .build(),
+                vscode::Args::builder().value("${fileBasenameNoExtension}")
+                                       .quoting(vscode::ArgQuoting::Escape)
+                                       .build(),
+            ])
+                                              .depends_on(default_depends_on.clone())
+                                              .depends_order(default_depends_order)
+                                              .build(),
+            );
+        }
+
         let rhai_scripts = {
             let scripts = find_files(".rhai", 3, &ROOT_DIR)?.iter()
                                                             .map(|f| f.display().to_string())
@@ -1607,27 +1755,180 @@ impl Project {
                 .build(),
         );
 
-        if !ROOT_DIR.join(".vscode").as_path().exists() {
-            tokio::fs::create_dir(ROOT_DIR.join(".vscode").as_path()).await
-                                                                     .unwrap();
-        }
-
-        let mut file =
-            tokio::fs::OpenOptions::new().write(true)
-                                         .truncate(true)
-                                         .create(true)
-                                         .open(ROOT_DIR.join(".vscode")
-                                                       .join("tasks.json")
-                                                       .as_path())
-                                         .await?;
+        let dir = ROOT_DIR.join(".vscode");
+        tokio::fs::create_dir_all(&dir).await?;
 
         let task_file = vscode::TasksFile::builder().tasks(tasks)
                                                     .inputs(inputs)
                                                     .build();
 
-        file.write_all(serde_json::to_string_pretty(&task_file)?.as_bytes())
-            .await?;
+        // Tasks and inputs the user added are kept, umm's own are replaced
+        let path = dir.join("tasks.json");
+        let existing = vscode::read_jsonc(&path).await?;
+        let mut generated = serde_json::to_value(&task_file)?;
+        for (field, key) in [("tasks", "label"), ("inputs", "id")] {
+            let entries = serde_json::from_value(generated[field].take())?;
+            let merged = vscode::merge_entries(existing.as_ref().and_then(|e| e.get(field)),
+                                               entries,
+                                               |e| vscode::string_field(e, key),
+                                               |e| field == "tasks" && vscode::is_umm_task(e));
+            generated[field] = serde_json::Value::Array(merged);
+        }
+        let merged = vscode::merge_objects(existing.clone(), generated);
+        vscode::write_json(&path, existing.as_ref(), &merged).await?;
+
+        let keybindings = [("ctrl+alt+r", "Run current file"),
+                           ("ctrl+alt+t", "Test current file"),
+                           ("ctrl+alt+c", "Check current file"),
+                           ("ctrl+alt+g", "Grade Assignment")].into_iter()
+                                                              .map(|(key, task)| {
+                                                                  vscode::KeyBindings::builder()
+                                .key(key.to_string())
+                                .args(task.to_string())
+                                .build()
+                                                              })
+                                                              .collect::<Vec<_>>();
+
+        let path = dir.join("keybindings.json");
+        let existing = vscode::read_jsonc(&path).await?;
+        let keybindings = serde_json::from_value(serde_json::to_value(&keybindings)?)?;
+        let merged = vscode::merge_entries(existing.as_ref(),
+                                           keybindings,
+                                           |_| None,
+                                           vscode::is_umm_keybinding);
+        vscode::write_json(&path, existing.as_ref(), &serde_json::Value::Array(merged)).await?;
+
+        Ok(())
+    }
+
+    /// Writes a .vscode/launch.json file for the project, with a debug
+    /// configuration for each class with a main method, each test class, and
+    /// each test.
+    pub async fn update_vscode_launch(&self) -> Result<()> {
+        let mut configurations = Vec::new();
+
+        for file in self.files().iter() {
+            match file.kind {
+                FileType::ClassWithMain => {
+                    configurations.push(
+                        vscode::LaunchConfiguration::builder()
+                            .name(format!("Debug {}", file.name))
+                            .request(vscode::Request::Launch)
+                            .main_class(file.proper_name.clone())
+                            .console("integratedTerminal".to_string())
+                            .pre_launch_task(format!("Check {}", file.name))
+                            .build(),
+                    );
+                }
+                FileType::Test => {
+                    configurations.push(
+                        vscode::LaunchConfiguration::builder()
+                            .name(format!("Debug tests for {}", file.name))
+                            .request(vscode::Request::Attach)
+                            .host_name("localhost".to_string())
+                            .port(JDWP_PORT)
+                            .pre_launch_task(format!("Debug tests for {}", file.name))
+                            .build(),
+                    );
+
+                    for method in file.test_methods() {
+                        let Some((_, method)) = method.split_once('#') else {
+                            continue;
+                        };
+                        let label = format!("Debug test {} from {}", method, file.name);
+                        configurations.push(
+                            vscode::LaunchConfiguration::builder()
+                                .name(label.clone())
+                                .request(vscode::Request::Attach)
+                                .host_name("localhost".to_string())
+                                .port(JDWP_PORT)
+                                .pre_launch_task(label)
+                                .build(),
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let dir = ROOT_DIR.join(".vscode");
+        tokio::fs::create_dir_all(&dir).await?;
+
+        let launch_file = vscode::LaunchFile::builder().configurations(configurations)
+                                                       .build();
+
+        // Configurations the user added are kept, umm's own are replaced
+        let path = dir.join("launch.json");
+        let existing = vscode::read_jsonc(&path).await?;
+        let mut generated = serde_json::to_value(&launch_file)?;
+        let configurations = serde_json::from_value(generated["configurations"].take())?;
+        let merged = vscode::merge_entries(existing.as_ref().and_then(|e| e.get("configurations")),
+                                           configurations,
+                                           |e| vscode::string_field(e, "name"),
+                                           vscode::is_umm_launch_configuration);
+        generated["configurations"] = serde_json::Value::Array(merged);
+        let merged = vscode::merge_objects(existing.clone(), generated);
+        vscode::write_json(&path, existing.as_ref(), &merged).await?;
+
+        Ok(())
+    }
+
+    /// Writes an IntelliJ IDEA module file along with `.idea/modules.xml` and
+    /// `.idea/misc.xml`, using the project's sourcepath and classpath.
+    pub async fn update_intellij_project(&self) -> Result<()> {
+        let layout = ProjectLayout::new(&self.sourcepath, &self.classpath);
+
+        tokio::fs::create_dir_all(ROOT_DIR.join(".idea")).await?;
+        tokio::fs::write(ROOT_DIR.join(format!("{}.iml", layout.name())),
+                         layout.intellij_module()).await?;
+        tokio::fs::write(ROOT_DIR.join(".idea").join("modules.xml"),
+                         layout.intellij_modules()).await?;
+        tokio::fs::write(ROOT_DIR.join(".idea").join("misc.xml"),
+                         layout.intellij_misc()).await?;
+
+        Ok(())
+    }
+
+    /// Writes Eclipse `.project` and `.classpath` files, using the project's
+    /// sourcepath and classpath.
+    pub async fn update_eclipse_project(&self) -> Result<()> {
+        let layout = ProjectLayout::new(&self.sourcepath, &self.classpath);
+
+        tokio::fs::write(ROOT_DIR.join(".project"), layout.eclipse_project()).await?;
+        tokio::fs::write(ROOT_DIR.join(".classpath"), layout.eclipse_classpath()).await?;
+
+        Ok(())
+    }
+
+    /// Downloads any libraries the project needs, and then generates project
+    /// files for the given editor.
+    ///
+    /// * `editor`: editor to generate project files for
+    pub fn init(&self,
+                editor: Editor)
+                -> Result<()> {
+        let rt = RUNTIME.handle().clone();
+        rt.block_on(async {
+              // Libraries have to be in place first, Eclipse lists each jar
+              self.download_libraries_if_needed().await?;
+
+              match editor {
+                  Editor::VsCode => {
+                      self.update_vscode_settings().await?;
+                      self.update_vscode_tasks().await?;
+                      self.update_vscode_launch().await
+                  }
+                  Editor::IntelliJ => self.update_intellij_project().await,
+                  Editor::Eclipse => self.update_eclipse_project().await,
+              }
+          })?;
 
+        println!("Generated {editor} project files in {}", ROOT_DIR.display());
+        if let Editor::VsCode = editor {
+            println!("VS Code only reads keybindings from your user settings. To use umm's, copy \
+                      the ones in .vscode/keybindings.json into yours, with \"Preferences: Open \
+                      Keyboard Shortcuts (JSON)\". They only apply in umm projects.");
+        }
         Ok(())
     }
 
@@ -1682,9 +1983,16 @@ pub struct SubmissionRow {
 impl CustomType for Project {
     fn build(mut builder: rhai::TypeBuilder<Self>) {
         builder.with_name("JavaProject")
+               .with_comments(&["/// The Java project being graded, made up of every discovered",
+                                "/// source and test file"])
                .with_fn("new_java_project", Project::new_script)
+               .and_comments(&["/// Discovers the project in the current folder"])
                .with_fn("identify", Project::identify_mut_script)
+               .and_comments(&["/// Finds the file declaring a class, by name or fully qualified",
+                               "/// name"])
                .with_fn("files", Project::files)
-               .with_fn("info", Project::info_script);
+               .and_comments(&["/// Every source and test file in the project"])
+               .with_fn("info", Project::info_script)
+               .and_comments(&["/// Prints information about the project's files as JSON"]);
     }
 }
@@ -10,29 +10,79 @@
 #![feature(let_chains)]
 #![feature(iter_collect_into)]
 
+/// For running grading scripts written for older versions of the scripting
+/// API
+pub mod compat;
+/// For project settings, read from `umm.toml`
+pub mod config;
 /// A module defining a bunch of constant values to be used throughout
 pub mod constants;
+/// For finding the Java files that make up a project
+pub mod discovery;
+/// For generating IntelliJ IDEA and Eclipse project files
+pub mod editors;
 /// For all things related to grading
 pub mod grade;
 /// For all things related to project health
 pub mod health;
+/// For importing shared modules into grading scripts
+pub mod imports;
 /// For discovering Java projects, analyzing them, and generating/executing
 /// build tasks
 pub mod java;
+/// For limiting what grading scripts can do
+pub mod limits;
+/// For a language server that publishes diagnostics to editors
+pub mod lsp;
+/// For computing code metrics such as cyclomatic complexity
+pub mod metrics;
+/// For the score and status grading scripts report
+pub mod outcome;
 /// For all parsers used
 pub mod parsers;
+/// For late penalties and grace periods
+pub mod policy;
+/// For generating a reference of the scripting API
+pub mod reference;
+/// For looking up grading scripts by assignment name
+pub mod registry;
+/// For grading with declarative rubrics instead of scripts
+pub mod rubric;
+/// For checking grading scripts before students run them
+pub mod script;
+/// For signing grading scripts, and attesting to grades
+pub mod signing;
+/// For detecting similar code across submissions
+pub mod similarity;
+/// For describing the expected structure of classes and interfaces
+pub mod spec;
+/// For comparing a submission against the starter code handed out to students
+pub mod starter;
+/// For built-in style and lint rules
+pub mod style;
+/// For finding Java installations and picking the one to use
+pub mod toolchain;
 /// Utility functions for convenience
 pub mod util;
 /// For structs and enums related to VSCode Tasks
 pub mod vscode;
 
 use anyhow::{Context, Result};
+use compat::{declared_api_version, prints_to_stdout, register_shims};
 use constants::{
-    BUILD_DIR, COURSE, LIB_DIR, POSTGREST_CLIENT, ROOT_DIR, RUNTIME, SCRIPT_AST, TERM,
+    BUILD_DIR, GRADED_SCRIPT_SHA256, LIB_DIR, SCRIPT_API_VERSION, SCRIPT_AST, STD_MODULE_NAME,
 };
 use grade::*;
+use imports::{module_path, std_module, ScriptModuleResolver};
 use java::{File, FileType, Parser, Project};
-use rhai::{Engine, EvalAltResult};
+use limits::EngineLimits;
+use outcome::{emit_score, report_outcome, reset_outcome, set_status};
+use policy::{use_late_policy_script, LatePolicy};
+use registry::{resolve, sha256_hex};
+use rhai::{Engine, EvalAltResult, FuncRegistration};
+use rubric::grade_rubric;
+use signing::verify_script;
+use starter::use_starter_code_script;
 use umm_derive::generate_rhai_variant;
 use util::{use_active_retrieval, use_heuristic_retrieval};
 
@@ -43,84 +93,142 @@ type Dict = std::collections::HashMap<String, String>;
 /// registered
 pub fn create_engine() -> Engine {
     let mut engine = Engine::new();
+    EngineLimits::configured().apply(&mut engine);
     engine.register_type_with_name::<FileType>("JavaFileType")
           .build_type::<DocsGrader>()
+          .build_type::<StyleGrader>()
+          .build_type::<MetricsGrader>()
+          .build_type::<ApiGrader>()
           .build_type::<ByUnitTestGrader>()
           .build_type::<UnitTestGrader>()
           .build_type::<ByHiddenTestGrader>()
           .build_type::<DiffGrader>()
+          .build_type::<CustomGrader>()
+          .build_type::<GraderContext>()
           .build_type::<Grade>()
           .build_type::<GradeResult>()
           .build_type::<Parser>()
           .build_type::<File>()
           .build_type::<Query>()
           .build_type::<QueryGrader>()
-          .build_type::<Project>()
-          .register_fn("clean", clean_script)
-          .register_fn("show_results", show_result_script)
-          .register_fn("generate_single_feedback", generate_single_feedback_script)
-          .register_fn("generate_feedback", generate_feedback_script)
-          .register_fn("use_active_retrieval", use_active_retrieval)
-          .register_fn("use_heuristic_retrieval", use_heuristic_retrieval);
+          .build_type::<LatePolicy>()
+          .build_type::<Project>();
+
+    let documented = |name: &str, comment: &str| {
+        FuncRegistration::new(name).with_comments([format!("/// {comment}")])
+    };
+    documented("clean", "Deletes compiled classes and downloaded libraries")
+        .register_into_engine(&mut engine, clean_script);
+    documented("show_results",
+               "Prints a table of results, and writes results.json when run on Gradescope")
+        .register_into_engine(&mut engine, show_result_script);
+    documented("generate_single_feedback",
+               "Generates feedback for a result without full marks, and posts it")
+        .register_into_engine(&mut engine, generate_single_feedback_script);
+    documented("generate_feedback",
+               "Writes a FEEDBACK file for every result without full marks")
+        .register_into_engine(&mut engine, generate_feedback_script);
+    documented("use_active_retrieval",
+               "Lets feedback prompts ask for the parts of the submission they need")
+        .register_into_engine(&mut engine, use_active_retrieval);
+    documented("use_heuristic_retrieval",
+               "Picks the parts of the submission feedback prompts include")
+        .register_into_engine(&mut engine, use_heuristic_retrieval);
+    documented("use_starter_code",
+               "Registers starter code, so graders can focus on what the student wrote")
+        .register_into_engine(&mut engine, use_starter_code_script);
+    documented("use_late_policy",
+               "Registers a late policy, so show_results deducts points for late submissions")
+        .register_into_engine(&mut engine, use_late_policy_script);
+    documented("emit_score", "Reports the score, written to stdout once the script finishes")
+        .register_into_engine(&mut engine, emit_score);
+    documented("emit_score", "Reports the score, written to stdout once the script finishes")
+        .register_into_engine(&mut engine, |score: i64| emit_score(score as f64));
+    documented("set_status", "Reports whether the submission passed, \"pass\" or \"fail\"")
+        .register_into_engine(&mut engine, set_status);
+
+    match std_module(&engine) {
+        Ok(module) => {
+            engine.register_static_module(STD_MODULE_NAME, module);
+        }
+        Err(e) => tracing::warn!("{e:#}"),
+    }
+    engine.set_module_resolver(ScriptModuleResolver::new(module_path()));
+
+    engine
+}
+
+/// Creates an `Engine` for a script, with compatibility shims for the
+/// functions the version of the scripting API it declares had
+///
+/// * `script`: source of the script
+pub fn create_engine_for(script: &str) -> Result<Engine> {
+    Ok(create_engine_for_version(declared_api_version(script)?))
+}
+
+/// Creates an `Engine` for scripts declaring `version` of the scripting API,
+/// with its compatibility shims and where it sends `print` output
+///
+/// * `version`: the declared version, if any
+pub fn create_engine_for_version(version: Option<u32>) -> Engine {
+    let mut engine = create_engine();
+    register_shims(&mut engine, version);
+    if !prints_to_stdout(version) {
+        // Keeps stdout for the outcome, so stray output can't pass for a score
+        engine.on_print(|s| eprintln!("{s}"));
+    }
     engine
 }
 
-/// Prints the result of grading
+/// Creates an `Engine` for graders to call back into the running script with,
+/// like custom grader logic and query filters, set up the same way as the
+/// one running it
+pub fn create_callback_engine() -> Engine {
+    create_engine_for_version(*SCRIPT_API_VERSION.lock().unwrap())
+}
+
+/// Reads a grading script from a file, or fetches the one for an assignment
+/// from the configured script source
+///
+/// * `name_or_path`: name of an assignment, or path to a script
+pub fn read_script(name_or_path: &str) -> Result<String> {
+    Ok(resolve(name_or_path)?.code)
+}
+
+/// Prints the result of grading. Rubrics, files ending in `.toml`, `.yaml` or
+/// `.yml`, are graded without a script.
 pub fn grade(name_or_path: &str) -> Result<()> {
-    let engine = create_engine();
-
-    // println!("{}", engine.gen_fn_signatures(false).join("\n"));
-    let script = match std::fs::read_to_string(name_or_path) {
-        Ok(s) => s,
-        Err(_) => {
-            let assignment_name = name_or_path.to_string().replace(['\"', '\\'], "");
-            let rt = RUNTIME.handle().clone();
-
-            let resp = rt.block_on(async {
-                             POSTGREST_CLIENT.from("grading_scripts")
-                                             .eq("course", COURSE)
-                                             .eq("term", TERM)
-                                             .eq("assignment", &assignment_name)
-                                             .select("url")
-                                             .single()
-                                             .execute()
-                                             .await?
-                                             .text()
-                                             .await
-                                             .context(format!("Could not get grading script for \
-                                                               {assignment_name}"))
-                         });
-
-            let resp: serde_json::Value = serde_json::from_str(resp?.as_str())?;
-            let resp = resp.as_object().unwrap();
-
-            if let Some(message) = resp.get("message") {
-                anyhow::bail!("Error for {assignment_name}: {message}");
-            }
-
-            let script_url = resp.get("url").unwrap().as_str().unwrap();
-
-            reqwest::blocking::get(script_url).context(format!("Cannot get url: {script_url}"))?
-                                              .text()
-                                              .context(format!("Could not parse the response \
-                                                                from {script_url} to text."))?
-        }
-    };
-    let ast = engine.compile(script)?;
-    {
-        let ast = std::sync::Arc::clone(&SCRIPT_AST);
-        let mut ast = ast.lock().unwrap();
-        *ast = ast.clone();
+    let script = resolve(name_or_path)?;
+    verify_script(&script)?;
+    *GRADED_SCRIPT_SHA256.lock().unwrap() = Some(sha256_hex(script.code.as_bytes()));
+
+    if let Some(format) = script.rubric_format() {
+        return grade_rubric(&script, format);
     }
 
+    let engine = create_engine_for(&script.code)?;
+    let mut ast = engine.compile(&script.code)?;
+    // Lets the script import modules from its own folder
+    ast.set_source(script.location.as_str());
+
+    // Project::new doesn't download anything, but graders need JUnit and PIT
+    Project::new()?.ensure_libraries()?;
+
+    // Graders that call back into the script, like query filters, need its AST
+    *SCRIPT_AST.lock().unwrap() = ast.clone();
+    *SCRIPT_API_VERSION.lock().unwrap() = declared_api_version(&script.code)?;
+
     // Run the script
+    reset_outcome();
     engine.run_ast(&ast)?;
+    report_outcome()?;
 
     Ok(())
 }
 
 #[generate_rhai_variant(Fallible)]
-/// Deletes all java compiler artefacts
+/// Deletes all java compiler artefacts and downloaded libraries. Editor
+/// settings are left alone, `umm init` merges into them.
 pub fn clean() -> Result<()> {
     if BUILD_DIR.as_path().exists() {
         std::fs::remove_dir_all(BUILD_DIR.as_path()).with_context(|| {
@@ -134,24 +242,6 @@ pub fn clean() -> Result<()> {
                                                               LIB_DIR.display())
                                                   })?;
     }
-    if ROOT_DIR.join(".vscode/settings.json").as_path().exists() {
-        std::fs::remove_file(ROOT_DIR.join(".vscode/settings.json").as_path()).with_context(
-            || {
-                format!(
-                    "Could not delete {}",
-                    ROOT_DIR.join(".vscode/settings.json").display()
-                )
-            },
-        )?;
-    }
-    if ROOT_DIR.join(".vscode/tasks.json").as_path().exists() {
-        std::fs::remove_file(ROOT_DIR.join(".vscode/tasks.json").as_path()).with_context(|| {
-                                                                               format!(
-                "Could not delete {}",
-                ROOT_DIR.join(".vscode/tasks.json").display()
-            )
-                                                                           })?;
-    }
 
     Ok(())
 }
@@ -0,0 +1,99 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use rhai::Engine;
+
+use crate::constants::ENGINE_LIMITS_ENV;
+
+/// Limits on what a grading script can do, so one that loops forever or
+/// builds huge values fails instead of hanging the grader. Graders count as
+/// single operations, so slow Java processes aren't limited by them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EngineLimits {
+    /// most operations a script can run
+    pub operations:  u64,
+    /// deepest function calls can nest
+    pub call_levels: usize,
+    /// longest a string can be, in bytes
+    pub string_size: usize,
+    /// most items an array can have
+    pub array_size:  usize,
+    /// most properties an object map can have
+    pub map_size:    usize,
+}
+
+impl Default for EngineLimits {
+    fn default() -> Self {
+        Self { operations:  10_000_000,
+               call_levels: 64,
+               string_size: 10 * 1024 * 1024,
+               array_size:  100_000,
+               map_size:    100_000, }
+    }
+}
+
+impl Display for EngineLimits {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        write!(f,
+               "operations={},call_levels={},string_size={},array_size={},map_size={}",
+               self.operations, self.call_levels, self.string_size, self.array_size, self.map_size)
+    }
+}
+
+impl FromStr for EngineLimits {
+    type Err = anyhow::Error;
+
+    /// Parses limits written like `operations=50000000,call_levels=128`.
+    /// Limits that aren't given keep their defaults.
+    fn from_str(s: &str) -> Result<Self> {
+        let mut limits = Self::default();
+        for entry in s.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((key, value)) = entry.split_once('=') else {
+                bail!("Expected `name=value`, found `{entry}`");
+            };
+            let value = value.trim();
+            let invalid = || format!("Invalid value for {}: {value}", key.trim());
+            match key.trim() {
+                "operations" => limits.operations = value.parse().with_context(invalid)?,
+                "call_levels" => limits.call_levels = value.parse().with_context(invalid)?,
+                "string_size" => limits.string_size = value.parse().with_context(invalid)?,
+                "array_size" => limits.array_size = value.parse().with_context(invalid)?,
+                "map_size" => limits.map_size = value.parse().with_context(invalid)?,
+                key => bail!("Unknown limit: {key}. Expected one of: operations, call_levels, \
+                              string_size, array_size, map_size"),
+            }
+        }
+        Ok(limits)
+    }
+}
+
+impl EngineLimits {
+    /// The limits set with [ENGINE_LIMITS_ENV], or the defaults. Invalid
+    /// settings are warned about and ignored.
+    pub fn configured() -> Self {
+        match std::env::var(ENGINE_LIMITS_ENV) {
+            Ok(value) => value.parse().unwrap_or_else(|e| {
+                                          tracing::warn!("Ignoring {ENGINE_LIMITS_ENV}: {e:#}");
+                                          Self::default()
+                                      }),
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Applies the limits to an engine
+    ///
+    /// * `engine`: the engine
+    pub fn apply(&self,
+                 engine: &mut Engine) {
+        engine.set_max_operations(self.operations)
+              .set_max_call_levels(self.call_levels)
+              .set_max_string_size(self.string_size)
+              .set_max_array_size(self.array_size)
+              .set_max_map_size(self.map_size);
+    }
+}
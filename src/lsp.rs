@@ -0,0 +1,684 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    collections::HashMap,
+    io::{BufRead, Write},
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use anyhow::{Context, Result};
+use serde::{Serialize, Serializer};
+use serde_json::{json, Value};
+use tree_sitter::{Query, QueryCursor};
+
+use crate::{
+    constants::{MAIN_METHOD_QUERY, TEST_ANNOTATION_QUERY},
+    java::{File, JavaFileError, Parser, Project},
+    parsers::parser,
+    style::{lint, StyleOptions, StyleRule},
+};
+
+/// Command sent by the "Run" code lens
+const RUN_COMMAND: &str = "umm.run";
+
+/// Command sent by the "Test" code lens
+const TEST_COMMAND: &str = "umm.test";
+
+/// JSON-RPC error code for unknown methods
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// JSON-RPC error code for errors while handling a request
+const INTERNAL_ERROR: i64 = -32603;
+
+#[derive(Serialize, Clone, Copy, Debug)]
+/// A zero-based position in a text document
+pub struct Position {
+    /// line, starting at 0
+    pub line:      u32,
+    /// character offset on the line, starting at 0
+    pub character: u32,
+}
+
+#[derive(Serialize, Clone, Copy, Debug)]
+/// A range in a text document
+pub struct Range {
+    /// start of the range, inclusive
+    pub start: Position,
+    /// end of the range, exclusive
+    pub end:   Position,
+}
+
+#[derive(Clone, Copy, Debug)]
+/// Severity of a diagnostic
+pub enum Severity {
+    /// Reports an error
+    Error       = 1,
+    /// Reports a warning
+    Warning     = 2,
+    /// Reports an information
+    Information = 3,
+}
+
+impl Serialize for Severity {
+    fn serialize<S: Serializer>(&self,
+                                serializer: S)
+                                -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+/// A diagnostic, such as a compiler error or a lint, shown by the editor
+pub struct Diagnostic {
+    /// where the diagnostic applies
+    pub range:    Range,
+    /// how severe the diagnostic is
+    pub severity: Severity,
+    /// what produced the diagnostic, e.g. `javac`
+    pub source:   String,
+    /// the diagnostic's code, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code:     Option<String>,
+    /// the diagnostic message
+    pub message:  String,
+}
+
+#[derive(Serialize, Clone, Debug)]
+/// A command the editor can ask the server to execute
+pub struct Command {
+    /// title shown in the editor
+    pub title:     String,
+    /// identifier of the command
+    pub command:   String,
+    /// arguments passed back when the command is executed
+    pub arguments: Vec<Value>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+/// A command shown inline with source code
+pub struct CodeLens {
+    /// the range the lens applies to
+    pub range:   Range,
+    /// the command to execute when the lens is clicked
+    pub command: Command,
+}
+
+/// Reads a single JSON-RPC message framed with a `Content-Length` header.
+/// Returns `None` once the input is closed.
+///
+/// * `reader`: where to read the message from
+fn read_message(reader: &mut impl BufRead) -> Result<Option<Value>> {
+    let mut length = None;
+
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(value) = line.strip_prefix("Content-Length:") {
+            length = Some(value.trim().parse::<usize>()?);
+        }
+    }
+
+    let length = length.context("Message is missing a Content-Length header")?;
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// Writes a single JSON-RPC message to stdout, framed with a `Content-Length`
+/// header.
+///
+/// * `message`: the message to write
+fn write_message(message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    let mut stdout = std::io::stdout().lock();
+
+    write!(stdout, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    stdout.flush()?;
+    Ok(())
+}
+
+/// Converts a `file://` URI to a path.
+///
+/// * `uri`: the URI to convert
+fn uri_to_path(uri: &str) -> Option<PathBuf> {
+    let path = uri.strip_prefix("file://")?;
+    let mut bytes = vec![];
+    let mut chars = path.bytes();
+
+    while let Some(b) = chars.next() {
+        if b == b'%' {
+            let hex = [chars.next()?, chars.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(b);
+        }
+    }
+
+    let path = String::from_utf8(bytes).ok()?;
+    // `file:///C:/...` on windows
+    let path = match path.strip_prefix('/') {
+        Some(p) if cfg!(windows) => p.to_string(),
+        _ => path,
+    };
+
+    Some(PathBuf::from(path))
+}
+
+/// Converts a path to a `file://` URI.
+///
+/// * `path`: the path to convert
+fn path_to_uri(path: &Path) -> String {
+    let path = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    let path = path.display().to_string().replace('\\', "/");
+    let path = path.replace('%', "%25").replace(' ', "%20");
+
+    if path.starts_with('/') {
+        format!("file://{path}")
+    } else {
+        format!("file:///{path}")
+    }
+}
+
+/// Returns a range spanning an entire line of `code`.
+///
+/// * `code`: source code the line belongs to
+/// * `line_number`: line number, starting at 1
+fn line_range(code: &str,
+              line_number: usize)
+              -> Range {
+    let line = line_number.saturating_sub(1);
+    let length = code.lines()
+                     .nth(line)
+                     .map(|l| l.chars().count())
+                     .unwrap_or_default();
+
+    Range { start: Position { line:      line as u32,
+                              character: 0, },
+            end:   Position { line:      line as u32,
+                              character: length as u32, }, }
+}
+
+/// Runs a tree-sitter query and returns the range and text of each node
+/// captured with `capture`.
+///
+/// * `parser`: parser for the source code to query
+/// * `query`: the query to run
+/// * `capture`: name of the capture to return
+fn query_ranges(parser: &Parser,
+                query: &str,
+                capture: &str)
+                -> Result<Vec<(Range, String)>> {
+    let tree = parser.tree().context("Treesitter could not parse code")?;
+    let query = Query::new(&tree_sitter_java::language(), query)?;
+    let index = query.capture_index_for_name(capture)
+                     .with_context(|| format!("Query has no capture named {capture}"))?;
+    let code = parser.source();
+
+    let mut cursor = QueryCursor::new();
+    let mut ranges = vec![];
+    for m in cursor.matches(&query, tree.root_node(), code.as_bytes()) {
+        for c in m.captures.iter().filter(|c| c.index == index) {
+            let start = c.node.start_position();
+            let end = c.node.end_position();
+            ranges.push((Range { start: Position { line:      start.row as u32,
+                                                   character: start.column as u32, },
+                                 end:   Position { line:      end.row as u32,
+                                                   character: end.column as u32, }, },
+                         c.node.utf8_text(code.as_bytes())?.to_string()));
+        }
+    }
+
+    Ok(ranges)
+}
+
+/// A language server that publishes umm's diagnostics and offers code lenses
+/// to run and test code.
+struct Server {
+    /// the project being edited, re-discovered whenever a file is saved
+    project:          Project,
+    /// contents of open documents, keyed by URI
+    documents:        HashMap<String, String>,
+    /// last published javac and style diagnostics, keyed by URI
+    diagnostics:      HashMap<String, Vec<Diagnostic>>,
+    /// diagnostics from the last run of each test, keyed by test name
+    test_diagnostics: HashMap<String, Vec<(String, Diagnostic)>>,
+}
+
+impl Server {
+    /// Creates a new server for the project in the current directory.
+    fn new() -> Result<Self> {
+        Ok(Self { project:          Project::new()?,
+                  documents:        HashMap::new(),
+                  diagnostics:      HashMap::new(),
+                  test_diagnostics: HashMap::new(), })
+    }
+
+    /// Sends a notification to the editor.
+    ///
+    /// * `method`: the notification method
+    /// * `params`: the notification parameters
+    fn notify(&self,
+              method: &str,
+              params: Value)
+              -> Result<()> {
+        write_message(&json!({
+                          "jsonrpc": "2.0",
+                          "method": method,
+                          "params": params,
+                      }))
+    }
+
+    /// Appends a message to the editor's output log for this server.
+    ///
+    /// * `message`: the message to log
+    fn log(&self,
+           message: &str)
+           -> Result<()> {
+        self.notify("window/logMessage",
+                    json!({ "type": 4, "message": message }))
+    }
+
+    /// Shows a message to the user.
+    ///
+    /// * `message`: the message to show
+    /// * `is_error`: whether the message is an error
+    fn show(&self,
+            message: &str,
+            is_error: bool)
+            -> Result<()> {
+        self.notify("window/showMessage",
+                    json!({ "type": if is_error { 1 } else { 3 }, "message": message }))
+    }
+
+    /// Returns the project file a URI points to.
+    ///
+    /// * `uri`: URI of the file
+    fn file(&self,
+            uri: &str)
+            -> Result<File> {
+        let path = uri_to_path(uri).with_context(|| format!("Not a file URI: {uri}"))?;
+        let path = path.canonicalize().unwrap_or(path);
+
+        self.project
+            .files()
+            .iter()
+            .find(|f| f.path().canonicalize().as_ref().unwrap_or(f.path()) == &path)
+            .cloned()
+            .with_context(|| format!("{} is not part of this project", path.display()))
+    }
+
+    /// Computes javac, javadoc, and style diagnostics for a file.
+    ///
+    /// * `file`: the file to compute diagnostics for
+    fn check(&self,
+             file: &File)
+             -> Result<Vec<Diagnostic>> {
+        let code = file.parser().source().to_string();
+        let mut diagnostics = vec![];
+
+        let output = match file.doc_check() {
+            Ok(output) => output,
+            Err(JavaFileError::DuringCompilation { stacktrace, .. }) => stacktrace,
+            Err(e) => {
+                self.log(&format!("Could not run javac on {}: {e}", file.file_name()))?;
+                String::new()
+            }
+        };
+
+        for diag in output.lines()
+                          .filter_map(|l| parser::parse_diag(l).ok())
+                          .filter(|d| d.file_name() == file.file_name())
+        {
+            diagnostics.push(Diagnostic { range:    line_range(&code, diag.line_number() as usize),
+                                          severity: if diag.is_error() {
+                                              Severity::Error
+                                          } else {
+                                              Severity::Warning
+                                          },
+                                          source:   "javac".to_string(),
+                                          code:     None,
+                                          message:  diag.message().to_string(), });
+        }
+
+        for violation in lint(file, &StyleRule::ALL, StyleOptions::default())? {
+            diagnostics.push(Diagnostic { range:    line_range(&code, violation.line_number),
+                                          severity: Severity::Information,
+                                          source:   "umm-style".to_string(),
+                                          code:     Some(violation.rule.to_string()),
+                                          message:  violation.message, });
+        }
+
+        Ok(diagnostics)
+    }
+
+    /// Publishes the last computed diagnostics for a URI, along with
+    /// diagnostics from failing tests.
+    ///
+    /// * `uri`: URI of the file to publish diagnostics for
+    fn publish(&self,
+               uri: &str)
+               -> Result<()> {
+        let diagnostics: Vec<&Diagnostic> = self.diagnostics
+                                                .get(uri)
+                                                .into_iter()
+                                                .flatten()
+                                                .chain(self.test_diagnostics
+                                                           .values()
+                                                           .flatten()
+                                                           .filter(|(u, _)| u == uri)
+                                                           .map(|(_, d)| d))
+                                                .collect();
+
+        self.notify("textDocument/publishDiagnostics",
+                    json!({ "uri": uri, "diagnostics": diagnostics }))
+    }
+
+    /// Re-discovers the project, then computes and publishes diagnostics for
+    /// a URI.
+    ///
+    /// * `uri`: URI of the file that was opened or saved
+    fn refresh(&mut self,
+               uri: &str)
+               -> Result<()> {
+        self.project = Project::new()?;
+        let file = self.file(uri)?;
+        let diagnostics = self.check(&file)?;
+
+        self.diagnostics.insert(uri.to_string(), diagnostics);
+        self.publish(uri)
+    }
+
+    /// Returns "Run" code lenses for main methods and "Test" code lenses for
+    /// test methods in a document.
+    ///
+    /// * `uri`: URI of the document
+    fn code_lenses(&self,
+                   uri: &str)
+                   -> Result<Vec<CodeLens>> {
+        let code = match self.documents.get(uri) {
+            Some(code) => code.clone(),
+            None => {
+                let path = uri_to_path(uri).with_context(|| format!("Not a file URI: {uri}"))?;
+                std::fs::read_to_string(path)?
+            }
+        };
+        let parser = Parser::new(code)?;
+        let mut lenses = vec![];
+
+        for (range, _) in query_ranges(&parser, MAIN_METHOD_QUERY, "body")? {
+            lenses.push(CodeLens { range,
+                                   command: Command { title:     "Run".to_string(),
+                                                      command:   RUN_COMMAND.to_string(),
+                                                      arguments: vec![json!(uri)], } });
+        }
+
+        for (range, name) in query_ranges(&parser, TEST_ANNOTATION_QUERY, "name")? {
+            let command = Command { title:     "Test".to_string(),
+                                    command:   TEST_COMMAND.to_string(),
+                                    arguments: vec![json!(uri), json!(name)], };
+            lenses.push(CodeLens { range, command });
+        }
+
+        Ok(lenses)
+    }
+
+    /// Runs the main method of a file and shows its output.
+    ///
+    /// * `uri`: URI of the file to run
+    fn run(&self,
+           uri: &str)
+           -> Result<()> {
+        let file = self.file(uri)?;
+
+        // Input is always provided so that the program does not read the
+        // server's stdin
+        match file.run(Some(String::new())) {
+            Ok(output) => {
+                self.log(&output)?;
+                self.show(&format!("{} ran successfully.", file.proper_name()), false)
+            }
+            Err(JavaFileError::AtRuntime { output, .. }) => {
+                self.log(&output)?;
+                self.show(&format!("{} exited with an error, see output.", file.proper_name()),
+                          true)
+            }
+            Err(JavaFileError::DuringCompilation { stacktrace, .. }) => {
+                self.log(&stacktrace)?;
+                self.show(&format!("{} could not be compiled.", file.proper_name()),
+                          true)
+            }
+            Err(e) => self.show(&format!("Could not run {}: {e}", file.proper_name()), true),
+        }
+    }
+
+    /// Runs a single test method, then publishes diagnostics for the test and
+    /// any project files in its stack trace.
+    ///
+    /// * `uri`: URI of the test file
+    /// * `test`: name of the test method
+    fn test(&mut self,
+            uri: &str,
+            test: &str)
+            -> Result<()> {
+        let file = self.file(uri)?;
+        let key = format!("{}#{}", file.proper_name(), test);
+        let code = file.parser().source().to_string();
+
+        let mut uris: Vec<String> = self.test_diagnostics
+                                        .remove(&key)
+                                        .unwrap_or_default()
+                                        .into_iter()
+                                        .map(|(u, _)| u)
+                                        .collect();
+
+        // JUnit must not read the server's stdin either
+        match file.test_with_stdin(vec![test], Some(&self.project), Stdio::null()) {
+            Ok(output) => {
+                self.log(&output)?;
+                self.show(&format!("{key} passed."), false)?;
+            }
+            Err(JavaFileError::FailedTests { test_results,
+                                             diags, }) => {
+                self.log(&test_results)?;
+                self.show(&format!("{key} failed, see output."), true)?;
+
+                let mut diagnostics = vec![];
+                if let Some((range, _)) = query_ranges(&file.parser(),
+                                                       TEST_ANNOTATION_QUERY,
+                                                       "name")?.into_iter()
+                                                               .find(|(_, name)| name == test)
+                {
+                    diagnostics.push((uri.to_string(),
+                                      Diagnostic { range,
+                                                   severity: Severity::Error,
+                                                   source: "junit".to_string(),
+                                                   code: None,
+                                                   message: test_results.trim().to_string() }));
+                }
+
+                for diag in diags {
+                    if let Ok(f) = self.project.identify(diag.file_name()) {
+                        let code = if f.path() == file.path() {
+                            code.clone()
+                        } else {
+                            f.parser().source().to_string()
+                        };
+                        diagnostics.push((path_to_uri(f.path()),
+                                          Diagnostic { range:    line_range(&code,
+                                                                            diag.line_number),
+                                                       severity: Severity::Information,
+                                                       source:   "junit".to_string(),
+                                                       code:     None,
+                                                       message:  format!("`{key}` failed, and \
+                                                                          its stack trace passes \
+                                                                          through here."), }));
+                    }
+                }
+
+                uris.extend(diagnostics.iter().map(|(u, _)| u.clone()));
+                self.test_diagnostics.insert(key, diagnostics);
+            }
+            Err(JavaFileError::DuringCompilation { stacktrace, .. }) => {
+                self.log(&stacktrace)?;
+                self.show(&format!("{key} could not be compiled."), true)?;
+            }
+            Err(e) => self.show(&format!("Could not run {key}: {e}"), true)?,
+        }
+
+        uris.push(uri.to_string());
+        uris.sort();
+        uris.dedup();
+        for uri in uris {
+            self.publish(&uri)?;
+        }
+
+        Ok(())
+    }
+
+    /// Handles a request, returning its result. Returns `None` if the method
+    /// is not supported.
+    ///
+    /// * `method`: the request method
+    /// * `params`: the request parameters
+    fn handle_request(&mut self,
+                      method: &str,
+                      params: &Value)
+                      -> Result<Option<Value>> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        let result = match method {
+            "initialize" => json!({
+                "capabilities": {
+                    "textDocumentSync": {
+                        "openClose": true,
+                        "change": 1,
+                        "save": { "includeText": false },
+                    },
+                    "codeLensProvider": { "resolveProvider": false },
+                    "executeCommandProvider": { "commands": [RUN_COMMAND, TEST_COMMAND] },
+                },
+                "serverInfo": {
+                    "name": "umm",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            }),
+            "shutdown" => Value::Null,
+            "textDocument/codeLens" => serde_json::to_value(self.code_lenses(uri)?)?,
+            "workspace/executeCommand" => {
+                let arguments = params["arguments"].as_array().cloned().unwrap_or_default();
+                let uri = arguments.first()
+                                   .and_then(Value::as_str)
+                                   .context("Command is missing a URI argument")?;
+
+                match params["command"].as_str().unwrap_or_default() {
+                    RUN_COMMAND => self.run(uri)?,
+                    TEST_COMMAND => {
+                        let test = arguments.get(1)
+                                            .and_then(Value::as_str)
+                                            .context("Command is missing a test argument")?;
+                        self.test(uri, test)?
+                    }
+                    command => anyhow::bail!("Unknown command: {command}"),
+                }
+                Value::Null
+            }
+            _ => return Ok(None),
+        };
+
+        Ok(Some(result))
+    }
+
+    /// Handles a notification.
+    ///
+    /// * `method`: the notification method
+    /// * `params`: the notification parameters
+    fn handle_notification(&mut self,
+                           method: &str,
+                           params: &Value)
+                           -> Result<()> {
+        let uri = params["textDocument"]["uri"].as_str().unwrap_or_default();
+
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.documents.insert(uri.to_string(), text.to_string());
+                self.refresh(uri)
+            }
+            "textDocument/didChange" => {
+                if let Some(text) = params["contentChanges"].as_array()
+                                                            .and_then(|c| c.last())
+                                                            .and_then(|c| c["text"].as_str())
+                {
+                    self.documents.insert(uri.to_string(), text.to_string());
+                }
+                Ok(())
+            }
+            "textDocument/didSave" => self.refresh(uri),
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Starts a language server over stdio. Publishes javac diagnostics, javadoc
+/// lints, style lints, and test failures, and offers code lenses to run main
+/// methods and test methods. Returns once the editor sends `exit`.
+pub fn serve() -> Result<()> {
+    let mut server = Server::new()?;
+    let mut stdin = std::io::stdin().lock();
+
+    while let Some(message) = read_message(&mut stdin)? {
+        // Responses to requests sent by the server have no method
+        let method = match message["method"].as_str() {
+            Some(m) => m,
+            None => continue,
+        };
+        let params = &message["params"];
+
+        if method == "exit" {
+            break;
+        }
+
+        match message.get("id") {
+            Some(id) => {
+                let response = match server.handle_request(method, params) {
+                    Ok(Some(result)) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                    Ok(None) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": {
+                            "code": METHOD_NOT_FOUND,
+                            "message": format!("Unsupported method: {method}"),
+                        },
+                    }),
+                    Err(e) => json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": INTERNAL_ERROR, "message": e.to_string() },
+                    }),
+                };
+                write_message(&response)?;
+            }
+            None => {
+                if let Err(e) = server.handle_notification(method, params) {
+                    server.log(&format!("Error while handling {method}: {e}"))?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
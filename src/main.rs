@@ -28,9 +28,17 @@ use tracing::{metadata::LevelFilter, Level};
 use tracing_subscriber::{fmt, prelude::*, util::SubscriberInitExt};
 use umm::{
     clean,
-    constants::{LIB_DIR, ROOT_DIR, SOURCE_DIR, TEST_DIR},
+    constants::{LIB_DIR, ROOT_DIR, SOURCE_DIR, TEST_DIR, TOOLCHAIN},
+    editors::Editor,
     grade,
     java::Project,
+    lsp,
+    reference::{write_script_docs, DocsFormat},
+    registry::list_assignments,
+    script::check_script,
+    signing::{generate_keys, sign_file, verify_attestation},
+    similarity::compare_submissions,
+    spec::ApiSpec,
 };
 use walkdir::WalkDir;
 
@@ -51,6 +59,14 @@ fn update() -> Result<()> {
     Ok(())
 }
 
+/// Discovers the project and downloads any libraries it needs to compile and
+/// run tests
+fn project() -> Result<Project> {
+    let project = Project::new()?;
+    project.ensure_libraries()?;
+    Ok(project)
+}
+
 /// Enum to represent different commands
 #[derive(Debug, Clone)]
 enum Cmd {
@@ -72,12 +88,36 @@ enum Cmd {
     Info,
     /// Update the command
     Update,
-    /// Checks project health
-    CheckHealth,
+    /// Checks project health, optionally as JSON or as a zip to share
+    CheckHealth(bool, bool),
+    /// Finds, and optionally fixes, project structure problems
+    Doctor(bool),
     /// Starts and serves a web server that serves the project code
     ServeProjectCode,
     /// Resets the project metadata, and re-downloads libraries
     Reset,
+    /// Compare submissions for similarity
+    Similarity(Option<String>, f64, String, String),
+    /// Extract an API spec and grading script from a reference solution
+    SpecExtract(String, String),
+    /// Start a language server over stdio
+    Lsp,
+    /// Generate project files for an editor
+    Init(Editor),
+    /// Show the Java installations found and the one in use
+    Toolchain,
+    /// Check a grading script, optionally against a reference solution
+    ScriptCheck(Option<String>, String),
+    /// Write a reference of the scripting API, and rhai definition files
+    ScriptDocs(String, DocsFormat),
+    /// List the assignments there are grading scripts for
+    AssignmentsList,
+    /// Generate a signing key pair
+    SignKeygen(String),
+    /// Sign a grading script, rubric, or hidden test file with a private key
+    SignFile(String, String),
+    /// Check a signed attestation of grades, optionally against a public key
+    SignVerify(Option<String>, String),
     /// Exit the program
     Exit,
 }
@@ -97,7 +137,8 @@ fn options() -> Cmd {
 
     /// parses Assignment name or path to grading script file
     fn g() -> impl Parser<String> {
-        positional("NAME/PATH").help("Name of assignment in database or path to grading script")
+        positional("NAME/PATH").help("Name of assignment in the script registry, or path to \
+                                      grading script or rubric")
     }
 
     /// parses path to project root folder
@@ -143,9 +184,27 @@ fn options() -> Cmd {
                                   .command("update")
                                   .help("Update the umm command");
 
-    let check_health = pure(Cmd::CheckHealth).to_options()
-                                             .command("check-health")
-                                             .help("Checks the health of the project");
+    let check_health = {
+        let json = long("json").help("Print the findings as JSON").switch();
+        let bundle = long("bundle").help("Write the findings, project info and java versions to \
+                                          a zip file to share when asking for help")
+                                   .switch();
+        construct!(Cmd::CheckHealth(json, bundle))
+    };
+    let check_health = check_health.to_options()
+                                   .command("check-health")
+                                   .help("Checks the health of the project");
+
+    let doctor = {
+        let fix = long("fix").help("Make the changes instead of only listing them")
+                             .switch();
+        construct!(Cmd::Doctor(fix))
+    };
+    let doctor =
+        doctor.to_options()
+              .command("doctor")
+              .help("Find and fix project structure problems, such as files in the wrong folder \
+                     for their package");
 
     let serve =
         pure(Cmd::ServeProjectCode).to_options()
@@ -157,6 +216,144 @@ fn options() -> Cmd {
                                 .command("reset")
                                 .help("Reset the project metadata, and re-download libraries");
 
+    let similarity = {
+        let dir = positional("DIR").help("Folder containing one sub-folder per submission");
+        let starter = long("starter").help("Path to starter code, excluded from comparison")
+                                     .argument::<String>("PATH")
+                                     .optional();
+        let threshold = long("threshold").help("Minimum similarity (0.0 - 1.0) for a pair to be \
+                                                reported")
+                                         .argument::<f64>("F")
+                                         .fallback(0.5);
+        let output = long("output").help("Path to write the report to, without an extension")
+                                   .argument::<String>("PATH")
+                                   .fallback(String::from("similarity-report"));
+        construct!(Cmd::Similarity(starter, threshold, output, dir))
+    };
+    let similarity = similarity.to_options()
+                               .command("similarity")
+                               .help("Detect similar code across a folder of submissions");
+
+    let spec_extract = {
+        let dir = positional("DIR").help("Path to the reference solution's project root folder");
+        let output = long("output").help("Path to write the spec and script to, without an \
+                                          extension")
+                                   .argument::<String>("PATH")
+                                   .fallback(String::from("api-spec"));
+        construct!(Cmd::SpecExtract(output, dir))
+    };
+    let spec_extract =
+        spec_extract.to_options()
+                    .command("spec-extract")
+                    .help("Extract an API spec and a starter grading script from a reference \
+                           solution");
+
+    let lsp = pure(Cmd::Lsp).to_options()
+                            .command("lsp")
+                            .help("Start a language server over stdio that publishes diagnostics");
+
+    let init = {
+        let editor = long("editor").help("Editor to generate project files for: vscode, \
+                                          intellij, or eclipse")
+                                   .argument::<Editor>("EDITOR")
+                                   .fallback(Editor::VsCode);
+        construct!(Cmd::Init(editor))
+    };
+    let init = init.to_options()
+                   .command("init")
+                   .help("Generate editor project files and download libraries");
+
+    let toolchain =
+        pure(Cmd::Toolchain).to_options()
+                            .command("toolchain")
+                            .help("Show the Java installations found, which one is used, and why");
+
+    let script = {
+        let check = {
+            let reference = long("reference").help("Folder containing a reference solution, to \
+                                                    check class and test names and find the \
+                                                    full-marks total")
+                                             .argument::<String>("DIR")
+                                             .optional();
+            construct!(Cmd::ScriptCheck(reference, g()))
+        };
+        let check = check.to_options()
+                         .command("check")
+                         .help("Check that a grading script only uses registered functions");
+
+        let docs = {
+            let out = long("out").help("Folder to write the reference and definitions to")
+                                 .argument::<String>("DIR")
+                                 .fallback(String::from("script-docs"));
+            let format = long("format").help("Format of the reference: markdown or html")
+                                       .argument::<DocsFormat>("FORMAT")
+                                       .fallback(DocsFormat::Markdown);
+            construct!(Cmd::ScriptDocs(out, format))
+        };
+        let docs = docs.to_options()
+                       .command("docs")
+                       .help("Write a reference of every registered type and function, and .d.rhai \
+                              definition files for editors");
+
+        construct!([check, docs])
+    };
+    let script = script.to_options()
+                       .command("script")
+                       .help("Tools for writing grading scripts");
+
+    let assignments = {
+        let list =
+            pure(Cmd::AssignmentsList).to_options()
+                                      .command("list")
+                                      .help("List the assignments there are grading scripts for");
+        construct!([list])
+    };
+    let assignments = assignments.to_options()
+                                 .command("assignments")
+                                 .help("Browse the grading scripts `umm grade` can find");
+
+    let sign = {
+        let keygen = {
+            let out = long("out").help("Path to write the key pair to, without an extension")
+                                 .argument::<String>("PATH")
+                                 .fallback(String::from("umm-signing"));
+            construct!(Cmd::SignKeygen(out))
+        };
+        let keygen = keygen.to_options()
+                           .command("keygen")
+                           .help("Generate an ed25519 key pair for signing grading scripts");
+
+        let file = {
+            let key = long("key").help("Path to the private key")
+                                 .argument::<String>("KEY");
+            let file =
+                positional("FILE").help("Grading script, module, rubric, or hidden test file");
+            construct!(Cmd::SignFile(key, file))
+        };
+        let file = file.to_options()
+                       .command("file")
+                       .help("Sign a file, writing the signature next to it");
+
+        let verify = {
+            let key = long("key").help("Path to the public key. Defaults to the trusted keys")
+                                 .argument::<String>("KEY")
+                                 .optional();
+            let path = positional("PATH").help("Attestation, or Gradescope results.json");
+            construct!(Cmd::SignVerify(key, path))
+        };
+        let verify =
+            verify.to_options()
+                  .command("verify")
+                  .help("Check a signed attestation of grades, and show what it attests to");
+
+        construct!([keygen, file, verify])
+    };
+    let sign =
+        sign.to_options()
+            .command("sign")
+            .help("Sign grading scripts, and check attestations of grades. Without trusted keys, \
+                   only local scripts and modules can be used");
+
     let exit = pure(Cmd::Exit).to_options()
                               .command("exit")
                               .help("Exit the program");
@@ -171,8 +368,17 @@ fn options() -> Cmd {
                           info,
                           update,
                           check_health,
+                          doctor,
                           serve,
                           reset,
+                          similarity,
+                          spec_extract,
+                          lsp,
+                          init,
+                          toolchain,
+                          script,
+                          assignments,
+                          sign,
                           exit]).fallback(Cmd::Exit);
 
     cmd.to_options().descr("Build tool for novices").run()
@@ -181,7 +387,9 @@ fn options() -> Cmd {
 fn main() -> Result<()> {
     dotenv().ok();
 
-    let fmt = fmt::layer().without_time()
+    // Logs go to stderr, stdout is reserved for the language server
+    let fmt = fmt::layer().with_writer(std::io::stderr)
+                          .without_time()
                           .with_file(false)
                           .with_line_number(false);
     let filter_layer = LevelFilter::from_level(Level::INFO);
@@ -194,7 +402,7 @@ fn main() -> Result<()> {
     // TODO: move this to a separate method and call that method in shell()
     match cmd {
         Cmd::Run(f) => {
-            match Project::new()?.identify(f.as_str())?.run_mut_script(None) {
+            match project()?.identify(f.as_str())?.run_mut_script(None) {
                 Ok(out) => {
                     println!("{out}");
                 }
@@ -203,7 +411,7 @@ fn main() -> Result<()> {
                 }
             };
         }
-        Cmd::Check(f) => match Project::new()?.identify(f.as_str())?.check_mut_script() {
+        Cmd::Check(f) => match project()?.identify(f.as_str())?.check_mut_script() {
             Ok(out) => {
                 println!("{out}");
             }
@@ -213,18 +421,16 @@ fn main() -> Result<()> {
         },
         Cmd::Test(f, t) => {
             let out = if t.is_empty() {
-                Project::new()?.identify(f.as_str())?
-                               .test_mut_script(vec![])?
+                project()?.identify(f.as_str())?.test_mut_script(vec![])?
             } else {
-                Project::new()?.identify(f.as_str())?
-                               .test_mut_script(t.iter().map(|i| i.as_str()).collect())?
+                project()?.identify(f.as_str())?
+                          .test_mut_script(t.iter().map(|i| i.as_str()).collect())?
             };
 
             println!("{out}");
         }
         Cmd::DocCheck(f) => {
-            let out = Project::new()?.identify(f.as_str())?
-                                     .doc_check_mut_script()?;
+            let out = project()?.identify(f.as_str())?.doc_check_mut_script()?;
             println!("{out}");
         }
         Cmd::Grade(g) => grade(&g)?,
@@ -311,12 +517,41 @@ fn main() -> Result<()> {
                 Err(e) => eprintln!("{e}"),
             };
         }
-        Cmd::CheckHealth => Project::new()?.check_health()?,
+        Cmd::CheckHealth(json, bundle) => {
+            if bundle {
+                let path = Project::new()?.health_bundle()?;
+                println!("Health report created - {}", path.display());
+            } else {
+                Project::new()?.check_health(json)?;
+            }
+        }
+        Cmd::Doctor(fix) => Project::new()?.doctor(fix)?,
         Cmd::ServeProjectCode => Project::new()?.serve_project_code()?,
         Cmd::Reset => {
             clean()?;
-            Project::new()?;
+            Project::new()?.init(Editor::VsCode)?;
+        }
+        Cmd::Similarity(starter, threshold, output, dir) => {
+            compare_submissions(PathBuf::from(dir).as_path(),
+                                starter.map(PathBuf::from).as_deref(),
+                                threshold)?.write(PathBuf::from(output).as_path())?
+        }
+        Cmd::SpecExtract(output, dir) => {
+            let output = std::env::current_dir()?.join(output);
+            std::env::set_current_dir(&dir)?;
+            ApiSpec::from_project(&Project::new()?)?.write(&output)?;
+        }
+        Cmd::Lsp => lsp::serve()?,
+        Cmd::Init(editor) => Project::new()?.init(editor)?,
+        Cmd::Toolchain => TOOLCHAIN.show()?,
+        Cmd::ScriptCheck(reference, script) => {
+            check_script(&script, reference.as_deref().map(std::path::Path::new))?
         }
+        Cmd::ScriptDocs(out, format) => write_script_docs(std::path::Path::new(&out), format)?,
+        Cmd::AssignmentsList => list_assignments()?,
+        Cmd::SignKeygen(out) => generate_keys(&out)?,
+        Cmd::SignFile(key, file) => sign_file(&key, &file)?,
+        Cmd::SignVerify(key, path) => verify_attestation(&path, key.as_deref())?,
         Cmd::Exit => {}
     };
 
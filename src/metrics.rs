@@ -0,0 +1,330 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+use tabled::Tabled;
+use tree_sitter::{Node, Tree};
+
+use crate::grade::LineRef;
+
+/// Node kinds that declare a type
+const TYPE_DECLARATION_KINDS: [&str; 4] = ["class_declaration",
+                                           "interface_declaration",
+                                           "enum_declaration",
+                                           "record_declaration"];
+
+/// Node kinds that introduce a new level of nesting
+const NESTING_KINDS: [&str; 8] = ["if_statement",
+                                  "for_statement",
+                                  "enhanced_for_statement",
+                                  "while_statement",
+                                  "do_statement",
+                                  "switch_expression",
+                                  "try_statement",
+                                  "try_with_resources_statement"];
+
+/// Node kinds that add a branch to the control flow graph
+const DECISION_KINDS: [&str; 8] = ["if_statement",
+                                   "for_statement",
+                                   "enhanced_for_statement",
+                                   "while_statement",
+                                   "do_statement",
+                                   "catch_clause",
+                                   "ternary_expression",
+                                   "switch_label"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// Metrics for a single method or constructor
+pub struct MethodMetrics {
+    /// name of the method
+    pub name:                  String,
+    /// line the method is declared on, starting at 1
+    pub line:                  usize,
+    /// number of linearly independent paths through the method
+    pub cyclomatic_complexity: usize,
+    /// lines of code, excluding blank lines and comments
+    pub loc:                   usize,
+    /// number of parameters
+    pub parameters:            usize,
+    /// deepest nesting of control flow inside the method
+    pub nesting_depth:         usize,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+/// Metrics for a single class, interface, enum, or record
+pub struct ClassMetrics {
+    /// name of the class
+    pub name:    String,
+    /// line the class is declared on, starting at 1
+    pub line:    usize,
+    /// number of distinct types this class refers to
+    pub fan_out: usize,
+    /// metrics for each method and constructor declared in this class
+    pub methods: Vec<MethodMetrics>,
+}
+
+#[derive(Tabled, Serialize, Deserialize, Clone, Debug)]
+/// A struct representing a metric that exceeds its threshold
+pub struct MetricsViolation {
+    /// * `path`: path to the file the violation is in
+    #[tabled(rename = "File")]
+    pub path:        String,
+    /// * `file_name`: name of the file the violation is in
+    #[tabled(skip)]
+    pub file_name:   String,
+    /// * `line_number`: line number, starting at 1
+    #[tabled(rename = "Line")]
+    pub line_number: usize,
+    /// * `unit`: the class or method the metric was computed for
+    #[tabled(rename = "Class/Method")]
+    pub unit:        String,
+    /// * `metric`: name of the metric
+    #[tabled(rename = "Metric")]
+    pub metric:      String,
+    /// * `value`: the computed value
+    #[tabled(rename = "Value")]
+    pub value:       usize,
+    /// * `threshold`: the maximum allowed value
+    #[tabled(rename = "Max")]
+    pub threshold:   usize,
+}
+
+impl From<MetricsViolation> for LineRef {
+    /// Converts a MetricsViolation to a LineRef
+    fn from(val: MetricsViolation) -> Self {
+        LineRef { file_name:   val.file_name,
+                  line_number: val.line_number, }
+    }
+}
+
+/// Returns the text of `node`
+fn text(node: Node,
+        code: &str)
+        -> String {
+    node.utf8_text(code.as_bytes())
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Returns the deepest nesting of control flow under `node`. An `else if` is
+/// treated as a continuation of the `if` it belongs to, not a new level.
+/// Nested type declarations and anonymous classes are not counted.
+///
+/// * `node`: the node to start counting from, usually a method body
+pub fn nesting_depth(node: Node) -> usize {
+    /// Recursive helper, `is_else_if` is true if `node` is the `else` branch
+    /// of an `if` statement
+    fn depth(node: Node,
+             is_else_if: bool)
+             -> usize {
+        let nests = NESTING_KINDS.contains(&node.kind()) && !is_else_if;
+        let alternative = if node.kind() == "if_statement" {
+            node.child_by_field_name("alternative")
+        } else {
+            None
+        };
+
+        let mut cursor = node.walk();
+        let deepest =
+            node.children(&mut cursor)
+                .filter(|c| !TYPE_DECLARATION_KINDS.contains(&c.kind()) && c.kind() != "class_body")
+                .map(|c| depth(c, Some(c) == alternative && c.kind() == "if_statement"))
+                .max()
+                .unwrap_or_default();
+        deepest + nests as usize
+    }
+
+    depth(node, false)
+}
+
+/// Returns the cyclomatic complexity of `node`, i.e. one more than the number
+/// of decision points under it. Nested type declarations are not counted.
+///
+/// * `node`: the node to compute complexity for, usually a method
+/// * `code`: source code the node belongs to
+pub fn cyclomatic_complexity(node: Node,
+                             code: &str)
+                             -> usize {
+    /// Counts decision points under `node`
+    fn decisions(node: Node,
+                 code: &str)
+                 -> usize {
+        let kind = node.kind();
+        let here = if kind == "switch_label" {
+            text(node, code).trim_start().starts_with("case") as usize
+        } else if kind == "binary_expression" {
+            node.child_by_field_name("operator")
+                .map(|o| matches!(o.kind(), "&&" | "||") as usize)
+                .unwrap_or_default()
+        } else {
+            DECISION_KINDS.contains(&kind) as usize
+        };
+
+        let mut cursor = node.walk();
+        here
+        + node.children(&mut cursor)
+              .filter(|c| !TYPE_DECLARATION_KINDS.contains(&c.kind()) && c.kind() != "class_body")
+              .map(|c| decisions(c, code))
+              .sum::<usize>()
+    }
+
+    1 + decisions(node, code)
+}
+
+/// Returns the number of lines in `node`, excluding blank lines and lines that
+/// only contain comments.
+///
+/// * `node`: the node to count lines for
+/// * `code`: source code the node belongs to
+pub fn lines_of_code(node: Node,
+                     code: &str)
+                     -> usize {
+    let mut in_block_comment = false;
+    let mut count = 0;
+
+    for line in text(node, code).lines() {
+        let line = line.trim();
+        if in_block_comment {
+            if line.contains("*/") {
+                in_block_comment = false;
+            }
+            continue;
+        }
+        if line.is_empty() || line.starts_with("//") {
+            continue;
+        }
+        if line.starts_with("/*") {
+            in_block_comment = !line.contains("*/");
+            continue;
+        }
+        count += 1;
+    }
+
+    count
+}
+
+/// Collects nodes under `node` in pre-order, without descending into nested
+/// type declarations or the bodies of anonymous classes.
+fn members(node: Node) -> Vec<Node> {
+    let mut nodes = vec![];
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        nodes.push(child);
+        let nested_type = TYPE_DECLARATION_KINDS.contains(&child.kind());
+        let anonymous_body =
+            child.kind() == "class_body" && !TYPE_DECLARATION_KINDS.contains(&node.kind());
+        if !nested_type && !anonymous_body {
+            nodes.extend(members(child));
+        }
+    }
+    nodes
+}
+
+/// Computes metrics for a method or constructor declaration.
+///
+/// * `node`: a `method_declaration` or `constructor_declaration` node
+/// * `code`: source code the node belongs to
+fn method_metrics(node: Node,
+                  code: &str)
+                  -> MethodMetrics {
+    let parameters =
+        node.child_by_field_name("parameters")
+            .map(|p| {
+                let mut cursor = p.walk();
+                p.named_children(&mut cursor)
+                 .filter(|c| matches!(c.kind(), "formal_parameter" | "spread_parameter"))
+                 .count()
+            })
+            .unwrap_or_default();
+
+    MethodMetrics { name: node.child_by_field_name("name")
+                              .map(|n| text(n, code))
+                              .unwrap_or_default(),
+                    line: node.start_position().row + 1,
+                    cyclomatic_complexity: cyclomatic_complexity(node, code),
+                    loc: lines_of_code(node, code),
+                    parameters,
+                    nesting_depth: node.child_by_field_name("body")
+                                       .map(nesting_depth)
+                                       .unwrap_or_default() }
+}
+
+/// Computes metrics for a type declaration and pushes them, along with
+/// metrics for any nested type declarations, to `classes`.
+///
+/// * `node`: a type declaration node
+/// * `code`: source code the node belongs to
+/// * `classes`: where to push computed metrics
+fn class_metrics(node: Node,
+                 code: &str,
+                 classes: &mut Vec<ClassMetrics>) {
+    let name = node.child_by_field_name("name")
+                   .map(|n| text(n, code))
+                   .unwrap_or_default();
+    let nodes = members(node);
+
+    let declared: HashSet<String> =
+        nodes.iter()
+             .filter(|n| TYPE_DECLARATION_KINDS.contains(&n.kind()) || n.kind() == "type_parameter")
+             .filter_map(|n| {
+                 n.child_by_field_name("name")
+                  .or_else(|| n.named_child(0))
+                  .map(|n| text(n, code))
+             })
+             .chain([name.clone()])
+             .collect();
+
+    let referenced: HashSet<String> =
+        nodes.iter()
+             .filter_map(|n| match n.kind() {
+                 "type_identifier" => Some(text(*n, code)),
+                 "method_invocation" | "field_access" => {
+                     n.child_by_field_name("object")
+                      .filter(|o| o.kind() == "identifier")
+                      .map(|o| text(o, code))
+                      .filter(|o| o.starts_with(|c: char| c.is_ascii_uppercase()))
+                 }
+                 _ => None,
+             })
+             .filter(|t| !declared.contains(t))
+             .collect();
+
+    let methods =
+        nodes.iter()
+             .filter(|n| matches!(n.kind(), "method_declaration" | "constructor_declaration"))
+             .map(|n| method_metrics(*n, code))
+             .collect();
+
+    classes.push(ClassMetrics { name,
+                                line: node.start_position().row + 1,
+                                fan_out: referenced.len(),
+                                methods });
+
+    for nested in nodes.iter()
+                       .filter(|n| TYPE_DECLARATION_KINDS.contains(&n.kind()))
+    {
+        class_metrics(*nested, code, classes);
+    }
+}
+
+/// Computes metrics for every type declared in a parse tree.
+///
+/// * `code`: the source code that was parsed
+/// * `tree`: the parse tree
+pub fn compute(code: &str,
+               tree: &Tree)
+               -> Vec<ClassMetrics> {
+    let root = tree.root_node();
+    let mut classes = vec![];
+    let mut cursor = root.walk();
+
+    for node in root.children(&mut cursor)
+                    .filter(|n| TYPE_DECLARATION_KINDS.contains(&n.kind()))
+    {
+        class_metrics(node, code, &mut classes);
+    }
+
+    classes
+}
@@ -0,0 +1,171 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{fmt::Display, str::FromStr};
+
+use anyhow::{bail, Result};
+use rhai::EvalAltResult;
+use serde::Serialize;
+
+use crate::constants::{OUTCOME_FORMAT_ENV, SCRIPT_OUTCOME};
+
+/// Whether a submission passed, as a grading script reports it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    /// the submission passed
+    Pass,
+    /// the submission didn't pass
+    Fail,
+}
+
+impl Status {
+    /// All statuses, in the order they are listed in help text
+    pub const ALL: [Status; 2] = [Status::Pass, Status::Fail];
+}
+
+impl Display for Status {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        match self {
+            Status::Pass => write!(f, "pass"),
+            Status::Fail => write!(f, "fail"),
+        }
+    }
+}
+
+impl FromStr for Status {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "pass" | "passed" => Ok(Status::Pass),
+            "fail" | "failed" => Ok(Status::Fail),
+            _ => bail!("Unknown status: {s}. Expected one of: {}",
+                       Status::ALL.iter()
+                                  .map(|s| s.to_string())
+                                  .collect::<Vec<_>>()
+                                  .join(", ")),
+        }
+    }
+}
+
+/// How the outcome of grading is written to stdout, set with
+/// [OUTCOME_FORMAT_ENV]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutcomeFormat {
+    /// `p;<score>` if the submission passed, `np` otherwise, which LMS
+    /// integrations have parsed from scripts' `print` output
+    #[default]
+    Legacy,
+    /// a line of JSON, like `{"score":50.0,"status":"pass"}`
+    Json,
+}
+
+impl OutcomeFormat {
+    /// All formats, in the order they are listed in help text
+    pub const ALL: [OutcomeFormat; 2] = [OutcomeFormat::Legacy, OutcomeFormat::Json];
+}
+
+impl Display for OutcomeFormat {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        match self {
+            OutcomeFormat::Legacy => write!(f, "legacy"),
+            OutcomeFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+impl FromStr for OutcomeFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "legacy" => Ok(OutcomeFormat::Legacy),
+            "json" => Ok(OutcomeFormat::Json),
+            _ => bail!("Unknown outcome format: {s}. Expected one of: {}",
+                       OutcomeFormat::ALL.iter()
+                                         .map(|f| f.to_string())
+                                         .collect::<Vec<_>>()
+                                         .join(", ")),
+        }
+    }
+}
+
+/// The score and status a grading script reported with `emit_score` and
+/// `set_status`
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Outcome {
+    /// the score, if one was emitted
+    pub score:  Option<f64>,
+    /// the status, if one was set
+    pub status: Option<Status>,
+}
+
+impl Outcome {
+    /// Whether the script reported anything
+    pub fn is_empty(&self) -> bool {
+        self.score.is_none() && self.status.is_none()
+    }
+
+    /// Formats the outcome for stdout
+    ///
+    /// * `format`: the format to use
+    pub fn render(&self,
+                  format: OutcomeFormat)
+                  -> Result<String> {
+        Ok(match format {
+            OutcomeFormat::Legacy => match (self.status, self.score) {
+                (Some(Status::Fail), _) => String::from("np"),
+                // Truncated, as scripts did with `to_int()`
+                (_, Some(score)) => format!("p;{}", score as i64),
+                (Some(Status::Pass), None) => String::from("p"),
+                (None, None) => String::new(),
+            },
+            OutcomeFormat::Json => serde_json::to_string(self)?,
+        })
+    }
+}
+
+/// Records the score of the submission, to be written to stdout once the
+/// script finishes. Replaces printing `p;<score>`.
+///
+/// * `score`: the score
+pub fn emit_score(score: f64) {
+    SCRIPT_OUTCOME.lock().unwrap().score = Some(score);
+}
+
+/// Records whether the submission passed, to be written to stdout once the
+/// script finishes. Replaces printing `np`.
+///
+/// * `status`: "pass" or "fail"
+pub fn set_status(status: &str) -> Result<(), Box<EvalAltResult>> {
+    let status = status.parse::<Status>()
+                       .map_err(|e| -> Box<EvalAltResult> { e.to_string().into() })?;
+    SCRIPT_OUTCOME.lock().unwrap().status = Some(status);
+    Ok(())
+}
+
+/// Forgets any outcome recorded by a previous script
+pub fn reset_outcome() {
+    *SCRIPT_OUTCOME.lock().unwrap() = Outcome::default();
+}
+
+/// Writes the outcome the script recorded to stdout, in the format set with
+/// [OUTCOME_FORMAT_ENV]. Nothing is written if it didn't record one.
+pub fn report_outcome() -> Result<()> {
+    let outcome = SCRIPT_OUTCOME.lock().unwrap().clone();
+    if outcome.is_empty() {
+        return Ok(());
+    }
+
+    let format = match std::env::var(OUTCOME_FORMAT_ENV) {
+        Ok(value) => value.parse()?,
+        Err(_) => OutcomeFormat::default(),
+    };
+    println!("{}", outcome.render(format)?);
+    Ok(())
+}
@@ -0,0 +1,514 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{collections::BTreeMap, path::Path, process::Command};
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Duration, FixedOffset, Local, NaiveDateTime, TimeZone};
+#[allow(deprecated)]
+use rhai::{Array, CustomType, EvalAltResult};
+use serde::{Deserialize, Serialize};
+use umm_derive::generate_rhai_variant;
+
+use crate::{
+    constants::{LATE_POLICY, ROOT_DIR, SUBMISSION_METADATA},
+    grade::GradeResult,
+};
+
+/// Parses a date and time, either in RFC 3339 like `2024-09-30T23:59:00-04:00`,
+/// or like `2024-09-30 23:59` in the local time zone
+///
+/// * `s`: the date and time
+pub fn parse_datetime(s: &str) -> Result<DateTime<FixedOffset>> {
+    let s = s.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Ok(datetime);
+    }
+
+    for format in ["%Y-%m-%d %H:%M:%S",
+                   "%Y-%m-%d %H:%M",
+                   "%Y-%m-%dT%H:%M:%S",
+                   "%Y-%m-%dT%H:%M"]
+    {
+        if let Ok(naive) = NaiveDateTime::parse_from_str(s, format) {
+            return Local.from_local_datetime(&naive)
+                        .single()
+                        .map(|d| d.fixed_offset())
+                        .with_context(|| format!("{s} is ambiguous in the local time zone"));
+        }
+    }
+    bail!("Invalid date and time: {s}. Expected something like 2024-09-30T23:59:00-04:00 or \
+           2024-09-30 23:59")
+}
+
+/// A student on a Gradescope submission
+#[derive(Deserialize, Debug)]
+struct MetadataUser {
+    /// their email
+    email: String,
+}
+
+/// The assignment a Gradescope submission is for
+#[derive(Deserialize, Debug)]
+struct MetadataAssignment {
+    /// when it is due
+    due_date: Option<String>,
+}
+
+/// The parts of Gradescope's submission metadata late policies use
+#[derive(Deserialize, Debug)]
+struct SubmissionMetadata {
+    /// when the submission was made
+    created_at: String,
+    /// who made it
+    #[serde(default)]
+    users:      Vec<MetadataUser>,
+    /// what it was for
+    assignment: Option<MetadataAssignment>,
+}
+
+/// When a submission was made, and by whom
+#[derive(Debug, Clone)]
+pub struct Submission {
+    /// when it was made
+    pub submitted_at: DateTime<FixedOffset>,
+    /// emails of the students who made it, if known
+    pub emails:       Vec<String>,
+    /// when the assignment is due, if the submission says
+    pub due:          Option<DateTime<FixedOffset>>,
+}
+
+impl Submission {
+    /// Reads a submission from Gradescope's metadata
+    ///
+    /// * `path`: path to the metadata
+    fn from_metadata(path: &Path) -> Result<Self> {
+        let metadata = std::fs::read_to_string(path).with_context(|| {
+                                                        format!("Could not read {}", path.display())
+                                                    })?;
+        let metadata: SubmissionMetadata = serde_json::from_str(&metadata).with_context(|| {
+                                               format!("Invalid submission metadata in {}",
+                                                       path.display())
+                                           })?;
+
+        let due = match metadata.assignment.and_then(|a| a.due_date) {
+            Some(due) => Some(parse_datetime(&due)?),
+            None => None,
+        };
+        Ok(Self { submitted_at: parse_datetime(&metadata.created_at)?,
+                  emails: metadata.users.into_iter().map(|u| u.email).collect(),
+                  due })
+    }
+
+    /// Uses the time of the project's last git commit as the submission time
+    fn from_git() -> Result<Self> {
+        let output = Command::new("git").arg("-C")
+                                        .arg(ROOT_DIR.as_path())
+                                        .args(["log", "-1", "--format=%cI"])
+                                        .output()
+                                        .context("Could not run git")?;
+        if !output.status.success() {
+            bail!("Could not read the last commit: {}",
+                  String::from_utf8_lossy(&output.stderr).trim());
+        }
+
+        Ok(Self { submitted_at: parse_datetime(&String::from_utf8_lossy(&output.stdout))?,
+                  emails:       vec![],
+                  due:          None, })
+    }
+
+    /// Works out when the submission being graded was made: from
+    /// [SUBMISSION_METADATA] on Gradescope, or else the last git commit, or
+    /// else now
+    pub fn detect() -> Result<Self> {
+        let metadata = Path::new(SUBMISSION_METADATA);
+        if metadata.is_file() {
+            return Self::from_metadata(metadata);
+        }
+
+        match Self::from_git() {
+            Ok(submission) => Ok(submission),
+            Err(e) => {
+                tracing::warn!("Using the current time as the submission time, as there is no \
+                                Gradescope metadata and {e:#}");
+                Ok(Self { submitted_at: Local::now().fixed_offset(),
+                          emails:       vec![],
+                          due:          None, })
+            }
+        }
+    }
+}
+
+/// How late a submission is, and what that costs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Lateness {
+    /// days late, counting part of a day as a whole one, after tokens
+    pub days_late:   u32,
+    /// tokens spent to excuse days late
+    pub tokens_used: u32,
+    /// tokens the students have left after this submission
+    pub tokens_left: u32,
+    /// fraction of the points earned that is deducted
+    pub fraction:    f64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+/// A late submission policy: when an assignment is due, how long the grace
+/// period is, and what each day late costs
+pub struct LatePolicy {
+    /// when the assignment is due. Defaults to the due date in Gradescope's
+    /// submission metadata.
+    pub due:         String,
+    /// hours after `due` that submissions are still on time
+    pub grace_hours: f64,
+    /// fraction of the points earned deducted per day late
+    pub per_day:     f64,
+    /// most that can be deducted, as a fraction of the points earned
+    pub max:         f64,
+    /// tokens each student has left to excuse days late, one day each, by
+    /// email
+    pub tokens:      BTreeMap<String, u32>,
+    /// extra days given to particular students, by email
+    pub extensions:  BTreeMap<String, f64>,
+}
+
+impl Default for LatePolicy {
+    fn default() -> Self {
+        Self { due:         String::new(),
+               grace_hours: 0.0,
+               per_day:     0.1,
+               max:         1.0,
+               tokens:      BTreeMap::new(),
+               extensions:  BTreeMap::new(), }
+    }
+}
+
+impl LatePolicy {
+    /// gets the `due` field
+    pub fn due(&mut self) -> String {
+        self.due.clone()
+    }
+
+    /// sets the `due` field
+    pub fn set_due(mut self,
+                   due: String)
+                   -> Self {
+        self.due = due;
+        self
+    }
+
+    /// gets the `grace_hours` field
+    pub fn grace_hours(&mut self) -> f64 {
+        self.grace_hours
+    }
+
+    /// sets the `grace_hours` field
+    pub fn set_grace_hours(mut self,
+                           grace_hours: f64)
+                           -> Self {
+        self.grace_hours = grace_hours;
+        self
+    }
+
+    /// gets the `per_day` field
+    pub fn per_day(&mut self) -> f64 {
+        self.per_day
+    }
+
+    /// sets the `per_day` field
+    pub fn set_per_day(mut self,
+                       per_day: f64)
+                       -> Self {
+        self.per_day = per_day;
+        self
+    }
+
+    /// gets the `max` field
+    pub fn max(&mut self) -> f64 {
+        self.max
+    }
+
+    /// sets the `max` field
+    pub fn set_max(mut self,
+                   max: f64)
+                   -> Self {
+        self.max = max;
+        self
+    }
+
+    /// Tokens a student has left
+    ///
+    /// * `email`: the student's email
+    pub fn tokens(&mut self,
+                  email: String)
+                  -> i64 {
+        self.tokens.get(&email).copied().unwrap_or_default() as i64
+    }
+
+    /// Gives a student tokens, each excusing a day late
+    ///
+    /// * `email`: the student's email
+    /// * `count`: how many tokens they have left
+    pub fn set_tokens(mut self,
+                      email: String,
+                      count: i64)
+                      -> Self {
+        self.tokens.insert(email, count.max(0) as u32);
+        self
+    }
+
+    /// Gives a student extra days
+    ///
+    /// * `email`: the student's email
+    /// * `days`: how many extra days they get
+    pub fn extension(mut self,
+                     email: String,
+                     days: f64)
+                     -> Self {
+        self.extensions.insert(email, days);
+        self
+    }
+
+    /// Works out how late a submission is, and what that costs
+    ///
+    /// * `submission`: the submission
+    pub fn assess(&self,
+                  submission: &Submission)
+                  -> Result<Lateness> {
+        let due = if self.due.trim().is_empty() {
+            submission.due
+                      .context("A late policy needs a due date, as the submission metadata \
+                                doesn't have one")?
+        } else {
+            parse_datetime(&self.due)?
+        };
+
+        // The most any of the students who made the submission were given
+        let extension = submission.emails
+                                  .iter()
+                                  .filter_map(|e| self.extensions.get(e))
+                                  .fold(0.0_f64, |a, b| a.max(*b));
+        let tokens = submission.emails
+                               .iter()
+                               .filter_map(|e| self.tokens.get(e))
+                               .copied()
+                               .max()
+                               .unwrap_or_default();
+
+        let minutes = (self.grace_hours + extension * 24.0) * 60.0;
+        let deadline = due + Duration::minutes(minutes.round() as i64);
+
+        /// seconds in a day
+        const DAY: i64 = 24 * 60 * 60;
+        let late_by = (submission.submitted_at - deadline).num_seconds();
+        let days = if late_by > 0 {
+            ((late_by + DAY - 1) / DAY) as u32
+        } else {
+            0
+        };
+
+        Ok(self.charge(days, tokens))
+    }
+
+    /// What being `days` days late costs, spending up to `tokens` tokens to
+    /// excuse some of them
+    ///
+    /// * `days`: whole days late, past any grace period and extension
+    /// * `tokens`: tokens the students have
+    pub fn charge(&self,
+                  days: u32,
+                  tokens: u32)
+                  -> Lateness {
+        let tokens_used = days.min(tokens);
+        let days_late = days - tokens_used;
+        let fraction = (days_late as f64 * self.per_day).clamp(0.0, self.max.max(0.0));
+
+        Lateness { days_late,
+                   tokens_used,
+                   tokens_left: tokens - tokens_used,
+                   fraction }
+    }
+
+    /// The deduction for `lateness`, as a result of its own with no points
+    /// possible
+    ///
+    /// * `lateness`: how late the submission is
+    /// * `earned`: points earned, the deduction is a fraction of
+    fn deduction(lateness: &Lateness,
+                 earned: f64)
+                 -> GradeResult {
+        let mut reason = format!("{} day(s) late", lateness.days_late + lateness.tokens_used);
+        if lateness.tokens_used > 0 {
+            reason.push_str(&format!(", {} token(s) used, {} left",
+                                     lateness.tokens_used, lateness.tokens_left));
+        }
+        reason.push_str(&format!(", -{:.0}%", lateness.fraction * 100.0));
+
+        GradeResult::default().set_requirement(String::from("Late penalty"))
+                              .set_grade(-lateness.fraction * earned.max(0.0))
+                              .set_out_of(0.0)
+                              .set_reason(reason)
+    }
+
+    /// The deduction for the submission being graded, as a result of its own
+    /// with no points possible, or `None` if nothing is deducted
+    ///
+    /// * `results`: the results the deduction is taken from
+    pub fn penalty(&self,
+                   results: &[GradeResult])
+                   -> Result<Option<GradeResult>> {
+        let lateness = self.assess(&Submission::detect()?)?;
+        if lateness.days_late == 0 && lateness.tokens_used == 0 {
+            return Ok(None);
+        }
+
+        let earned: f64 = results.iter().cloned().map(|mut r| r.grade()).sum();
+        Ok(Some(Self::deduction(&lateness, earned)))
+    }
+
+    /// The deduction for a submission a given number of days late, without
+    /// looking at when it was made, for scripts that work that out themselves
+    ///
+    /// * `results`: the results the deduction is taken from
+    /// * `days_late`: whole days late
+    pub fn penalty_for_days(&mut self,
+                            results: Array,
+                            days_late: i64)
+                            -> GradeResult {
+        let earned: f64 = results.into_iter()
+                                 .map(|r| r.cast::<GradeResult>().grade())
+                                 .sum();
+        Self::deduction(&self.charge(days_late.max(0) as u32, 0), earned)
+    }
+}
+
+/// Returns the registered late policy, if any.
+pub fn late_policy() -> Option<LatePolicy> {
+    LATE_POLICY.lock().unwrap().clone()
+}
+
+#[generate_rhai_variant(Fallible)]
+/// Registers a late policy, so the results `show_results` reports include a
+/// deduction for late submissions
+///
+/// * `policy`: the policy
+pub fn use_late_policy(policy: LatePolicy) -> Result<()> {
+    if !policy.due.trim().is_empty() {
+        parse_datetime(&policy.due).map_err(|e| anyhow!("Invalid late policy: {e}"))?;
+    }
+    *LATE_POLICY.lock().unwrap() = Some(policy);
+    Ok(())
+}
+
+// Allowed because CustomType is not deprecated, just volatile
+#[allow(deprecated)]
+/// Allows registering custom types with Rhai.
+impl CustomType for LatePolicy {
+    /// Builds a custom type to be registered with Rhai.
+    fn build(mut builder: rhai::TypeBuilder<Self>) {
+        builder.with_name("LatePolicy")
+               .with_comments(&["/// When an assignment is due, and what each day late costs"])
+               .with_fn("due", Self::due)
+               .with_fn("due", Self::set_due)
+               .with_fn("grace_hours", Self::grace_hours)
+               .with_fn("grace_hours", Self::set_grace_hours)
+               .with_fn("per_day", Self::per_day)
+               .with_fn("per_day", Self::set_per_day)
+               .with_fn("max", Self::max)
+               .with_fn("max", Self::set_max)
+               .with_fn("tokens", Self::tokens)
+               .with_fn("tokens", Self::set_tokens)
+               .and_comments(&["/// Gives the student with an email tokens, each excusing a day \
+                                late"])
+               .with_fn("extension", Self::extension)
+               .and_comments(&["/// Gives the student with an email extra days"])
+               .with_fn("penalty_for_days", Self::penalty_for_days)
+               .and_comments(&["/// The deduction for being some number of days late"])
+               .with_fn("new_late_policy", Self::default)
+               .and_comments(&["/// Creates a policy deducting 10% a day, with no grace period"]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A policy due at midnight UTC on 2024-01-01, deducting 10% a day
+    fn policy() -> LatePolicy {
+        LatePolicy::default().set_due(String::from("2024-01-01T00:00:00Z"))
+    }
+
+    /// A submission by `a@example.edu` at `submitted_at`
+    fn submitted(submitted_at: &str) -> Submission {
+        Submission { submitted_at: parse_datetime(submitted_at).unwrap(),
+                     emails:       vec![String::from("a@example.edu")],
+                     due:          None, }
+    }
+
+    /// Submissions within the grace period are on time
+    #[test]
+    fn grace_period() {
+        let policy = policy().set_grace_hours(2.0);
+        let lateness = policy.assess(&submitted("2024-01-01T01:59:00Z")).unwrap();
+        assert_eq!(lateness.days_late, 0);
+        assert_eq!(lateness.fraction, 0.0);
+
+        let lateness = policy.assess(&submitted("2024-01-01T02:01:00Z")).unwrap();
+        assert_eq!(lateness.days_late, 1);
+        assert!((lateness.fraction - 0.1).abs() < 1e-9);
+    }
+
+    /// Part of a day late counts as a whole day
+    #[test]
+    fn partial_days() {
+        let lateness = policy().assess(&submitted("2024-01-02T00:00:01Z")).unwrap();
+        assert_eq!(lateness.days_late, 2);
+    }
+
+    /// Extensions move the deadline for the students given them only
+    #[test]
+    fn extensions() {
+        let policy = policy().extension(String::from("a@example.edu"), 1.0);
+        let lateness = policy.assess(&submitted("2024-01-01T23:00:00Z")).unwrap();
+        assert_eq!(lateness.days_late, 0);
+
+        let mut other = submitted("2024-01-01T23:00:00Z");
+        other.emails = vec![String::from("b@example.edu")];
+        assert_eq!(policy.assess(&other).unwrap().days_late, 1);
+    }
+
+    /// Tokens excuse a day each, up to what the student has left
+    #[test]
+    fn tokens() {
+        let policy = policy().set_tokens(String::from("a@example.edu"), 2);
+        let lateness = policy.assess(&submitted("2024-01-03T12:00:00Z")).unwrap();
+        assert_eq!(lateness.tokens_used, 2);
+        assert_eq!(lateness.tokens_left, 0);
+        assert_eq!(lateness.days_late, 1);
+
+        let lateness = policy.assess(&submitted("2024-01-01T12:00:00Z")).unwrap();
+        assert_eq!(lateness.tokens_used, 1);
+        assert_eq!(lateness.tokens_left, 1);
+        assert_eq!(lateness.fraction, 0.0);
+    }
+
+    /// The deduction never goes past the cap
+    #[test]
+    fn cap() {
+        let policy = policy().set_max(0.25);
+        let lateness = policy.assess(&submitted("2024-01-10T00:00:00Z")).unwrap();
+        assert_eq!(lateness.days_late, 9);
+        assert_eq!(lateness.fraction, 0.25);
+    }
+
+    /// The deduction is a fraction of the points earned
+    #[test]
+    fn deduction() {
+        let mut result = LatePolicy::deduction(&policy().charge(3, 1), 8.0);
+        assert!((result.grade() + 1.6).abs() < 1e-9);
+        assert_eq!(result.out_of(), 0.0);
+        assert_eq!(result.reason(),
+                   "3 day(s) late, 1 token(s) used, 0 left, -20%");
+    }
+}
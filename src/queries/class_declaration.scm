@@ -4,6 +4,7 @@
   (class_declaration 
   name: (_) @className
   type_parameters: (_)* @typeParameters
+  superclass: (_)* @superclass
   interfaces: (_)* @interfaces
   )
 )
\ No newline at end of file
@@ -0,0 +1,14 @@
+(program
+  (block_comment)*
+  (line_comment)*
+  (class_declaration 
+      (class_body
+          (field_declaration
+            (modifiers)* @modifier
+            type: (_) @type
+            declarator: (variable_declarator
+              name: (_) @identifier)
+            )
+      )
+	)
+)
\ No newline at end of file
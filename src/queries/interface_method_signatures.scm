@@ -0,0 +1,16 @@
+(program
+  (block_comment)*
+  (line_comment)*
+  (interface_declaration 
+      (interface_body
+          (method_declaration
+          	(modifiers)* @modifier
+            type_parameters: (_)* @typeParameters
+            type: (_) @returnType
+            name: (_) @identifier
+		        parameters: (_) @parameters
+            (throws)* @throws
+            )
+      )
+	)
+)
\ No newline at end of file
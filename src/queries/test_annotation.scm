@@ -6,6 +6,7 @@
         )
     )
     name: (_) @name
+    (#eq? @annotation "Test")
 )
 
 (method_declaration
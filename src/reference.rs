@@ -0,0 +1,491 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs,
+    path::Path,
+    str::FromStr,
+};
+
+use anyhow::{bail, Context, Result};
+use rhai::Engine;
+use serde::Deserialize;
+
+use crate::{
+    compat::{API_VERSION, API_VERSION_DIRECTIVE, DEPRECATIONS},
+    create_engine,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// A format `umm script docs` can write the reference in
+pub enum DocsFormat {
+    /// a single `reference.md` file
+    Markdown,
+    /// a single, self-contained `reference.html` file
+    Html,
+}
+
+impl DocsFormat {
+    /// All supported formats
+    pub const ALL: [DocsFormat; 2] = [DocsFormat::Markdown, DocsFormat::Html];
+
+    /// Name of the file the reference is written to
+    fn file_name(&self) -> &'static str {
+        match self {
+            DocsFormat::Markdown => "reference.md",
+            DocsFormat::Html => "reference.html",
+        }
+    }
+}
+
+impl Display for DocsFormat {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        match self {
+            DocsFormat::Markdown => write!(f, "markdown"),
+            DocsFormat::Html => write!(f, "html"),
+        }
+    }
+}
+
+impl FromStr for DocsFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match DocsFormat::ALL.iter()
+                             .find(|f| f.to_string() == s.to_lowercase())
+        {
+            Some(format) => Ok(*format),
+            None => bail!("Unknown format: {}. Expected one of: {}",
+                          s,
+                          DocsFormat::ALL.map(|f| f.to_string()).join(", ")),
+        }
+    }
+}
+
+/// The parts of rhai's function metadata JSON the reference uses
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Metadata {
+    /// registered types
+    #[serde(default)]
+    custom_types: Vec<TypeMetadata>,
+    /// registered functions, including builder methods
+    #[serde(default)]
+    functions:    Vec<FnMetadata>,
+    /// static modules, like `umm::std`
+    #[serde(default)]
+    modules:      BTreeMap<String, ModuleMetadata>,
+}
+
+/// A static module, as described by rhai
+#[derive(Debug, Deserialize)]
+struct ModuleMetadata {
+    /// modules nested in this one
+    #[serde(default)]
+    modules:   BTreeMap<String, ModuleMetadata>,
+    /// functions in this module
+    #[serde(default)]
+    functions: Vec<FnMetadata>,
+}
+
+impl ModuleMetadata {
+    /// Collects the functions of this module and the ones nested in it, by
+    /// the full name of their module
+    ///
+    /// * `name`: full name of this module, e.g. `umm::std`
+    /// * `modules`: where to collect them
+    fn collect(&self,
+               name: &str,
+               modules: &mut BTreeMap<String, Vec<Entry>>) {
+        if !self.functions.is_empty() {
+            modules.insert(name.to_string(),
+                           self.functions.iter().map(|f| Entry::new(f, 0)).collect());
+        }
+        for (inner, module) in &self.modules {
+            module.collect(&format!("{name}::{inner}"), modules);
+        }
+    }
+}
+
+/// A registered type, as described by rhai
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TypeMetadata {
+    /// Rust name of the type, e.g. `umm::java::File`
+    type_name:    String,
+    /// name scripts know the type by
+    display_name: String,
+    /// doc comments registered with the type
+    #[serde(default)]
+    doc_comments: Vec<String>,
+}
+
+/// A registered function, as described by rhai
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FnMetadata {
+    /// name of the function
+    name:         String,
+    /// its parameters
+    #[serde(default)]
+    params:       Vec<ParamMetadata>,
+    /// its return type, empty for `()`
+    #[serde(default)]
+    return_type:  String,
+    /// doc comments registered with the function
+    #[serde(default)]
+    doc_comments: Vec<String>,
+}
+
+/// A parameter of a registered function
+#[derive(Debug, Deserialize)]
+struct ParamMetadata {
+    /// name of the parameter, if it was registered with one
+    name: Option<String>,
+    /// its type
+    #[serde(rename = "type")]
+    typ:  Option<String>,
+}
+
+/// Rewrites a Rust type from rhai's metadata the way scripts see it, e.g.
+/// `&mut umm::java::File` as `JavaFile`, and
+/// `Result<String, Box<EvalAltResult>>` as `string`
+///
+/// * `typ`: the type to rewrite
+/// * `names`: names scripts know registered types by, keyed by Rust name
+fn script_type(typ: &str,
+               names: &HashMap<String, String>)
+               -> String {
+    let typ = typ.trim()
+                 .trim_start_matches('&')
+                 .trim_start_matches("mut ")
+                 .trim();
+
+    if let Some((ok, _)) = typ.strip_prefix("core::result::Result<")
+                              .and_then(|t| t.rsplit_once(','))
+    {
+        return script_type(ok, names);
+    }
+    if let Some(inner) = typ.strip_prefix("core::option::Option<")
+                            .and_then(|t| t.strip_suffix('>'))
+    {
+        return script_type(inner, names);
+    }
+    if typ.starts_with('[') || typ.starts_with("alloc::vec::Vec<") {
+        return String::from("array");
+    }
+
+    match typ {
+        "alloc::string::String" | "std::path::PathBuf" => String::from("string"),
+        _ => names.get(typ)
+                  .cloned()
+                  .unwrap_or_else(|| typ.rsplit("::").next().unwrap_or(typ).to_string()),
+    }
+}
+
+/// Joins doc comments into a single line of text, without the `///` leaders
+///
+/// * `comments`: the comments, each of which may span several lines
+fn doc_text(comments: &[String]) -> String {
+    comments.iter()
+            .flat_map(|c| c.lines())
+            .map(|l| l.trim().trim_start_matches("///").trim())
+            .filter(|l| !l.is_empty())
+            .collect::<Vec<_>>()
+            .join(" ")
+}
+
+/// A function or method in the reference
+#[derive(Debug)]
+struct Entry {
+    /// how it is called, e.g. `out_of(f64) -> DocsGrader`
+    signature: String,
+    /// what it does, without the `///` leaders
+    docs:      String,
+}
+
+impl Entry {
+    /// Describes a function, leaving out the first `skip` parameters
+    ///
+    /// * `function`: the function
+    /// * `skip`: 1 for methods, whose first parameter is the object
+    fn new(function: &FnMetadata,
+           skip: usize)
+           -> Self {
+        let params = function.params
+                             .iter()
+                             .skip(skip)
+                             .map(|p| match (p.name.as_deref(), p.typ.as_deref()) {
+                                 (Some(name), Some(typ)) => format!("{name}: {typ}"),
+                                 (Some(name), None) => name.to_string(),
+                                 (None, typ) => typ.unwrap_or("?").to_string(),
+                             })
+                             .collect::<Vec<_>>()
+                             .join(", ");
+        let signature = match function.return_type.as_str() {
+            "" | "()" => format!("{}({params})", function.name),
+            ret => format!("{}({params}) -> {ret}", function.name),
+        };
+        let docs = doc_text(&function.doc_comments);
+
+        Self { signature, docs }
+    }
+}
+
+/// A registered type in the reference
+#[derive(Debug, Default)]
+struct TypeEntry {
+    /// what the type is for
+    docs:         String,
+    /// functions that create one
+    constructors: Vec<Entry>,
+    /// methods called on one
+    methods:      Vec<Entry>,
+}
+
+/// Everything `create_engine` registers, grouped by type
+#[derive(Debug)]
+struct Reference {
+    /// types by the name scripts know them by
+    types:     BTreeMap<String, TypeEntry>,
+    /// functions that aren't tied to a type
+    functions: Vec<Entry>,
+    /// functions of static modules, by the full name of their module
+    modules:   BTreeMap<String, Vec<Entry>>,
+}
+
+impl Reference {
+    /// Builds the reference from the metadata an engine has for its
+    /// registered functions. A function whose first parameter is a
+    /// registered type is a method of that type, and any other function
+    /// returning one creates it.
+    ///
+    /// * `engine`: the engine to describe
+    fn from_engine(engine: &Engine) -> Result<Self> {
+        let mut metadata: Metadata =
+            serde_json::from_str(&engine.gen_fn_metadata_to_json(false)?)
+                .context("Could not read the scripting API's metadata")?;
+
+        let names = metadata.custom_types
+                            .iter()
+                            .map(|t| (t.type_name.clone(), t.display_name.clone()))
+                            .collect::<HashMap<_, _>>();
+        for function in metadata.functions.iter_mut() {
+            for param in function.params.iter_mut() {
+                param.typ = param.typ.as_deref().map(|t| script_type(t, &names));
+            }
+            function.return_type = script_type(&function.return_type, &names);
+        }
+
+        let mut types = metadata.custom_types
+                                .iter()
+                                .map(|t| {
+                                    let docs = doc_text(&t.doc_comments);
+                                    (t.display_name.clone(),
+                                     TypeEntry { docs,
+                                                 ..Default::default() })
+                                })
+                                .collect::<BTreeMap<_, _>>();
+        let mut functions = vec![];
+
+        for function in &metadata.functions {
+            let this = function.params.first().and_then(|p| p.typ.clone());
+            if let Some(entry) = this.and_then(|t| types.get_mut(&t)) {
+                entry.methods.push(Entry::new(function, 1));
+            } else if let Some(entry) = types.get_mut(&function.return_type) {
+                entry.constructors.push(Entry::new(function, 0));
+            } else {
+                functions.push(Entry::new(function, 0));
+            }
+        }
+
+        types.retain(|_, t| !t.methods.is_empty() || !t.constructors.is_empty());
+
+        let mut modules = BTreeMap::new();
+        for (name, module) in &metadata.modules {
+            module.collect(name, &mut modules);
+        }
+
+        Ok(Self { types,
+                  functions,
+                  modules })
+    }
+
+    /// Renders the reference as markdown
+    fn to_markdown(&self) -> String {
+        let entries = |entries: &[Entry]| {
+            entries.iter()
+                   .map(|e| {
+                       if e.docs.is_empty() {
+                           format!("- `{}`\n", e.signature)
+                       } else {
+                           format!("- `{}` - {}\n", e.signature, e.docs)
+                       }
+                   })
+                   .collect::<String>()
+        };
+
+        let mut out = format!("# Scripting API reference\n\nThis is version {API_VERSION} of the \
+                               scripting API. Declare it at the top of a grading script with \
+                               `{API_VERSION_DIRECTIVE} {API_VERSION}`.\n\n## Functions\n\n{}",
+                              entries(&self.functions));
+
+        out.push_str("\n## Types\n");
+        for (name, entry) in &self.types {
+            out.push_str(&format!("\n### {name}\n\n"));
+            if !entry.docs.is_empty() {
+                out.push_str(&format!("{}\n\n", entry.docs));
+            }
+            if !entry.constructors.is_empty() {
+                out.push_str(&format!("Created with:\n\n{}\n", entries(&entry.constructors)));
+            }
+            if !entry.methods.is_empty() {
+                out.push_str(&format!("Methods:\n\n{}", entries(&entry.methods)));
+            }
+        }
+
+        if !self.modules.is_empty() {
+            out.push_str("\n## Modules\n");
+        }
+        for (name, functions) in &self.modules {
+            out.push_str(&format!("\n### {name}\n\nCalled as `{name}::function(...)`.\n\n{}",
+                                  entries(functions)));
+        }
+
+        out.push_str("\n## Removed functions\n\n");
+        out.push_str("Scripts that declare an older version, or none at all, can still call \
+                      these.\n\n");
+        out.push_str("| Function | Removed in version | How to migrate |\n");
+        out.push_str("| --- | --- | --- |\n");
+        for d in DEPRECATIONS.iter() {
+            out.push_str(&format!("| `{}` {} | {} | {} |\n",
+                                  d.name,
+                                  arguments(d.params),
+                                  d.removed_in,
+                                  d.hint));
+        }
+
+        out
+    }
+
+    /// Renders the reference as a self-contained HTML page
+    fn to_html(&self) -> String {
+        let entries = |entries: &[Entry]| {
+            let items = entries.iter()
+                               .map(|e| {
+                                   format!("<li><code>{}</code> {}</li>",
+                                           escape(&e.signature),
+                                           escape(&e.docs))
+                               })
+                               .collect::<String>();
+            format!("<ul>{items}</ul>\n")
+        };
+
+        let mut body = String::from("<h1>Scripting API reference</h1>\n");
+        body.push_str(&format!("<p>This is version {API_VERSION} of the scripting API. Declare \
+                                it at the top of a grading script with <code>{} \
+                                {API_VERSION}</code>.</p>\n",
+                               escape(API_VERSION_DIRECTIVE)));
+        body.push_str("<h2 id=\"functions\">Functions</h2>\n");
+        body.push_str(&entries(&self.functions));
+
+        body.push_str("<h2 id=\"types\">Types</h2>\n");
+        for (name, entry) in &self.types {
+            body.push_str(&format!("<h3 id=\"{name}\">{name}</h3>\n<p>{}</p>\n",
+                                   escape(&entry.docs)));
+            if !entry.constructors.is_empty() {
+                body.push_str(&format!("<p>Created with:</p>\n{}", entries(&entry.constructors)));
+            }
+            if !entry.methods.is_empty() {
+                body.push_str(&format!("<p>Methods:</p>\n{}", entries(&entry.methods)));
+            }
+        }
+
+        if !self.modules.is_empty() {
+            body.push_str("<h2 id=\"modules\">Modules</h2>\n");
+        }
+        for (name, functions) in &self.modules {
+            body.push_str(&format!("<h3 id=\"{name}\">{name}</h3>\n<p>Called as \
+                                    <code>{name}::function(...)</code>.</p>\n{}",
+                                   entries(functions)));
+        }
+
+        body.push_str("<h2 id=\"removed\">Removed functions</h2>\n");
+        body.push_str("<p>Scripts that declare an older version, or none at all, can still call \
+                       these.</p>\n");
+        body.push_str("<table>\n<tr><th>Function</th><th>Removed in version</th><th>How to \
+                       migrate</th></tr>\n");
+        for d in DEPRECATIONS.iter() {
+            body.push_str(&format!("<tr><td><code>{}</code> {}</td><td>{}</td><td>{}</td></tr>\n",
+                                   d.name,
+                                   arguments(d.params),
+                                   d.removed_in,
+                                   escape(d.hint)));
+        }
+        body.push_str("</table>\n");
+
+        let style = "body { font-family: sans-serif; max-width: 60em; margin: auto; } code { \
+                     background: #f3f3f3; } td, th { padding: 0.3em; text-align: left; }";
+        let mut page = String::from("<!DOCTYPE html>\n<html>\n<head>\n");
+        page.push_str("<meta charset=\"utf-8\">\n<title>Scripting API reference</title>\n");
+        page.push_str(&format!("<style>{style}</style>\n</head>\n"));
+        page.push_str(&format!("<body>\n{body}</body>\n</html>\n"));
+        page
+    }
+}
+
+/// Describes how many arguments a removed function took, e.g. `(1 argument)`
+///
+/// * `count`: the number of arguments
+fn arguments(count: usize) -> String {
+    match count {
+        1 => String::from("(1 argument)"),
+        n => format!("({n} arguments)"),
+    }
+}
+
+/// Escapes a string for use in HTML text
+///
+/// * `s`: the string to escape
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+     .replace('<', "&lt;")
+     .replace('>', "&gt;")
+     .replace('"', "&quot;")
+}
+
+/// Writes a reference of every type, builder method and function registered
+/// for grading scripts to `out`, along with `.d.rhai` definition files in
+/// `out/definitions` for editor autocompletion.
+///
+/// * `out`: folder to write to, created if needed
+/// * `format`: format of the reference
+pub fn write_script_docs(out: &Path,
+                         format: DocsFormat)
+                         -> Result<()> {
+    let engine = create_engine();
+    let reference = Reference::from_engine(&engine)?;
+
+    fs::create_dir_all(out).with_context(|| format!("Could not create {}", out.display()))?;
+    let contents = match format {
+        DocsFormat::Markdown => reference.to_markdown(),
+        DocsFormat::Html => reference.to_html(),
+    };
+    let path = out.join(format.file_name());
+    fs::write(&path, contents).with_context(|| format!("Could not write {}", path.display()))?;
+
+    let definitions = out.join("definitions");
+    engine.definitions()
+          .with_headers(true)
+          .write_to_dir(&definitions)
+          .with_context(|| format!("Could not write definitions to {}", definitions.display()))?;
+
+    println!("Wrote {} and definitions to {}",
+             path.display(),
+             definitions.display());
+    Ok(())
+}
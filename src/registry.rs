@@ -0,0 +1,406 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+use anyhow::{bail, ensure, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tabled::{object::Rows, Modify, TableIteratorExt, Tabled, Width};
+
+use crate::{
+    constants::{
+        COURSE, POSTGREST_CLIENT, REGISTRY_INDEX, RUNTIME, SCRIPTS_DIR, SCRIPT_SOURCE_ENV, TERM,
+        UMM_DIR,
+    },
+    rubric::RubricFormat,
+};
+
+#[derive(Tabled, Serialize, Deserialize, Clone, Debug)]
+/// An assignment a script source has a grading script or rubric for
+pub struct Assignment {
+    /// * `name`: what to pass to `umm grade`
+    #[tabled(rename = "Assignment")]
+    pub name:        String,
+    /// * `path`: path to the script or rubric, relative to the index
+    #[tabled(rename = "Script")]
+    pub path:        String,
+    /// * `sha256`: hex SHA-256 of the script, checked when it is fetched
+    #[tabled(skip)]
+    #[serde(default)]
+    pub sha256:      Option<String>,
+    /// * `signature`: base64 signature of the script, instead of a detached
+    ///   `.sig` file next to it
+    #[tabled(skip)]
+    #[serde(default)]
+    pub signature:   Option<String>,
+    /// * `description`: what the assignment is
+    #[tabled(rename = "Description")]
+    #[serde(default)]
+    pub description: String,
+}
+
+/// The `index.json` of a registry
+#[derive(Serialize, Deserialize, Debug)]
+struct Index {
+    /// assignments in the registry
+    assignments: Vec<Assignment>,
+}
+
+/// A grading script or rubric, and where it came from
+#[derive(Debug, Clone)]
+pub struct ResolvedScript {
+    /// its source
+    pub code:      String,
+    /// the path or URL it was read from
+    pub location:  String,
+    /// its signature, if the index had one
+    pub signature: Option<String>,
+    /// whether it was fetched from a database, git repository or URL, rather
+    /// than read from a local file
+    pub fetched:   bool,
+}
+
+impl ResolvedScript {
+    /// The format of the rubric this is, or `None` for grading scripts
+    pub fn rubric_format(&self) -> Option<RubricFormat> {
+        RubricFormat::from_path(Path::new(&self.location))
+    }
+}
+
+/// Where `umm grade <name>` looks up grading scripts, set with
+/// [SCRIPT_SOURCE_ENV]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptSource {
+    /// the course's `grading_scripts` table, for [COURSE] and [TERM]. Its
+    /// rows have no hashes, so scripts from it can't be checked.
+    Database,
+    /// a local folder, with an [REGISTRY_INDEX] or just script files
+    Dir(PathBuf),
+    /// a git repository laid out like a folder, checked out under
+    /// [struct@UMM_DIR]
+    Git(String),
+    /// an [REGISTRY_INDEX] served over HTTP, whose entries must have hashes
+    Http(String),
+}
+
+impl FromStr for ScriptSource {
+    type Err = anyhow::Error;
+
+    /// Parses `database`, `git:<url>`, an `http://` or `https://` URL, or a
+    /// folder, optionally written as `dir:<path>`
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        ensure!(!s.is_empty(), "Script source cannot be empty");
+
+        Ok(if s == "database" {
+            Self::Database
+        } else if let Some(url) = s.strip_prefix("git:") {
+            Self::Git(url.to_string())
+        } else if s.starts_with("http://") || s.starts_with("https://") {
+            Self::Http(s.trim_end_matches('/').to_string())
+        } else {
+            Self::Dir(PathBuf::from(s.strip_prefix("dir:").unwrap_or(s)))
+        })
+    }
+}
+
+impl Display for ScriptSource {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        match self {
+            Self::Database => write!(f, "the {COURSE} ({TERM}) database"),
+            Self::Dir(dir) => write!(f, "{}", dir.display()),
+            Self::Git(url) => write!(f, "git:{url}"),
+            Self::Http(url) => write!(f, "{url}"),
+        }
+    }
+}
+
+/// Hex SHA-256 of some bytes
+///
+/// * `bytes`: what to hash
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Fails if `code` doesn't hash to what `assignment` says it should
+///
+/// * `assignment`: the index entry
+/// * `code`: the script fetched for it
+fn verify(assignment: &Assignment,
+          code: &str)
+          -> Result<()> {
+    if let Some(expected) = &assignment.sha256 {
+        let actual = sha256_hex(code.as_bytes());
+        ensure!(actual.eq_ignore_ascii_case(expected.trim()),
+                "The script for {} does not match its hash in the index (expected {expected}, \
+                 got {actual}). It may have been tampered with.",
+                assignment.name);
+    }
+    Ok(())
+}
+
+/// Fetches a URL as text
+///
+/// * `url`: the URL
+fn get(url: &str) -> Result<String> {
+    let response = reqwest::blocking::get(url).with_context(|| format!("Cannot get url: {url}"))?;
+    ensure!(response.status().is_success(),
+            "Could not get {url}: {}",
+            response.status());
+    response.text()
+            .with_context(|| format!("Could not parse the response from {url} to text."))
+}
+
+impl ScriptSource {
+    /// The source set with [SCRIPT_SOURCE_ENV], or the local registry in
+    /// [struct@SCRIPTS_DIR] if it isn't set
+    pub fn configured() -> Result<Self> {
+        match std::env::var(SCRIPT_SOURCE_ENV) {
+            Ok(value) => value.parse()
+                              .with_context(|| format!("Invalid {SCRIPT_SOURCE_ENV}")),
+            Err(_) => Ok(Self::Dir(SCRIPTS_DIR.clone())),
+        }
+    }
+
+    /// Where a git source is checked out
+    ///
+    /// * `url`: the repository
+    fn checkout_dir(url: &str) -> PathBuf {
+        UMM_DIR.join("registry")
+               .join(&sha256_hex(url.as_bytes())[..16])
+    }
+
+    /// Clones a git source, or pulls it if it was cloned before, and returns
+    /// the folder it is checked out in. A failed pull only warns, so the last
+    /// checkout can still be used offline.
+    ///
+    /// * `url`: the repository
+    fn checkout(url: &str) -> Result<PathBuf> {
+        let dir = Self::checkout_dir(url);
+        if dir.join(".git").is_dir() {
+            let status = Command::new("git").arg("-C")
+                                            .arg(&dir)
+                                            .args(["pull", "--ff-only", "--quiet"])
+                                            .status();
+            match status {
+                Ok(s) if s.success() => {}
+                Ok(s) => tracing::warn!("Could not update {url} ({s}), using the last checkout"),
+                Err(e) => tracing::warn!("Could not run git to update {url} ({e}), using the \
+                                          last checkout"),
+            }
+        } else {
+            std::fs::create_dir_all(&dir).with_context(|| {
+                                             format!("Could not create {}", dir.display())
+                                         })?;
+            let status = Command::new("git").args(["clone", "--depth", "1", "--quiet", url])
+                                            .arg(&dir)
+                                            .status()
+                                            .context("Could not run git, is it installed?")?;
+            if !status.success() {
+                // Leaves no half-cloned folder to be mistaken for a checkout
+                let _ = std::fs::remove_dir_all(&dir);
+                bail!("Could not clone {url} ({status})");
+            }
+        }
+        Ok(dir)
+    }
+
+    /// Lists the assignments in a folder: the entries of its
+    /// [REGISTRY_INDEX], or every script and rubric in it if it has none
+    ///
+    /// * `dir`: the folder
+    fn list_dir(dir: &Path) -> Result<Vec<Assignment>> {
+        ensure!(dir.is_dir(),
+                "{} does not exist. Put grading scripts there, or set {SCRIPT_SOURCE_ENV} to \
+                 where they are.",
+                dir.display());
+
+        let index = dir.join(REGISTRY_INDEX);
+        if index.is_file() {
+            let index = std::fs::read_to_string(&index).with_context(|| {
+                                                           format!("Could not read {}",
+                                                                   index.display())
+                                                       })?;
+            let index: Index = serde_json::from_str(&index).context("Invalid registry index")?;
+            return Ok(index.assignments);
+        }
+
+        let entries =
+            std::fs::read_dir(dir).with_context(|| format!("Could not read {}", dir.display()))?;
+        let mut assignments = vec![];
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let is_script = path.extension().is_some_and(|e| e == "rhai")
+                            || RubricFormat::from_path(&path).is_some();
+            if !path.is_file() || !is_script {
+                continue;
+            }
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            assignments.push(Assignment { name:        name.to_string(),
+                                          path:        entry.file_name()
+                                                            .to_string_lossy()
+                                                            .to_string(),
+                                          sha256:      None,
+                                          signature:   None,
+                                          description: String::new(), });
+        }
+        assignments.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(assignments)
+    }
+
+    /// The URL of an HTTP source's index, and the URL entries are relative to
+    ///
+    /// * `url`: the source's URL, either the index itself or the folder it is
+    ///   in
+    fn http_urls(url: &str) -> (String, String) {
+        if url.ends_with(".json") {
+            let base = url.rsplit_once('/').map(|(base, _)| base).unwrap_or(url);
+            (url.to_string(), base.to_string())
+        } else {
+            (format!("{url}/{REGISTRY_INDEX}"), url.to_string())
+        }
+    }
+
+    /// Lists the assignments this source has scripts for
+    pub fn list(&self) -> Result<Vec<Assignment>> {
+        match self {
+            Self::Database => {
+                let rt = RUNTIME.handle().clone();
+                let resp = rt.block_on(async {
+                                 POSTGREST_CLIENT.from("grading_scripts")
+                                                 .eq("course", COURSE)
+                                                 .eq("term", TERM)
+                                                 .select("assignment,url")
+                                                 .execute()
+                                                 .await?
+                                                 .text()
+                                                 .await
+                                                 .context("Could not list grading scripts")
+                             })?;
+
+                /// A row of the `grading_scripts` table
+                #[derive(Deserialize)]
+                struct Row {
+                    /// name of the assignment
+                    assignment: String,
+                    /// where its script is
+                    url:        String,
+                }
+                let rows: Vec<Row> = serde_json::from_str(&resp).with_context(|| {
+                                                                    format!("Unexpected response: \
+                                                                             {resp}")
+                                                                })?;
+                Ok(rows.into_iter()
+                       .map(|r| Assignment { name:        r.assignment,
+                                             path:        r.url,
+                                             sha256:      None,
+                                             signature:   None,
+                                             description: String::new(), })
+                       .collect())
+            }
+            Self::Dir(dir) => Self::list_dir(dir),
+            Self::Git(url) => Self::list_dir(&Self::checkout(url)?),
+            Self::Http(url) => {
+                let (index_url, _) = Self::http_urls(url);
+                let index: Index =
+                    serde_json::from_str(&get(&index_url)?).context("Invalid registry index")?;
+                Ok(index.assignments)
+            }
+        }
+    }
+
+    /// Fetches the grading script or rubric for an assignment, checking it
+    /// against the hash in the index when there is one. Scripts served over
+    /// HTTP must have a hash.
+    ///
+    /// * `name`: name of the assignment
+    pub fn fetch(&self,
+                 name: &str)
+                 -> Result<ResolvedScript> {
+        let assignments = self.list()?;
+        let Some(assignment) = assignments.iter().find(|a| a.name == name) else {
+            bail!("No grading script for {name} in {self}. Run `umm assignments list` to see \
+                   what is available.");
+        };
+
+        let (code, location) = match self {
+            Self::Database => {
+                tracing::warn!("The script for {name} comes from {self}, which has no hashes to \
+                                check it against");
+                (get(&assignment.path)?, assignment.path.clone())
+            }
+            Self::Dir(dir) => Self::read_local(&dir.join(&assignment.path))?,
+            Self::Git(url) => Self::read_local(&Self::checkout_dir(url).join(&assignment.path))?,
+            Self::Http(url) => {
+                ensure!(assignment.sha256.is_some(),
+                        "{name} has no sha256 in the index at {url}, so its script cannot be \
+                         checked and won't be run");
+                let (_, base) = Self::http_urls(url);
+                let location = format!("{base}/{}", assignment.path);
+                (get(&location)?, location)
+            }
+        };
+
+        verify(assignment, &code)?;
+        Ok(ResolvedScript { code,
+                            location,
+                            signature: assignment.signature.clone(),
+                            fetched: !matches!(self, Self::Dir(_)) })
+    }
+
+    /// Reads a script from disk
+    ///
+    /// * `path`: where it is
+    fn read_local(path: &Path) -> Result<(String, String)> {
+        let code = std::fs::read_to_string(path).with_context(|| {
+                                                    format!("Could not read {}", path.display())
+                                                })?;
+        Ok((code, path.display().to_string()))
+    }
+}
+
+/// Reads a grading script or rubric from a path, or, if there is no such
+/// file, looks it up by assignment name in the configured [ScriptSource]
+///
+/// * `name_or_path`: path to a script, or name of an assignment
+pub fn resolve(name_or_path: &str) -> Result<ResolvedScript> {
+    let path = Path::new(name_or_path);
+    if path.is_file() {
+        let (code, location) = ScriptSource::read_local(path)?;
+        return Ok(ResolvedScript { code,
+                                   location,
+                                   signature: None,
+                                   fetched: false });
+    }
+
+    let name = name_or_path.replace(['\"', '\\'], "");
+    ScriptSource::configured()?.fetch(&name)
+}
+
+/// Prints the assignments the configured [ScriptSource] has scripts for
+pub fn list_assignments() -> Result<()> {
+    let source = ScriptSource::configured()?;
+    let assignments = source.list()?;
+
+    if assignments.is_empty() {
+        println!("No assignments in {source}.");
+    } else {
+        println!("Assignments in {source} (set {SCRIPT_SOURCE_ENV} to change where they come \
+                  from):");
+        println!("{}",
+                 assignments.iter()
+                            .table()
+                            .with(Modify::new(Rows::new(1..)).with(Width::wrap(60).keep_words()))
+                            .with(tabled::Style::modern()));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,591 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{fmt::Display, path::Path, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use rhai::{Array, Dynamic};
+use serde::Deserialize;
+
+use crate::{
+    grade::{
+        show_result, ApiGrader, ByHiddenTestGrader, ByUnitTestGrader, DiffGrader, DocsGrader,
+        GradeResult, GradescopeVisibility, MetricsGrader, NitGrader, QueryGrader, StyleGrader,
+        UnitTestGrader,
+    },
+    java::Project,
+    policy::{use_late_policy, LatePolicy},
+    registry::ResolvedScript,
+    spec::ApiSpec,
+};
+
+/// Formats a rubric can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RubricFormat {
+    /// TOML, from `.toml` files
+    Toml,
+    /// YAML, from `.yaml` or `.yml` files
+    Yaml,
+}
+
+impl RubricFormat {
+    /// All formats, in the order they are listed in help text
+    pub const ALL: [RubricFormat; 2] = [RubricFormat::Toml, RubricFormat::Yaml];
+
+    /// The format of a rubric at `path`, going by its extension, or `None` if
+    /// it isn't a rubric
+    ///
+    /// * `path`: path to the file
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|e| e.to_str())
+            .and_then(|e| e.parse().ok())
+    }
+}
+
+impl Display for RubricFormat {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        match self {
+            RubricFormat::Toml => write!(f, "toml"),
+            RubricFormat::Yaml => write!(f, "yaml"),
+        }
+    }
+}
+
+impl FromStr for RubricFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "toml" => Ok(RubricFormat::Toml),
+            "yaml" | "yml" => Ok(RubricFormat::Yaml),
+            _ => bail!("Unknown rubric format: {s}. Expected one of: {}",
+                       RubricFormat::ALL.iter()
+                                        .map(|f| f.to_string())
+                                        .collect::<Vec<_>>()
+                                        .join(", ")),
+        }
+    }
+}
+
+/// Student learning outcomes that feedback can be generated for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Slo {
+    /// Algorithmic Solutions
+    AlgorithmicSolutions,
+    /// Code Readability and Formatting
+    CodeReadability,
+    /// Comments
+    Comments,
+    /// Error Handling
+    ErrorHandling,
+    /// Logic
+    Logic,
+    /// Naming Conventions
+    NamingConventions,
+    /// Object Oriented Programming
+    OopProgramming,
+    /// Syntax
+    Syntax,
+    /// Testing
+    Testing,
+}
+
+impl Display for Slo {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        let name = match self {
+            Slo::AlgorithmicSolutions => "algorithmic_solutions",
+            Slo::CodeReadability => "code_readability",
+            Slo::Comments => "comments",
+            Slo::ErrorHandling => "error_handling",
+            Slo::Logic => "logic",
+            Slo::NamingConventions => "naming_conventions",
+            Slo::OopProgramming => "oop_programming",
+            Slo::Syntax => "syntax",
+            Slo::Testing => "testing",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// How results are shown and reported, the same options `show_results`
+/// takes in grading scripts
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RubricOutput {
+    /// source files to give feedback on
+    pub source_files:   Vec<String>,
+    /// test files to give feedback on
+    pub test_files:     Vec<String>,
+    /// fraction of the points a requirement needs to pass
+    pub pass_threshold: f64,
+    /// whether to show the grading table
+    pub show_table:     bool,
+    /// whether to write Gradescope results JSON
+    pub results_json:   bool,
+    /// whether to give feedback on penalties to students
+    pub feedback:       bool,
+    /// whether to write Gradescope results JSON to the current directory
+    pub debug:          bool,
+    /// learning outcomes to give feedback on
+    pub slos:           Vec<Slo>,
+}
+
+impl Default for RubricOutput {
+    fn default() -> Self {
+        Self { source_files:   vec![],
+               test_files:     vec![],
+               pass_threshold: 0.7,
+               show_table:     true,
+               results_json:   false,
+               feedback:       false,
+               debug:          false,
+               slos:           vec![], }
+    }
+}
+
+/// A style rule to enable, and how much its violations cost
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RubricStyleRule {
+    /// name of the rule, e.g. "naming" or "magic_numbers"
+    pub rule:    String,
+    /// penalty for each violation
+    pub penalty: f64,
+    /// maximum total penalty
+    pub cap:     f64,
+}
+
+/// A tree-sitter query, and the capture to pass on to the next one
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RubricQuery {
+    /// the query to run
+    pub query:   String,
+    /// the capture to pass on, if any
+    #[serde(default)]
+    pub capture: String,
+}
+
+/// How many times the last query of a requirement must match
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchCount {
+    /// at least once
+    #[default]
+    AtLeastOnce,
+    /// exactly this many times
+    Exactly(usize),
+    /// not at all
+    Never,
+}
+
+/// A grader, and its parameters. Each one builds the grader of the same name
+/// that grading scripts use.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "grader", rename_all = "snake_case", deny_unknown_fields)]
+pub enum RubricGrader {
+    /// checks for missing or malformed javadoc, with a `DocsGrader`
+    Docs {
+        /// files to check
+        files:   Vec<String>,
+        /// penalty for each issue
+        penalty: Option<f64>,
+    },
+    /// lints files, with a `StyleGrader`
+    Style {
+        /// files to lint
+        files:             Vec<String>,
+        /// rules to enable, all of them when empty
+        #[serde(default)]
+        rules:             Vec<RubricStyleRule>,
+        /// longest a method can be
+        max_method_length: Option<i64>,
+        /// deepest blocks can be nested
+        max_nesting_depth: Option<i64>,
+    },
+    /// checks code metrics against thresholds, with a `MetricsGrader`
+    Metrics {
+        /// files to check
+        files:             Vec<String>,
+        /// penalty for each violation
+        penalty:           Option<f64>,
+        /// highest cyclomatic complexity a method can have
+        max_complexity:    Option<i64>,
+        /// most lines of code a method can have
+        max_loc:           Option<i64>,
+        /// most parameters a method can have
+        max_parameters:    Option<i64>,
+        /// deepest blocks can be nested
+        max_nesting_depth: Option<i64>,
+        /// most classes a class can depend on
+        max_fan_out:       Option<i64>,
+    },
+    /// checks classes against an API spec, with an `ApiGrader`
+    Api {
+        /// path to a JSON spec, relative to the project root
+        spec_file: Option<String>,
+        /// the spec itself
+        spec:      Option<ApiSpec>,
+    },
+    /// runs JUnit tests and grades by how many pass, with a
+    /// `ByUnitTestGrader`
+    UnitTests {
+        /// test classes to run
+        test_files:     Vec<String>,
+        /// tests that must exist, as `Class#method`
+        #[serde(default)]
+        expected_tests: Vec<String>,
+    },
+    /// runs tests hidden from students, with a `ByHiddenTestGrader`
+    HiddenTests {
+        /// URL to download the test class from
+        url:             String,
+        /// name of the test class
+        test_class_name: String,
+    },
+    /// grades the students' own tests by mutation testing, with a
+    /// `UnitTestGrader`
+    MutationTests {
+        /// test classes to run
+        target_test:      Vec<String>,
+        /// classes to mutate
+        target_class:     Vec<String>,
+        /// methods not to mutate
+        #[serde(default)]
+        excluded_methods: Vec<String>,
+        /// calls not to mutate
+        #[serde(default)]
+        avoid_calls_to:   Vec<String>,
+    },
+    /// runs a file and compares its output, with a `DiffGrader`
+    Diff {
+        /// name of the class to run
+        file:        String,
+        /// what to write to standard input for each case, nothing when empty
+        #[serde(default)]
+        input:       Vec<String>,
+        /// output expected for each case
+        expected:    Vec<String>,
+        /// whether to compare ignoring case
+        #[serde(default)]
+        ignore_case: bool,
+    },
+    /// runs tree-sitter queries on a file, with a `QueryGrader`
+    Query {
+        /// name of the class to query
+        file:                  String,
+        /// queries to run, each on the capture of the one before it
+        queries:               Vec<RubricQuery>,
+        /// how many times the last query must match
+        #[serde(default)]
+        must_match:            MatchCount,
+        /// reason to give the student when it fails
+        #[serde(default)]
+        reason:                String,
+        /// whether to only look at methods changed from the starter code
+        #[serde(default)]
+        only_modified_methods: bool,
+    },
+}
+
+/// A requirement of an assignment
+#[derive(Debug, Clone, Deserialize)]
+pub struct Requirement {
+    /// name of the requirement
+    pub name:       String,
+    /// points possible
+    pub points:     f64,
+    /// when students see the result on Gradescope, always if not given
+    pub visibility: Option<GradescopeVisibility>,
+    /// how it is graded
+    #[serde(flatten)]
+    pub grader:     RubricGrader,
+}
+
+/// Converts a list of strings to a rhai array, as graders take
+///
+/// * `items`: the strings
+fn array(items: &[String]) -> Array {
+    items.iter().map(|i| i.clone().into()).collect()
+}
+
+impl Requirement {
+    /// Builds the grader for this requirement, and runs it
+    ///
+    /// * `project`: the project to grade
+    pub fn grade(&self,
+                 project: &Project)
+                 -> Result<GradeResult> {
+        let name = self.name.clone();
+        let project = project.clone();
+
+        match &self.grader {
+            RubricGrader::Docs { files, penalty } => {
+                let mut grader = DocsGrader::default().set_req_name(name)
+                                                      .set_out_of(self.points)
+                                                      .set_project(project)
+                                                      .set_files(array(files));
+                if let Some(penalty) = penalty {
+                    grader = grader.set_penalty(*penalty);
+                }
+                grader.grade_docs()
+            }
+            RubricGrader::Style { files,
+                                  rules,
+                                  max_method_length,
+                                  max_nesting_depth, } => {
+                let mut grader = StyleGrader::default().set_req_name(name)
+                                                       .set_out_of(self.points)
+                                                       .set_project(project)
+                                                       .set_files(array(files));
+                for rule in rules {
+                    grader = grader.rule(rule.rule.clone(), rule.penalty, rule.cap)?;
+                }
+                if let Some(max) = max_method_length {
+                    grader = grader.set_max_method_length(*max);
+                }
+                if let Some(max) = max_nesting_depth {
+                    grader = grader.set_max_nesting_depth(*max);
+                }
+                grader.grade_style()
+            }
+            RubricGrader::Metrics { files,
+                                    penalty,
+                                    max_complexity,
+                                    max_loc,
+                                    max_parameters,
+                                    max_nesting_depth,
+                                    max_fan_out, } => {
+                let mut grader = MetricsGrader::default().set_req_name(name)
+                                                         .set_out_of(self.points)
+                                                         .set_project(project)
+                                                         .set_files(array(files));
+                if let Some(penalty) = penalty {
+                    grader = grader.set_penalty(*penalty);
+                }
+                if let Some(max) = max_complexity {
+                    grader = grader.set_max_complexity(*max);
+                }
+                if let Some(max) = max_loc {
+                    grader = grader.set_max_loc(*max);
+                }
+                if let Some(max) = max_parameters {
+                    grader = grader.set_max_parameters(*max);
+                }
+                if let Some(max) = max_nesting_depth {
+                    grader = grader.set_max_nesting_depth(*max);
+                }
+                if let Some(max) = max_fan_out {
+                    grader = grader.set_max_fan_out(*max);
+                }
+                grader.grade_metrics()
+            }
+            RubricGrader::Api { spec_file, spec } => {
+                let grader = ApiGrader::default().set_req_name(name)
+                                                 .set_out_of(self.points)
+                                                 .set_project(project);
+                let grader = match (spec_file, spec) {
+                    (Some(path), None) => grader.set_spec_file(path.clone())?,
+                    (None, Some(spec)) => ApiGrader { spec: spec.clone(),
+                                                      ..grader },
+                    _ => bail!("An api requirement needs exactly one of `spec_file` or `spec`"),
+                };
+                grader.grade_api()
+            }
+            RubricGrader::UnitTests { test_files,
+                                      expected_tests, } => {
+                ByUnitTestGrader::default().set_req_name(name)
+                                           .set_out_of(self.points)
+                                           .set_project(project)
+                                           .set_test_files(array(test_files))
+                                           .set_expected_tests(array(expected_tests))
+                                           .grade_by_tests()
+            }
+            RubricGrader::HiddenTests { url,
+                                        test_class_name, } => {
+                ByHiddenTestGrader::default().set_req_name(name)
+                                             .set_out_of(self.points)
+                                             .set_url(url.clone())
+                                             .set_test_class_name(test_class_name.clone())
+                                             .grade_by_hidden_tests()
+            }
+            RubricGrader::MutationTests { target_test,
+                                          target_class,
+                                          excluded_methods,
+                                          avoid_calls_to, } => {
+                UnitTestGrader::default().set_req_name(name)
+                                         .set_out_of(self.points)
+                                         .set_target_test(array(target_test))
+                                         .set_target_class(array(target_class))
+                                         .set_excluded_methods(array(excluded_methods))
+                                         .set_avoid_calls_to(array(avoid_calls_to))
+                                         .grade_unit_tests()
+            }
+            RubricGrader::Diff { file,
+                                 input,
+                                 expected,
+                                 ignore_case, } => {
+                // Cases without input still need one, empty, per expected output
+                let input = if input.is_empty() {
+                    vec![String::new(); expected.len()]
+                } else {
+                    input.clone()
+                };
+                DiffGrader::default().set_req_name(name)
+                                     .set_out_of(self.points)
+                                     .set_project(project)
+                                     .set_file(file.clone())
+                                     .set_input(array(&input))
+                                     .set_expected(array(expected))
+                                     .set_ignore_case(*ignore_case)
+                                     .grade_by_diff()
+            }
+            RubricGrader::Query { file,
+                                  queries,
+                                  must_match,
+                                  reason,
+                                  only_modified_methods, } => {
+                let mut grader = QueryGrader::default().set_req_name(name)
+                                                       .set_out_of(self.points)
+                                                       .set_project(project)
+                                                       .set_file(file.clone())
+                                                       .set_reason(reason.clone());
+                for query in queries {
+                    grader = grader.query(query.query.clone())?;
+                    if !query.capture.is_empty() {
+                        grader = grader.capture(query.capture.clone())?;
+                    }
+                }
+                grader = match must_match {
+                    MatchCount::AtLeastOnce => grader.must_match_at_least_once(),
+                    MatchCount::Exactly(n) => grader.must_match_exactly_n_times(*n),
+                    MatchCount::Never => grader.must_not_match(),
+                };
+                if *only_modified_methods {
+                    grader = grader.only_modified_methods();
+                }
+                grader.grade_by_query()
+            }
+        }
+    }
+}
+
+/// A declarative alternative to a grading script: the requirements of an
+/// assignment, how each is graded, and how the results are reported
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Rubric {
+    /// title of the assignment, needed for learning outcome feedback
+    #[serde(default)]
+    pub title:        String,
+    /// description of the assignment, needed for learning outcome feedback
+    #[serde(default)]
+    pub description:  String,
+    /// how results are shown and reported
+    #[serde(default)]
+    pub output:       RubricOutput,
+    /// deduction for late submissions, taken from the total
+    #[serde(default)]
+    pub late_policy:  Option<LatePolicy>,
+    /// the requirements, graded in order
+    #[serde(rename = "requirement", alias = "requirements")]
+    pub requirements: Vec<Requirement>,
+}
+
+impl Rubric {
+    /// Parses a rubric
+    ///
+    /// * `source`: the rubric
+    /// * `format`: the format it is written in
+    pub fn parse(source: &str,
+                 format: RubricFormat)
+                 -> Result<Self> {
+        let rubric: Rubric = match format {
+            RubricFormat::Toml => toml::from_str(source)?,
+            RubricFormat::Yaml => serde_yaml::from_str(source)?,
+        };
+
+        let mut names = std::collections::HashSet::new();
+        for requirement in &rubric.requirements {
+            if !names.insert(&requirement.name) {
+                bail!("Requirement {} is listed more than once", requirement.name);
+            }
+        }
+
+        Ok(rubric)
+    }
+
+    /// The options to pass to `show_results`
+    pub fn show_results_config(&self) -> Result<rhai::Map> {
+        let output = &self.output;
+        let mut config = rhai::Map::new();
+        let mut set = |key: &str, value: Dynamic| {
+            config.insert(key.into(), value);
+        };
+
+        set("source_files", array(&output.source_files).into());
+        set("test_files", array(&output.test_files).into());
+        set("project_title", self.title.clone().into());
+        set("project_description", self.description.clone().into());
+        set("pass_threshold", output.pass_threshold.into());
+        set("show_table", output.show_table.into());
+        set("results_json", output.results_json.into());
+        set("feedback", output.feedback.into());
+        set("debug", output.debug.into());
+        for slo in &output.slos {
+            set(&format!("slo_{slo}"), true.into());
+        }
+
+        let mut visibility = rhai::Map::new();
+        for requirement in &self.requirements {
+            if let Some(v) = &requirement.visibility {
+                let v = rhai::serde::to_dynamic(v).map_err(|e| anyhow::anyhow!("{e}"))?;
+                visibility.insert(requirement.name.as_str().into(), v);
+            }
+        }
+        set("visibility", visibility.into());
+
+        Ok(config)
+    }
+
+    /// Grades every requirement, and shows the results
+    pub fn grade(&self) -> Result<()> {
+        let project = Project::new()?;
+        let mut results = Array::new();
+        for requirement in &self.requirements {
+            let result = requirement.grade(&project)
+                                    .with_context(|| {
+                                        format!("Could not grade requirement {}", requirement.name)
+                                    })?;
+            results.push(Dynamic::from(result));
+        }
+
+        if let Some(policy) = &self.late_policy {
+            use_late_policy(policy.clone())?;
+        }
+        show_result(results, self.show_results_config()?)
+    }
+}
+
+/// Grades the project with a rubric instead of a grading script
+///
+/// * `script`: the rubric
+/// * `format`: the format it is written in
+pub fn grade_rubric(script: &ResolvedScript,
+                    format: RubricFormat)
+                    -> Result<()> {
+    let rubric = Rubric::parse(&script.code, format).with_context(|| {
+                                                        format!("Could not read rubric {}",
+                                                                script.location)
+                                                    })?;
+
+    // Project::new doesn't download anything, but graders need JUnit and PIT
+    Project::new()?.ensure_libraries()?;
+    rubric.grade()
+}
@@ -0,0 +1,475 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt::Display,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{bail, Context, Result};
+use rhai::{ASTNode, Array, Dynamic, Expr, FnCallExpr, NativeCallContext, Position, Stmt, AST};
+
+use crate::{
+    compat::{declared_api_version, deprecations_for, API_VERSION, API_VERSION_DIRECTIVE},
+    constants::{SCRIPT_API_VERSION, SCRIPT_AST},
+    create_engine_for,
+    grade::GradeResult,
+    java::Project,
+    read_script,
+};
+
+/// Functions rhai handles itself, which don't show up in the registered API
+const KEYWORDS: [&str; 11] = ["print",
+                              "debug",
+                              "type_of",
+                              "Fn",
+                              "call",
+                              "curry",
+                              "eval",
+                              "is_shared",
+                              "is_def_fn",
+                              "is_def_var",
+                              "this"];
+
+/// Builder methods whose string arguments are class names, such as
+/// `files(["pkg.Main"])`
+const CLASS_ARGUMENTS: [&str; 6] = ["files",
+                                    "test_files",
+                                    "target_test",
+                                    "target_class",
+                                    "file",
+                                    "identify"];
+
+/// Builder methods whose string arguments are test names, such as
+/// `expected_tests(["pkg.MainTest#testAdd"])`
+const TEST_ARGUMENTS: [&str; 1] = ["expected_tests"];
+
+/// A problem found in a grading script
+#[derive(Debug, Clone)]
+pub struct ScriptIssue {
+    /// line the problem is on, if known
+    pub line:    Option<usize>,
+    /// a description of the problem
+    pub message: String,
+}
+
+impl ScriptIssue {
+    /// Creates an issue at a position in the script
+    ///
+    /// * `position`: where the problem is
+    /// * `message`: a description of the problem
+    fn at(position: Position,
+          message: String)
+          -> Self {
+        Self { line: position.line(),
+               message }
+    }
+}
+
+impl Display for ScriptIssue {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {line}: {}", self.message),
+            None => write!(f, "{}", self.message),
+        }
+    }
+}
+
+/// A function or method call in a script
+#[derive(Debug, Clone)]
+struct Call {
+    /// name of the function
+    name:     String,
+    /// number of arguments, including the object a method is called on
+    arity:    usize,
+    /// whether it was called as a method, `x.name(...)`
+    method:   bool,
+    /// where the call is
+    position: Position,
+    /// string literals passed to it, including inside arrays
+    strings:  Vec<String>,
+}
+
+impl Call {
+    /// Describes a call expression
+    ///
+    /// * `call`: the call
+    /// * `method`: whether it is a method call
+    /// * `position`: where it is
+    fn new(call: &FnCallExpr,
+           method: bool,
+           position: Position)
+           -> Self {
+        Self { name: call.name.to_string(),
+               arity: call.args.len() + usize::from(method),
+               method,
+               position,
+               strings: call.args.iter().flat_map(strings_in).collect() }
+    }
+}
+
+/// String literals in an expression, including inside array literals
+///
+/// * `expr`: the expression
+fn strings_in(expr: &Expr) -> Vec<String> {
+    /// String values in a constant, including inside arrays
+    fn strings_in_value(value: &Dynamic) -> Vec<String> {
+        if let Some(s) = value.read_lock::<rhai::ImmutableString>() {
+            vec![s.to_string()]
+        } else if let Some(array) = value.read_lock::<Array>() {
+            array.iter().flat_map(strings_in_value).collect()
+        } else {
+            vec![]
+        }
+    }
+
+    match expr {
+        Expr::StringConstant(s, ..) => vec![s.to_string()],
+        Expr::Array(items, ..) => items.iter().flat_map(strings_in).collect(),
+        Expr::DynamicConstant(value, ..) => strings_in_value(value),
+        _ => vec![],
+    }
+}
+
+/// Records calls and variable names while walking a script. Method call
+/// arguments aren't visited by rhai's walker, so they are walked here.
+///
+/// * `path`: the node being visited, and its ancestors
+/// * `calls`: calls found so far
+/// * `variables`: variable names found so far
+fn visit(path: &[ASTNode],
+         calls: &mut Vec<Call>,
+         variables: &mut HashSet<String>)
+         -> bool {
+    match path.last() {
+        Some(ASTNode::Expr(Expr::FnCall(call, position)))
+        | Some(ASTNode::Stmt(Stmt::FnCall(call, position))) => {
+            if !call.is_qualified() && !call.is_operator_call() {
+                calls.push(Call::new(call, false, *position));
+            }
+        }
+        Some(ASTNode::Expr(Expr::MethodCall(call, position))) => {
+            calls.push(Call::new(call, true, *position));
+            for arg in call.args.iter() {
+                arg.walk(&mut vec![], &mut |p| visit(p, calls, variables));
+            }
+        }
+        Some(ASTNode::Stmt(Stmt::Var(var, ..))) => {
+            variables.insert(var.0.name.to_string());
+        }
+        _ => {}
+    }
+    true
+}
+
+/// Splits a signature like `files(self: DocsGrader, files: Array) ->
+/// DocsGrader` into its name and number of parameters
+///
+/// * `signature`: a signature from `Engine::gen_fn_signatures`
+fn parse_signature(signature: &str) -> Option<(String, usize)> {
+    let (name, rest) = signature.split_once('(')?;
+
+    let mut depth = 0;
+    let mut params = 0;
+    let mut empty = true;
+    for c in rest.chars() {
+        match c {
+            '(' | '<' | '[' => depth += 1,
+            ')' if depth == 0 => break,
+            ')' | '>' | ']' => depth -= 1,
+            ',' if depth == 0 => params += 1,
+            c if !c.is_whitespace() => empty = false,
+            _ => {}
+        }
+    }
+
+    Some((name.to_string(), if empty { 0 } else { params + 1 }))
+}
+
+/// Checks that every function a script calls is registered with a matching
+/// number of arguments, or defined by the script itself
+///
+/// * `ast`: the compiled script
+/// * `signatures`: registered signatures, from `Engine::gen_fn_signatures`
+/// * `calls`: calls found in the script
+/// * `variables`: variable names in the script, which may hold closures
+fn check_calls(ast: &AST,
+               signatures: &[String],
+               calls: &[Call],
+               variables: &HashSet<String>)
+               -> Vec<ScriptIssue> {
+    let mut known = BTreeMap::<String, Vec<(usize, String)>>::new();
+    for signature in signatures {
+        if let Some((name, params)) = parse_signature(signature) {
+            known.entry(name)
+                 .or_default()
+                 .push((params, signature.clone()));
+        }
+    }
+
+    let mut variables = variables.clone();
+    for f in ast.iter_functions() {
+        variables.extend(f.params.iter().map(|p| p.to_string()));
+        known.entry(f.name.to_string())
+             .or_default()
+             .push((f.params.len(), f.to_string()));
+    }
+
+    let mut issues = vec![];
+    for call in calls {
+        if KEYWORDS.contains(&call.name.as_str())
+           || (!call.method && variables.contains(&call.name))
+        {
+            continue;
+        }
+
+        let shown = if call.method {
+            format!(".{}(...)", call.name)
+        } else {
+            format!("{}(...)", call.name)
+        };
+        match known.get(&call.name) {
+            None => issues.push(ScriptIssue::at(call.position,
+                                                format!("{shown} is not a registered function"))),
+            Some(overloads) if !overloads.iter().any(|(n, _)| *n == call.arity) => {
+                let given = call.arity - usize::from(call.method);
+                let expected =
+                    overloads.iter()
+                             .map(|(_, s)| format!("    {}", s.split(" -> ").next().unwrap_or(s)))
+                             .collect::<Vec<_>>()
+                             .join("\n");
+                issues.push(ScriptIssue::at(call.position,
+                                            format!("{shown} is called with {given} \
+                                                     argument(s), but is registered \
+                                                     as:\n{expected}")));
+            }
+            Some(_) => {}
+        }
+    }
+
+    issues
+}
+
+/// Checks that classes and tests a script refers to exist in a reference
+/// project
+///
+/// * `project`: the reference project
+/// * `calls`: calls found in the script
+fn check_references(project: &Project,
+                    calls: &[Call])
+                    -> Vec<ScriptIssue> {
+    let mut issues = vec![];
+
+    for call in calls {
+        if CLASS_ARGUMENTS.contains(&call.name.as_str()) {
+            for name in &call.strings {
+                if let Err(e) = project.identify(name) {
+                    issues.push(ScriptIssue::at(call.position, format!("{e:#}")));
+                }
+            }
+        } else if TEST_ARGUMENTS.contains(&call.name.as_str()) {
+            for test in &call.strings {
+                let Some((class, _)) = test.split_once('#') else {
+                    issues.push(ScriptIssue::at(call.position,
+                                                format!("{test} should look like \
+                                                         Class#testMethod")));
+                    continue;
+                };
+                match project.identify(class) {
+                    Ok(file) if file.test_methods().contains(test) => {}
+                    Ok(_) => issues.push(ScriptIssue::at(call.position,
+                                                         format!("{class} has no test called \
+                                                                  {test}"))),
+                    Err(e) => issues.push(ScriptIssue::at(call.position, format!("{e:#}"))),
+                }
+            }
+        }
+    }
+
+    issues
+}
+
+/// Results a dry run passed to `show_results`, and anything it passed that
+/// wasn't a result
+#[derive(Default)]
+struct Shown {
+    /// the results
+    results: Vec<GradeResult>,
+    /// values that weren't results
+    issues:  Vec<ScriptIssue>,
+}
+
+impl Shown {
+    /// Records what a script passed to `show_results`
+    ///
+    /// * `position`: where `show_results` was called
+    /// * `results`: what it was passed
+    fn record(&mut self,
+              position: Position,
+              results: Array) {
+        for result in results {
+            let type_name = result.type_name();
+            match result.try_cast::<GradeResult>() {
+                Some(result) => self.results.push(result),
+                None => {
+                    let message = format!("show_results expects GradeResults, but got {type_name}");
+                    self.issues.push(ScriptIssue::at(position, message));
+                }
+            }
+        }
+    }
+}
+
+/// Runs a script against a reference solution, returning the results passed
+/// to `show_results`, and problems with what it passed
+///
+/// * `script`: source of the script
+/// * `path`: absolute path to the script, if it is a local file, so it can
+///   import modules from its folder
+fn dry_run(script: &str,
+           path: Option<&Path>)
+           -> Result<(Vec<GradeResult>, Vec<ScriptIssue>)> {
+    let shown = Arc::new(Mutex::new(Shown::default()));
+
+    let mut engine = create_engine_for(script)?;
+    let sink = Arc::clone(&shown);
+    engine.register_fn("show_results",
+                       move |context: NativeCallContext, results: Array, _: rhai::Map| {
+                           sink.lock().unwrap().record(context.position(), results);
+                       });
+    if deprecations_for(declared_api_version(script)?).any(|d| d.name == "show_results") {
+        let sink = Arc::clone(&shown);
+        engine.register_fn("show_results",
+                           move |context: NativeCallContext, results: Array| {
+                               sink.lock().unwrap().record(context.position(), results);
+                           });
+    }
+
+    let mut ast = engine.compile(script).map_err(|e| anyhow::anyhow!("{e}"))?;
+    if let Some(path) = path {
+        ast.set_source(path.to_string_lossy().as_ref());
+    }
+
+    Project::new()?.ensure_libraries()?;
+    *SCRIPT_AST.lock().unwrap() = ast.clone();
+    *SCRIPT_API_VERSION.lock().unwrap() = declared_api_version(script)?;
+    engine.run_ast(&ast)
+          .map_err(|e| anyhow::anyhow!("{e}"))
+          .context("Grading script failed on the reference solution")?;
+
+    let shown = std::mem::take(&mut *shown.lock().unwrap());
+    Ok((shown.results, shown.issues))
+}
+
+/// Checks a grading script without grading a submission. The script is
+/// compiled, and every function it calls is looked up in the API
+/// `create_engine` registers. With a reference solution, the classes and
+/// tests it mentions are looked up too, and the script is run against it to
+/// find the total a full-marks submission gets.
+///
+/// * `name_or_path`: name of an assignment, or path to a script
+/// * `reference`: folder containing a reference solution
+pub fn check_script(name_or_path: &str,
+                    reference: Option<&Path>)
+                    -> Result<()> {
+    let script = read_script(name_or_path)?;
+    let version = declared_api_version(&script)?;
+    let engine = create_engine_for(&script)?;
+    let ast = engine.compile(&script)
+                    .map_err(|e| anyhow::anyhow!("{e}"))
+                    .with_context(|| format!("{name_or_path} does not compile"))?;
+
+    let mut calls = vec![];
+    let mut variables = HashSet::new();
+    ast.walk(&mut |path| visit(path, &mut calls, &mut variables));
+
+    let mut issues = check_calls(&ast, &engine.gen_fn_signatures(true), &calls, &variables);
+
+    let results = match reference {
+        Some(reference) => {
+            let path = std::fs::canonicalize(name_or_path).ok();
+            let current = std::env::current_dir()?;
+            std::env::set_current_dir(reference).with_context(|| {
+                                                    format!("Could not open {}",
+                                                            reference.display())
+                                                })?;
+
+            let outcome = Project::new().and_then(|project| {
+                                            issues.extend(check_references(&project, &calls));
+                                            if issues.is_empty() {
+                                                let (results, invalid) =
+                                                    dry_run(&script, path.as_deref())?;
+                                                issues.extend(invalid);
+                                                Ok(Some(results))
+                                            } else {
+                                                Ok(None)
+                                            }
+                                        });
+
+            std::env::set_current_dir(current)?;
+            outcome?
+        }
+        None => None,
+    };
+
+    issues.sort_by_key(|i| i.line);
+    if !issues.is_empty() {
+        eprintln!("{name_or_path} has {} problem(s):", issues.len());
+        for issue in &issues {
+            eprintln!("- {issue}");
+        }
+        bail!("{name_or_path} is not ready to use");
+    }
+
+    println!("{name_or_path} compiles, and every function it calls is registered.");
+
+    let deprecated = calls.iter()
+                          .filter_map(|call| {
+                              deprecations_for(version).find(|d| {
+                                                           !call.method
+                                                           && d.name == call.name
+                                                           && d.params == call.arity
+                                                       })
+                                                       .map(|d| (call, d))
+                          })
+                          .collect::<Vec<_>>();
+    if !deprecated.is_empty() {
+        println!("It uses {} function(s) removed from the scripting API:",
+                 deprecated.len());
+        for (call, d) in deprecated {
+            let issue = ScriptIssue::at(call.position,
+                                        format!("{}() was removed in version {}, {}",
+                                                d.name, d.removed_in, d.hint));
+            println!("- {issue}");
+        }
+    }
+    if version.is_none() {
+        println!("It doesn't declare a scripting API version, so every compatibility shim is \
+                  available. Add `{API_VERSION_DIRECTIVE} {API_VERSION}` to it once it only uses \
+                  the current API.");
+    }
+
+    match results {
+        Some(mut results) => {
+            let grade = results.iter_mut().map(|r| r.grade()).sum::<f64>();
+            let out_of = results.iter_mut().map(|r| r.out_of()).sum::<f64>();
+            println!("The reference solution gets {grade:.2}/{out_of:.2}.");
+            for result in results.iter_mut() {
+                let (grade, out_of) = (result.grade(), result.out_of());
+                if grade < out_of {
+                    println!("- {}: {grade:.2}/{out_of:.2} {}",
+                             result.requirement(),
+                             result.reason());
+                }
+            }
+        }
+        None => println!("Pass --reference with a reference solution to also check class and \
+                          test names, and find the full-marks total."),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,379 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use ring::{
+    rand::SystemRandom,
+    signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    constants::{
+        ATTESTATION_KEY_ENV, GRADED_SCRIPT_SHA256, ROOT_DIR, SIGNATURE_EXTENSION, TRUSTED_KEYS,
+        TRUSTED_KEYS_ENV, UMM_DIR,
+    },
+    grade::GradeResult,
+    java::Project,
+    registry::ResolvedScript,
+};
+
+/// Public keys scripts, modules and hidden tests must be signed with: the ones
+/// built into umm, or if there are none, the ones in [TRUSTED_KEYS_ENV]. Keys
+/// from the environment are ignored when some are built in, so students can't
+/// trust a key of their own.
+///
+/// Without any, only local files can be used, see [verify].
+pub fn trusted_keys() -> Result<Vec<Vec<u8>>> {
+    let keys = match TRUSTED_KEYS.filter(|k| !k.trim().is_empty()) {
+        Some(keys) => keys.to_string(),
+        None => std::env::var(TRUSTED_KEYS_ENV).unwrap_or_default(),
+    };
+
+    keys.split(';')
+        .map(str::trim)
+        .filter(|k| !k.is_empty())
+        .map(|k| {
+            let key = STANDARD.decode(k)
+                              .with_context(|| format!("Invalid trusted key: {k}"))?;
+            ensure!(key.len() == 32,
+                    "Invalid trusted key: {k}, expected 32 bytes");
+            Ok(key)
+        })
+        .collect()
+}
+
+/// Whether any of `keys` signed `message`
+///
+/// * `keys`: public keys
+/// * `message`: what was signed
+/// * `signature`: base64 signature
+fn signed_by_any(keys: &[Vec<u8>],
+                 message: &[u8],
+                 signature: &str)
+                 -> Result<bool> {
+    let signature = STANDARD.decode(signature.trim())
+                            .context("Signature is not valid base64")?;
+    Ok(keys.iter().any(|key| {
+                      UnparsedPublicKey::new(&ED25519, key).verify(message, &signature)
+                                                           .is_ok()
+                  }))
+}
+
+/// Fetches the detached signature next to a script or file, `<location>.sig`,
+/// if there is one
+///
+/// * `location`: path or URL of the signed file
+fn detached_signature(location: &str) -> Option<String> {
+    let location = format!("{location}.{SIGNATURE_EXTENSION}");
+    if location.starts_with("http://") || location.starts_with("https://") {
+        let response = reqwest::blocking::get(&location).ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        response.text().ok()
+    } else {
+        std::fs::read_to_string(location).ok()
+    }
+}
+
+/// Fails unless `bytes` are signed by a trusted key. Without trusted keys,
+/// local files are used unchecked, but fetched ones are refused, as anyone
+/// could otherwise point umm at a copy they changed.
+///
+/// * `what`: what is being checked, for error messages
+/// * `location`: path or URL the bytes came from
+/// * `bytes`: the contents
+/// * `signature`: the base64 signature, if it was given alongside them.
+///   Otherwise it is read from `<location>.sig`.
+/// * `fetched`: whether they came from a database, git repository or URL
+fn verify(what: &str,
+          location: &str,
+          bytes: &[u8],
+          signature: Option<String>,
+          fetched: bool)
+          -> Result<()> {
+    let keys = trusted_keys()?;
+    if keys.is_empty() {
+        ensure!(!fetched,
+                "{what} at {location} can't be checked, as there are no trusted keys. Set \
+                 {TRUSTED_KEYS_ENV}, or build umm with it set, to use it.");
+        return Ok(());
+    }
+
+    let Some(signature) = signature.or_else(|| detached_signature(location)) else {
+        bail!("{what} at {location} isn't signed, and only signed ones can be used");
+    };
+    ensure!(signed_by_any(&keys, bytes, &signature)?,
+            "{what} at {location} isn't signed by a trusted key. It may have been tampered with.");
+    Ok(())
+}
+
+/// Fails unless a grading script or rubric is signed by a trusted key, see
+/// [verify]. Checked before it is compiled or parsed.
+///
+/// * `script`: the script
+pub fn verify_script(script: &ResolvedScript) -> Result<()> {
+    verify("The grading script",
+           &script.location,
+           script.code.as_bytes(),
+           script.signature.clone(),
+           script.fetched)
+}
+
+/// Fails unless a module imported by a grading script is signed by a trusted
+/// key, see [verify]. Checked before it is compiled, wherever it was found.
+/// Its signature is read from `<location>.sig`.
+///
+/// * `location`: path or URL of the module
+/// * `code`: its source
+/// * `fetched`: whether it was downloaded
+pub fn verify_module(location: &str,
+                     code: &str,
+                     fetched: bool)
+                     -> Result<()> {
+    verify("The module", location, code.as_bytes(), None, fetched)
+}
+
+/// Fails unless a downloaded file, like hidden tests, is signed by a trusted
+/// key. Its signature is downloaded from `<url>.sig`.
+///
+/// * `url`: where it was downloaded from
+/// * `bytes`: its contents
+pub fn verify_download(url: &str,
+                       bytes: &[u8])
+                       -> Result<()> {
+    verify("The file", url, bytes, None, true)
+}
+
+/// Reads a base64 private key written by [generate_keys]
+///
+/// * `path`: the key file
+fn read_key_pair(path: &Path) -> Result<Ed25519KeyPair> {
+    let key = std::fs::read_to_string(path).with_context(|| {
+                                               format!("Could not read {}", path.display())
+                                           })?;
+    let key = STANDARD.decode(key.trim())
+                      .with_context(|| format!("{} is not a valid key", path.display()))?;
+    Ed25519KeyPair::from_pkcs8(&key).map_err(|e| {
+                                        anyhow!("{} is not a valid key: {e}", path.display())
+                                    })
+}
+
+/// Generates a key pair, writing the private key to `<name>.key` and the
+/// public key to `<name>.pub`, both base64
+///
+/// * `name`: path to write them to, without an extension
+pub fn generate_keys(name: &str) -> Result<()> {
+    let private = PathBuf::from(format!("{name}.key"));
+    let public = PathBuf::from(format!("{name}.pub"));
+    ensure!(!private.exists(),
+            "{} already exists, not overwriting it",
+            private.display());
+
+    let document =
+        Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).map_err(|e| {
+                                                                anyhow!("Could not generate a \
+                                                                         key: {e}")
+                                                            })?;
+    let pair = Ed25519KeyPair::from_pkcs8(document.as_ref()).map_err(|e| {
+                                                                anyhow!("Could not generate a \
+                                                                         key: {e}")
+                                                            })?;
+
+    std::fs::write(&private, STANDARD.encode(document.as_ref())).with_context(|| {
+                                                                    format!("Could not write {}",
+                                                                            private.display())
+                                                                })?;
+    std::fs::write(&public, STANDARD.encode(pair.public_key().as_ref()))
+        .with_context(|| format!("Could not write {}", public.display()))?;
+
+    println!("Wrote the private key to {} and the public key to {}.",
+             private.display(),
+             public.display());
+    println!("Keep the private key secret. Add the public key to {TRUSTED_KEYS_ENV}, when \
+              building umm for your course or on your autograder.");
+    Ok(())
+}
+
+/// Signs a grading script, module, rubric or hidden test file, writing the
+/// signature to `<file>.sig`
+///
+/// * `key`: path to the private key
+/// * `file`: the file to sign
+pub fn sign_file(key: &str,
+                 file: &str)
+                 -> Result<()> {
+    let pair = read_key_pair(Path::new(key))?;
+    let bytes = std::fs::read(file).with_context(|| format!("Could not read {file}"))?;
+    let out = format!("{file}.{SIGNATURE_EXTENSION}");
+    std::fs::write(&out, STANDARD.encode(pair.sign(&bytes).as_ref())).with_context(|| {
+                                                                         format!("Could not write \
+                                                                                  {out}")
+                                                                     })?;
+
+    println!("Wrote {out}. Publish it next to {file}, or as its `signature` in a registry index.");
+    Ok(())
+}
+
+/// Hashes the project's Java files, in order of their paths, so that a
+/// submission can be matched to an attestation
+///
+/// * `project`: the project
+pub fn source_tree_sha256(project: &Project) -> Result<String> {
+    let mut files: Vec<PathBuf> = project.files().iter().map(|f| f.path().clone()).collect();
+    files.sort();
+
+    let mut hasher = Sha256::new();
+    for path in files {
+        let relative = path.strip_prefix(ROOT_DIR.as_path()).unwrap_or(&path);
+        let contents =
+            std::fs::read(&path).with_context(|| format!("Could not read {}", path.display()))?;
+        hasher.update(relative.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(&contents);
+        hasher.update([0]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// A requirement's result, as recorded in an [Attestation]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AttestedResult {
+    /// name of the requirement
+    pub requirement: String,
+    /// points earned
+    pub grade:       f64,
+    /// points possible
+    pub out_of:      f64,
+}
+
+/// What was graded, with what, and the scores it got
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Attestation {
+    /// version of umm that graded it
+    pub umm_version:   String,
+    /// when it was graded, in RFC 3339
+    pub graded_at:     String,
+    /// hex SHA-256 of the grading script or rubric
+    pub script_sha256: Option<String>,
+    /// hex SHA-256 of the project's Java files, see [source_tree_sha256]
+    pub source_sha256: String,
+    /// result of each requirement
+    pub results:       Vec<AttestedResult>,
+    /// total points earned
+    pub grade:         f64,
+    /// total points possible
+    pub out_of:        f64,
+}
+
+/// An [Attestation], signed so instructors can check it offline
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SignedAttestation {
+    /// the attestation, as the exact JSON that was signed
+    pub payload:    String,
+    /// base64 public key of the signer
+    pub public_key: String,
+    /// base64 signature of `payload`
+    pub signature:  String,
+}
+
+/// Signs an attestation of the results with the key in
+/// [ATTESTATION_KEY_ENV], or warns and returns `None` if it isn't set
+///
+/// * `results`: the results being reported
+pub fn attest(results: &[GradeResult]) -> Result<Option<SignedAttestation>> {
+    let Ok(key) = std::env::var(ATTESTATION_KEY_ENV) else {
+        tracing::warn!("{ATTESTATION_KEY_ENV} isn't set, so these results aren't attested");
+        return Ok(None);
+    };
+    let pair = read_key_pair(Path::new(&key))?;
+
+    let results: Vec<AttestedResult> =
+        results.iter()
+               .cloned()
+               .map(|mut r| AttestedResult { requirement: r.requirement(),
+                                             grade:       r.grade(),
+                                             out_of:      r.out_of(), })
+               .collect();
+    let attestation = Attestation { umm_version: env!("CARGO_PKG_VERSION").to_string(),
+                                    graded_at: chrono::Utc::now().to_rfc3339(),
+                                    script_sha256: GRADED_SCRIPT_SHA256.lock().unwrap().clone(),
+                                    source_sha256: source_tree_sha256(&Project::new()?)?,
+                                    grade: results.iter().map(|r| r.grade).sum(),
+                                    out_of: results.iter().map(|r| r.out_of).sum(),
+                                    results };
+
+    let payload = serde_json::to_string(&attestation)?;
+    let signature = pair.sign(payload.as_bytes());
+    Ok(Some(SignedAttestation { payload,
+                                public_key:
+                                    STANDARD.encode(pair.public_key().as_ref()),
+                                signature:
+                                    STANDARD.encode(signature.as_ref()) }))
+}
+
+/// Writes an attestation to [struct@UMM_DIR], for runs that don't write
+/// Gradescope results to embed it in
+///
+/// * `attestation`: the attestation
+pub fn write_attestation(attestation: &SignedAttestation) -> Result<PathBuf> {
+    std::fs::create_dir_all(UMM_DIR.as_path())?;
+    let path = UMM_DIR.join("attestation.json");
+    std::fs::write(&path, serde_json::to_string_pretty(attestation)?).with_context(|| {
+                                                                         format!("Could not write \
+                                                                                  {}",
+                                                                                 path.display())
+                                                                     })?;
+    Ok(path)
+}
+
+/// Checks an attestation offline, and prints what it attests to. Accepts
+/// an attestation file, or Gradescope results with one embedded.
+///
+/// * `path`: the attestation or results file
+/// * `key`: path to the public key it should be signed with. Defaults to the
+///   trusted keys.
+pub fn verify_attestation(path: &str,
+                          key: Option<&str>)
+                          -> Result<()> {
+    let contents = std::fs::read_to_string(path).with_context(|| format!("Could not read {path}"))?;
+    let mut value: serde_json::Value = serde_json::from_str(&contents)?;
+    if let Some(embedded) = value.pointer("/extra_data/attestation") {
+        value = embedded.clone();
+    }
+    let signed: SignedAttestation =
+        serde_json::from_value(value).with_context(|| format!("No attestation in {path}"))?;
+
+    let keys = match key {
+        Some(key) => {
+            let key =
+                std::fs::read_to_string(key).with_context(|| format!("Could not read {key}"))?;
+            vec![STANDARD.decode(key.trim()).context("Invalid public key")?]
+        }
+        None => trusted_keys()?,
+    };
+    ensure!(!keys.is_empty(),
+            "No key to check the attestation with, pass one with --key");
+    ensure!(signed_by_any(&keys, signed.payload.as_bytes(), &signed.signature)?,
+            "The attestation in {path} is not signed by the given key. It may have been tampered \
+             with.");
+
+    let attestation: Attestation = serde_json::from_str(&signed.payload)?;
+    println!("The attestation in {path} is valid.");
+    println!("Graded at {} by umm {}",
+             attestation.graded_at, attestation.umm_version);
+    println!("Script SHA-256: {}",
+             attestation.script_sha256.as_deref().unwrap_or("unknown"));
+    println!("Source SHA-256: {}", attestation.source_sha256);
+    for result in &attestation.results {
+        println!("  {}: {:.2}/{:.2}",
+                 result.requirement, result.grade, result.out_of);
+    }
+    println!("Total: {:.2}/{:.2}", attestation.grade, attestation.out_of);
+    Ok(())
+}
@@ -0,0 +1,779 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    fmt::Write as _,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+use walkdir::WalkDir;
+
+use crate::java::Parser;
+
+/// Number of consecutive normalized tokens hashed into one fingerprint
+pub const KGRAM_SIZE: usize = 12;
+
+/// Size of the winnowing window, in k-grams
+pub const WINNOW_WINDOW: usize = 8;
+
+/// Node kinds that are replaced by a placeholder token while normalizing
+const IDENTIFIER_KINDS: [&str; 2] = ["identifier", "type_identifier"];
+
+/// Literal node kinds that are replaced by a placeholder token while
+/// normalizing
+const LITERAL_KINDS: [&str; 14] = ["decimal_integer_literal",
+                                   "hex_integer_literal",
+                                   "octal_integer_literal",
+                                   "binary_integer_literal",
+                                   "decimal_floating_point_literal",
+                                   "hex_floating_point_literal",
+                                   "string_literal",
+                                   "text_block",
+                                   "character_literal",
+                                   "true",
+                                   "false",
+                                   "null_literal",
+                                   "string_fragment",
+                                   "escape_sequence"];
+
+/// Node kinds that are dropped entirely while normalizing
+const IGNORED_KINDS: [&str; 2] = ["line_comment", "block_comment"];
+
+/// Kind of a fingerprinted unit of code
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitKind {
+    /// A class, interface, enum or record declaration
+    Class,
+    /// A method or constructor declaration
+    Method,
+}
+
+/// A normalized token, remembering which source line it came from
+#[derive(Debug, Clone)]
+struct Token {
+    /// the normalized text of the token
+    text: String,
+    /// zero-indexed line the token starts on
+    line: usize,
+}
+
+/// A winnowed fingerprint
+#[derive(Debug, Clone, Copy)]
+struct Fingerprint {
+    /// hash of the k-gram
+    hash:       u64,
+    /// zero-indexed line the k-gram starts on
+    start_line: usize,
+    /// zero-indexed line the k-gram ends on
+    end_line:   usize,
+}
+
+/// A method or class, and the fingerprints of its normalized source
+#[derive(Debug, Clone)]
+struct Unit {
+    /// path of the file this unit belongs to, relative to the submission
+    file:         String,
+    /// whether this is a class or a method
+    kind:         UnitKind,
+    /// name of the class or method
+    name:         String,
+    /// winnowed fingerprints of the unit
+    fingerprints: Vec<Fingerprint>,
+}
+
+/// A single submission, i.e. one folder inside the submissions directory
+#[derive(Debug, Clone)]
+struct Submission {
+    /// name of the submission (the folder name)
+    name:    String,
+    /// source code of each java file, keyed by relative path
+    sources: BTreeMap<String, String>,
+    /// fingerprinted units discovered in the submission
+    units:   Vec<Unit>,
+}
+
+impl Submission {
+    /// Fingerprints the given java files.
+    ///
+    /// * `name`: name of the submission
+    /// * `sources`: source code of each file, keyed by relative path
+    fn new(name: String,
+           sources: BTreeMap<String, String>)
+           -> Result<Self> {
+        let mut units = vec![];
+        for (file, source) in &sources {
+            let parser = Parser::new(source.clone())?;
+            if let Some(tree) = parser.tree() {
+                collect_units(tree.root_node(), source, file, None, &mut units);
+            }
+        }
+
+        Ok(Self { name,
+                  sources,
+                  units })
+    }
+
+    /// All distinct fingerprint hashes of the class level units in this
+    /// submission. Methods are contained in classes, so this covers the
+    /// entire submission.
+    fn hashes(&self) -> HashSet<u64> {
+        self.units
+            .iter()
+            .filter(|u| u.kind == UnitKind::Class)
+            .flat_map(|u| u.fingerprints.iter().map(|f| f.hash))
+            .collect()
+    }
+}
+
+/// A region of code that matched between two submissions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MatchedRegion {
+    /// kind of the units that matched
+    pub kind:       UnitKind,
+    /// file in the first submission
+    pub file_a:     String,
+    /// class or method name in the first submission
+    pub unit_a:     String,
+    /// one-indexed, inclusive line range in the first submission
+    pub lines_a:    (usize, usize),
+    /// file in the second submission
+    pub file_b:     String,
+    /// class or method name in the second submission
+    pub unit_b:     String,
+    /// one-indexed, inclusive line range in the second submission
+    pub lines_b:    (usize, usize),
+    /// number of fingerprints shared by the two units
+    pub shared:     usize,
+    /// shared fingerprints as a fraction of the smaller unit's fingerprints
+    pub similarity: f64,
+}
+
+/// Similarity between a pair of submissions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PairReport {
+    /// name of the first submission
+    pub submission_a: String,
+    /// name of the second submission
+    pub submission_b: String,
+    /// shared fingerprints as a fraction of all fingerprints in either
+    /// submission (Jaccard index)
+    pub similarity:   f64,
+    /// shared fingerprints as a fraction of the first submission's
+    /// fingerprints
+    pub coverage_a:   f64,
+    /// shared fingerprints as a fraction of the second submission's
+    /// fingerprints
+    pub coverage_b:   f64,
+    /// method level matches, most similar first
+    pub methods:      Vec<MatchedRegion>,
+    /// class level matches, most similar first
+    pub classes:      Vec<MatchedRegion>,
+}
+
+/// The result of comparing all submissions in a directory
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimilarityReport {
+    /// names of all submissions compared
+    pub submissions:      Vec<String>,
+    /// whether starter code fingerprints were excluded
+    pub starter_excluded: bool,
+    /// pairs at or above the reporting threshold, most similar first
+    pub pairs:            Vec<PairReport>,
+    /// source code of each submission, used to render the HTML report
+    #[serde(skip)]
+    sources:              HashMap<String, BTreeMap<String, String>>,
+}
+
+/// Normalizes a subtree into a token stream, stripping identifiers, literals
+/// and comments.
+///
+/// * `node`: root of the subtree to normalize
+/// * `tokens`: where to push normalized tokens
+fn normalize(node: Node,
+             tokens: &mut Vec<Token>) {
+    let kind = node.kind();
+    let line = node.start_position().row;
+
+    if IGNORED_KINDS.contains(&kind) {
+        return;
+    }
+
+    if IDENTIFIER_KINDS.contains(&kind) {
+        tokens.push(Token { text: "$id".into(),
+                            line });
+        return;
+    }
+
+    if LITERAL_KINDS.contains(&kind) {
+        tokens.push(Token { text: "$lit".into(),
+                            line });
+        return;
+    }
+
+    if node.child_count() == 0 || node.is_named() {
+        tokens.push(Token { text: kind.to_string(),
+                            line });
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        normalize(child, tokens);
+    }
+}
+
+/// Hashes k-grams of the token stream and winnows them, keeping the
+/// rightmost minimal hash in every window.
+///
+/// * `tokens`: normalized tokens
+fn winnow(tokens: &[Token]) -> Vec<Fingerprint> {
+    if tokens.len() < KGRAM_SIZE {
+        return vec![];
+    }
+
+    let kgrams: Vec<Fingerprint> =
+        tokens.windows(KGRAM_SIZE)
+              .map(|w| {
+                  let mut hasher = DefaultHasher::new();
+                  for t in w {
+                      t.text.hash(&mut hasher);
+                  }
+                  Fingerprint { hash:       hasher.finish(),
+                                start_line: w.first().map(|t| t.line).unwrap_or_default(),
+                                end_line:   w.last().map(|t| t.line).unwrap_or_default(), }
+              })
+              .collect();
+
+    if kgrams.len() <= WINNOW_WINDOW {
+        return kgrams.into_iter()
+                     .min_by_key(|f| f.hash)
+                     .into_iter()
+                     .collect();
+    }
+
+    let mut selected: Vec<(usize, Fingerprint)> = vec![];
+    for (start, window) in kgrams.windows(WINNOW_WINDOW).enumerate() {
+        let (offset, min) = window.iter()
+                                  .enumerate()
+                                  .rev()
+                                  .min_by_key(|(_, f)| f.hash)
+                                  .expect("winnowing window is never empty");
+        let index = start + offset;
+        if selected.last().map(|(i, _)| *i) != Some(index) {
+            selected.push((index, *min));
+        }
+    }
+
+    selected.into_iter().map(|(_, f)| f).collect()
+}
+
+/// Returns the name of a declaration node, if it has one.
+///
+/// * `node`: a class or method declaration node
+/// * `source`: source code the node belongs to
+fn declaration_name(node: Node,
+                    source: &str)
+                    -> String {
+    node.child_by_field_name("name")
+        .and_then(|n| n.utf8_text(source.as_bytes()).ok())
+        .unwrap_or("[anonymous]")
+        .to_string()
+}
+
+/// Walks the tree and fingerprints every class and method declaration.
+///
+/// * `node`: the node to start walking from
+/// * `source`: source code of the file
+/// * `file`: relative path of the file
+/// * `class`: name of the enclosing class, if any
+/// * `units`: where to push fingerprinted units
+fn collect_units(node: Node,
+                 source: &str,
+                 file: &str,
+                 class: Option<&str>,
+                 units: &mut Vec<Unit>) {
+    let kind = match node.kind() {
+        "class_declaration"
+        | "interface_declaration"
+        | "enum_declaration"
+        | "record_declaration" => Some(UnitKind::Class),
+        "method_declaration" | "constructor_declaration" => Some(UnitKind::Method),
+        _ => None,
+    };
+
+    let mut class_name = class.map(String::from);
+
+    if let Some(kind) = kind {
+        let name = declaration_name(node, source);
+        let name = match (kind, class) {
+            (UnitKind::Method, Some(c)) => format!("{c}#{name}"),
+            _ => name,
+        };
+
+        let mut tokens = vec![];
+        normalize(node, &mut tokens);
+        units.push(Unit { file: file.to_string(),
+                          kind,
+                          name: name.clone(),
+                          fingerprints: winnow(&tokens) });
+
+        if kind == UnitKind::Class {
+            class_name = Some(name);
+        }
+    }
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_units(child, source, file, class_name.as_deref(), units);
+    }
+}
+
+/// Discovers and fingerprints all java files under `root`.
+///
+/// * `name`: name of the submission
+/// * `root`: root folder of the submission
+fn load_submission(name: String,
+                   root: &Path)
+                   -> Result<Submission> {
+    let mut sources = BTreeMap::new();
+
+    for entry in WalkDir::new(root).into_iter()
+                                   .filter_map(|e| e.ok())
+                                   .filter(|e| {
+                                       e.file_type().is_file()
+                                       && e.path().extension().unwrap_or_default() == "java"
+                                   })
+    {
+        let path = entry.path();
+        let relative = path.strip_prefix(root)
+                           .unwrap_or(path)
+                           .display()
+                           .to_string();
+        match std::fs::read_to_string(path) {
+            Ok(source) => {
+                sources.insert(relative, source);
+            }
+            Err(e) => tracing::warn!("Skipping {}: {}", path.display(), e),
+        }
+    }
+
+    Submission::new(name, sources)
+}
+
+/// Compares units of two submissions, returning matched regions of the given
+/// kind, most similar first.
+///
+/// * `a`: the first submission
+/// * `b`: the second submission
+/// * `kind`: kind of units to compare
+fn matched_regions(a: &Submission,
+                   b: &Submission,
+                   kind: UnitKind)
+                   -> Vec<MatchedRegion> {
+    let mut regions = vec![];
+
+    for unit_a in a.units.iter().filter(|u| u.kind == kind) {
+        let hashes_a: HashSet<u64> = unit_a.fingerprints.iter().map(|f| f.hash).collect();
+        if hashes_a.is_empty() {
+            continue;
+        }
+
+        for unit_b in b.units.iter().filter(|u| u.kind == kind) {
+            let hashes_b: HashSet<u64> = unit_b.fingerprints.iter().map(|f| f.hash).collect();
+            let shared: HashSet<u64> = hashes_a.intersection(&hashes_b).copied().collect();
+            if shared.is_empty() {
+                continue;
+            }
+
+            let lines = |unit: &Unit| {
+                unit.fingerprints
+                    .iter()
+                    .filter(|f| shared.contains(&f.hash))
+                    .fold((usize::MAX, 0), |(start, end), f| {
+                        (start.min(f.start_line + 1), end.max(f.end_line + 1))
+                    })
+            };
+
+            regions.push(MatchedRegion { kind,
+                                         file_a: unit_a.file.clone(),
+                                         unit_a: unit_a.name.clone(),
+                                         lines_a: lines(unit_a),
+                                         file_b: unit_b.file.clone(),
+                                         unit_b: unit_b.name.clone(),
+                                         lines_b: lines(unit_b),
+                                         shared: shared.len(),
+                                         similarity: shared.len() as f64
+                                                     / hashes_a.len().min(hashes_b.len())
+                                                       as f64 });
+        }
+    }
+
+    regions.sort_by(|l, r| {
+               r.similarity
+                .total_cmp(&l.similarity)
+                .then(r.shared.cmp(&l.shared))
+           });
+    regions
+}
+
+/// Compares every pair of submissions found in `submissions_dir`.
+///
+/// Each immediate sub-folder of `submissions_dir` is treated as one
+/// submission. Fingerprints that also occur in `starter_dir`, if supplied,
+/// are ignored so that instructor provided code does not count towards
+/// similarity.
+///
+/// * `submissions_dir`: folder containing one folder per submission
+/// * `starter_dir`: optional folder containing the starter code
+/// * `threshold`: minimum submission level similarity to report a pair
+pub fn compare_submissions(submissions_dir: &Path,
+                           starter_dir: Option<&Path>,
+                           threshold: f64)
+                           -> Result<SimilarityReport> {
+    let mut submissions = vec![];
+    let mut entries: Vec<PathBuf> = std::fs::read_dir(submissions_dir).with_context(|| {
+                                        format!("Could not read {}", submissions_dir.display())
+                                    })?
+                                    .filter_map(|e| e.ok())
+                                    .map(|e| e.path())
+                                    .filter(|p| p.is_dir())
+                                    .collect();
+    entries.sort();
+
+    for path in entries {
+        let name = path.file_name()
+                       .map(|n| n.to_string_lossy().to_string())
+                       .unwrap_or_else(|| path.display().to_string());
+        submissions.push(load_submission(name, &path)?);
+    }
+
+    let starter = match starter_dir {
+        Some(dir) => Some(load_submission(String::from("starter"), dir)?),
+        None => None,
+    };
+
+    Ok(compare(submissions, starter.as_ref(), threshold))
+}
+
+/// Compares every pair of submissions, leaving out fingerprints that also
+/// occur in the starter code.
+///
+/// * `submissions`: the submissions to compare
+/// * `starter`: the starter code, if any
+/// * `threshold`: minimum submission level similarity to report a pair
+fn compare(mut submissions: Vec<Submission>,
+           starter: Option<&Submission>,
+           threshold: f64)
+           -> SimilarityReport {
+    let starter_hashes = starter.map(Submission::hashes).unwrap_or_default();
+
+    for submission in submissions.iter_mut() {
+        for unit in submission.units.iter_mut() {
+            unit.fingerprints
+                .retain(|f| !starter_hashes.contains(&f.hash));
+        }
+    }
+
+    let mut pairs = vec![];
+    for (i, a) in submissions.iter().enumerate() {
+        let hashes_a = a.hashes();
+        for b in submissions.iter().skip(i + 1) {
+            let hashes_b = b.hashes();
+            let shared = hashes_a.intersection(&hashes_b).count() as f64;
+            let union = hashes_a.union(&hashes_b).count() as f64;
+
+            if union == 0.0 {
+                continue;
+            }
+
+            let similarity = shared / union;
+            if similarity < threshold {
+                continue;
+            }
+
+            pairs.push(PairReport { submission_a: a.name.clone(),
+                                    submission_b: b.name.clone(),
+                                    similarity,
+                                    coverage_a: shared / hashes_a.len().max(1) as f64,
+                                    coverage_b: shared / hashes_b.len().max(1) as f64,
+                                    methods: matched_regions(a, b, UnitKind::Method),
+                                    classes: matched_regions(a, b, UnitKind::Class) });
+        }
+    }
+
+    pairs.sort_by(|l, r| r.similarity.total_cmp(&l.similarity));
+
+    SimilarityReport { submissions: submissions.iter().map(|s| s.name.clone()).collect(),
+                       starter_excluded: starter.is_some(),
+                       pairs,
+                       sources: submissions.into_iter()
+                                           .map(|s| (s.name, s.sources))
+                                           .collect() }
+}
+
+/// Escapes text for use inside HTML
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl SimilarityReport {
+    /// Renders source code of `file` from `submission`, highlighting `lines`.
+    ///
+    /// * `submission`: name of the submission
+    /// * `file`: relative path of the file
+    /// * `lines`: one-indexed inclusive range to highlight
+    fn render_source(&self,
+                     submission: &str,
+                     file: &str,
+                     lines: (usize, usize))
+                     -> String {
+        let source = self.sources
+                         .get(submission)
+                         .and_then(|s| s.get(file))
+                         .map(String::as_str)
+                         .unwrap_or_default();
+
+        let mut out = String::from("<pre>");
+        for (n, line) in source.lines().enumerate() {
+            let n = n + 1;
+            let class = if n >= lines.0 && n <= lines.1 {
+                " class=\"hit\""
+            } else {
+                ""
+            };
+            let _ = writeln!(out,
+                             "<span{class}><i>{n:>4}</i> {}</span>",
+                             html_escape(line));
+        }
+        out.push_str("</pre>");
+        out
+    }
+
+    /// Renders a matched region side by side, highlighting the matching lines
+    ///
+    /// * `pair`: the pair of submissions the region is from
+    /// * `region`: the region
+    fn render_region(&self,
+                     pair: &PairReport,
+                     region: &MatchedRegion)
+                     -> String {
+        format!("<h4>{} &harr; {} ({:.1}%, {} shared fingerprints)</h4><div \
+                 class=\"sides\"><div><b>{}</b>{}</div><div><b>{}</b>{}</div></div>",
+                html_escape(&region.unit_a),
+                html_escape(&region.unit_b),
+                region.similarity * 100.0,
+                region.shared,
+                html_escape(&region.file_a),
+                self.render_source(&pair.submission_a, &region.file_a, region.lines_a),
+                html_escape(&region.file_b),
+                self.render_source(&pair.submission_b, &region.file_b, region.lines_b))
+    }
+
+    /// Renders the report as a self-contained HTML page.
+    pub fn to_html(&self) -> String {
+        let mut html = String::from("<!DOCTYPE html>\n<html><head><meta \
+                                     charset=\"utf-8\"><title>Similarity \
+                                     Report</title><style>body{font-family:sans-serif;margin:\
+                                     2em}table{border-collapse:collapse}td,th{border:1px solid \
+                                     #ccc;padding:4px \
+                                     8px}.sides{display:flex;gap:1em}.sides>div{flex:1;overflow:\
+                                     auto}pre{font-size:12px}pre \
+                                     i{color:#999}.hit{background:#ffe08a;display:block}</\
+                                     style></head><body>\n");
+
+        let _ = writeln!(html,
+                         "<h1>Similarity Report</h1><p>{} submissions compared. Starter code \
+                          {}excluded.</p>",
+                         self.submissions.len(),
+                         if self.starter_excluded { "" } else { "not " });
+
+        html.push_str("<table><tr><th>Submission A</th><th>Submission \
+                       B</th><th>Similarity</th><th>Coverage A</th><th>Coverage B</th></tr>\n");
+        for (i, pair) in self.pairs.iter().enumerate() {
+            let _ = writeln!(html,
+                             "<tr><td><a \
+                              href=\"#pair-{i}\">{}</a></td><td>{}</td><td>{:.1}%</td><td>{:.1}%\
+                              </td><td>{:.1}%</td></tr>",
+                             html_escape(&pair.submission_a),
+                             html_escape(&pair.submission_b),
+                             pair.similarity * 100.0,
+                             pair.coverage_a * 100.0,
+                             pair.coverage_b * 100.0);
+        }
+        html.push_str("</table>\n");
+
+        for (i, pair) in self.pairs.iter().enumerate() {
+            let _ = writeln!(html,
+                             "<h2 id=\"pair-{i}\">{} &harr; {} ({:.1}%)</h2>",
+                             html_escape(&pair.submission_a),
+                             html_escape(&pair.submission_b),
+                             pair.similarity * 100.0);
+
+            for (title, regions) in [("Classes", &pair.classes), ("Methods", &pair.methods)] {
+                if regions.is_empty() {
+                    continue;
+                }
+                let _ = writeln!(html, "<h3>{title}</h3>");
+                for region in regions.iter().take(10) {
+                    let _ = writeln!(html, "{}", self.render_region(pair, region));
+                }
+            }
+        }
+
+        html.push_str("</body></html>\n");
+        html
+    }
+
+    /// Writes the report as `<output>.json` and `<output>.html`.
+    ///
+    /// * `output`: path to write the report to, without an extension
+    pub fn write(&self,
+                 output: &Path)
+                 -> Result<()> {
+        let json = output.with_extension("json");
+        let html = output.with_extension("html");
+
+        std::fs::write(&json, serde_json::to_string_pretty(self)?).with_context(|| {
+                                                                      format!("Could not write {}",
+                                                                              json.display())
+                                                                  })?;
+        std::fs::write(&html, self.to_html()).with_context(|| {
+                                                 format!("Could not write {}", html.display())
+                                             })?;
+
+        eprintln!("Similarity report written to {} and {}",
+                  json.display(),
+                  html.display());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Code every submission starts with
+    const STARTER: &str = r#"
+public class Shapes {
+    public static double circleArea(double radius) {
+        double area = Math.PI * radius * radius;
+        return area;
+    }
+
+    public static int sumTo(int n) {
+        int total = 0;
+        for (int i = 1; i <= n; i++) {
+            total += i;
+        }
+        return total;
+    }
+}
+"#;
+
+    /// A method one student added to the starter code
+    const REVERSE: &str = r#"
+    public static String reverse(String text) {
+        StringBuilder reversed = new StringBuilder();
+        int index = text.length() - 1;
+        while (index >= 0) {
+            reversed.append(text.charAt(index));
+            index--;
+        }
+        return reversed.toString();
+    }
+"#;
+
+    /// A method another student added to the starter code
+    const PARSE: &str = r#"
+    public static int parseOrDefault(String value, int fallback) {
+        try {
+            return Integer.parseInt(value.trim());
+        } catch (NumberFormatException e) {
+            System.err.println("Not a number: " + value);
+            return fallback;
+        }
+    }
+"#;
+
+    /// A submission with a single `Shapes.java`
+    fn submission(name: &str,
+                  source: &str)
+                  -> Submission {
+        let sources = BTreeMap::from([(String::from("Shapes.java"), source.to_string())]);
+        Submission::new(name.to_string(), sources).unwrap()
+    }
+
+    /// The starter code with `method` added to the class
+    fn with_method(method: &str) -> String {
+        let end = STARTER.rfind('}').unwrap();
+        format!("{}{}{}", &STARTER[..end], method, &STARTER[end..])
+    }
+
+    /// Similarity of the only pair in a report
+    fn similarity(report: &SimilarityReport) -> f64 {
+        assert_eq!(report.pairs.len(), 1);
+        report.pairs[0].similarity
+    }
+
+    /// Identical submissions are fully similar
+    #[test]
+    fn identical() {
+        let source = with_method(REVERSE);
+        let report = compare(vec![submission("a", &source), submission("b", &source)],
+                             None,
+                             0.0);
+        assert!((similarity(&report) - 1.0).abs() < 1e-9);
+    }
+
+    /// Renaming identifiers and changing literals doesn't hide a copy
+    #[test]
+    fn renamed_identifiers() {
+        let renamed = with_method(REVERSE).replace("reversed", "out")
+                                          .replace("index", "j")
+                                          .replace("text", "s")
+                                          .replace("- 1", "- 2");
+        let report = compare(vec![submission("a", &with_method(REVERSE)),
+                                  submission("b", &renamed)],
+                             None,
+                             0.0);
+        assert!((similarity(&report) - 1.0).abs() < 1e-9);
+    }
+
+    /// Comments don't count towards similarity
+    #[test]
+    fn comments_ignored() {
+        let commented = with_method(REVERSE).replace("return reversed", "// done\nreturn reversed");
+        let report = compare(vec![submission("a", &with_method(REVERSE)),
+                                  submission("b", &commented)],
+                             None,
+                             0.0);
+        assert!((similarity(&report) - 1.0).abs() < 1e-9);
+    }
+
+    /// Code that only overlaps in the starter code is not reported once the
+    /// starter code is excluded
+    #[test]
+    fn starter_excluded() {
+        let submissions = || {
+            vec![submission("a", &with_method(REVERSE)),
+                 submission("b", &with_method(PARSE)),]
+        };
+        let without = compare(submissions(), None, 0.0);
+        assert!(similarity(&without) > 0.3);
+
+        let starter = submission("starter", STARTER);
+        let with = compare(submissions(), Some(&starter), 0.0);
+        assert!(with.starter_excluded);
+        assert!(similarity(&with) < 0.1);
+        assert!(with.pairs[0].methods.is_empty());
+        assert!(compare(submissions(), Some(&starter), 0.5).pairs.is_empty());
+    }
+}
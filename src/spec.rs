@@ -0,0 +1,742 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{fmt::Display, path::Path};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use tabled::Tabled;
+
+use crate::{
+    compat::{API_VERSION, API_VERSION_DIRECTIVE},
+    constants::{
+        CLASS_CONSTRUCTOR_QUERY, CLASS_DECLARATION_QUERY, CLASS_FIELD_SIGNATURES_QUERY,
+        CLASS_METHOD_QUERY, INTERFACE_DECLARATION_QUERY, INTERFACE_METHOD_SIGNATURES_QUERY,
+    },
+    java::{File, FileType, Project},
+};
+
+/// Java modifiers that are part of a member's signature
+const MODIFIERS: [&str; 13] = ["public",
+                               "protected",
+                               "private",
+                               "static",
+                               "final",
+                               "abstract",
+                               "default",
+                               "synchronized",
+                               "native",
+                               "strictfp",
+                               "transient",
+                               "volatile",
+                               "sealed"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// The expected public surface of a set of classes and interfaces
+pub struct ApiSpec {
+    /// expected classes and interfaces
+    pub classes: Vec<ClassSpec>,
+    /// JUnit test classes and their test methods
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests:   Vec<TestClassSpec>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// A JUnit test class and its test methods
+pub struct TestClassSpec {
+    /// name of the test class, qualified with its package
+    pub name:    String,
+    /// test methods, as `package.Class#method`
+    pub methods: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// The expected surface of a single class or interface
+pub struct ClassSpec {
+    /// name of the class, either simple or qualified with its package
+    pub name:            String,
+    /// whether this is an interface rather than a class
+    #[serde(default)]
+    pub interface:       bool,
+    /// type parameters, e.g. `<T extends Comparable<T>>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_parameters: Option<String>,
+    /// superclass, if any
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extends:         Option<String>,
+    /// interfaces implemented by a class, or extended by an interface
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub implements:      Vec<String>,
+    /// fields
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields:          Vec<FieldSpec>,
+    /// constructors
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub constructors:    Vec<ConstructorSpec>,
+    /// methods
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub methods:         Vec<MethodSpec>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// The expected signature of a field
+pub struct FieldSpec {
+    /// name of the field
+    pub name:      String,
+    /// type of the field
+    #[serde(rename = "type")]
+    pub type_name: String,
+    /// modifiers the field must have
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// The expected signature of a constructor
+pub struct ConstructorSpec {
+    /// parameter types, in order
+    #[serde(default)]
+    pub parameters: Vec<String>,
+    /// exceptions the constructor declares
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub throws:     Vec<String>,
+    /// modifiers the constructor must have
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers:  Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+/// The expected signature of a method
+pub struct MethodSpec {
+    /// name of the method
+    pub name:            String,
+    /// type parameters, e.g. `<T>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub type_parameters: Option<String>,
+    /// return type
+    pub return_type:     String,
+    /// parameter types, in order
+    #[serde(default)]
+    pub parameters:      Vec<String>,
+    /// exceptions the method declares
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub throws:          Vec<String>,
+    /// modifiers the method must have
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub modifiers:       Vec<String>,
+}
+
+impl Display for FieldSpec {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{modifier} ")?;
+        }
+        write!(f, "{} {}", self.type_name, self.name)
+    }
+}
+
+impl ConstructorSpec {
+    /// Returns the signature of this constructor as Java source
+    ///
+    /// * `class`: simple name of the class the constructor belongs to
+    pub fn signature(&self,
+                     class: &str)
+                     -> String {
+        let mut signature = format!("{}({})", class, self.parameters.join(", "));
+        if !self.modifiers.is_empty() {
+            signature = format!("{} {}", self.modifiers.join(" "), signature);
+        }
+        if !self.throws.is_empty() {
+            signature = format!("{} throws {}", signature, self.throws.join(", "));
+        }
+        signature
+    }
+}
+
+impl Display for MethodSpec {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        for modifier in &self.modifiers {
+            write!(f, "{modifier} ")?;
+        }
+        if let Some(type_parameters) = &self.type_parameters {
+            write!(f, "{type_parameters} ")?;
+        }
+        write!(f,
+               "{} {}({})",
+               self.return_type,
+               self.name,
+               self.parameters.join(", "))?;
+        if !self.throws.is_empty() {
+            write!(f, " throws {}", self.throws.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+impl ApiSpec {
+    /// Reads a spec from a JSON file
+    ///
+    /// * `path`: path to the JSON file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path).with_context(|| {
+                                                        format!("Could not read spec file: {}",
+                                                                path.display())
+                                                    })?;
+        serde_json::from_str(&contents).with_context(|| {
+                                           format!("Could not parse spec file: {}", path.display())
+                                       })
+    }
+
+    /// Extracts a spec from a reference solution. Private members are left
+    /// out, since students are free to name and structure those as they like.
+    ///
+    /// * `project`: the reference project
+    pub fn from_project(project: &Project) -> Result<Self> {
+        let mut spec = ApiSpec::default();
+        let is_private = |modifiers: &[String]| modifiers.iter().any(|m| m == "private");
+
+        for file in project.files() {
+            if *file.kind() == FileType::Test {
+                spec.tests.push(TestClassSpec { name:    file.proper_name(),
+                                                methods: file.test_methods(), });
+                continue;
+            }
+
+            let mut class = ClassSpec::from_file(file)?;
+            class.fields.retain(|f| !is_private(&f.modifiers));
+            class.constructors.retain(|c| !is_private(&c.modifiers));
+            class.methods.retain(|m| !is_private(&m.modifiers));
+            spec.classes.push(class);
+        }
+
+        spec.classes.sort_by(|a, b| a.name.cmp(&b.name));
+        spec.tests.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(spec)
+    }
+
+    /// Returns a starter grading script that checks the API against this spec,
+    /// checks documentation of every class, and runs every test method.
+    ///
+    /// * `spec_file`: path to the spec, relative to the student's project root
+    pub fn grading_script(&self,
+                          spec_file: &str)
+                          -> String {
+        let classes = self.classes
+                          .iter()
+                          .map(|c| format!("{:?}", c.name))
+                          .collect::<Vec<String>>();
+        let test_files = self.tests
+                             .iter()
+                             .map(|t| format!("{:?}", t.name))
+                             .collect::<Vec<String>>();
+        let expected_tests = self.tests
+                                 .iter()
+                                 .flat_map(|t| t.methods.iter())
+                                 .map(|m| format!("        {m:?},"))
+                                 .collect::<Vec<String>>();
+
+        let mut reqs = vec![String::from("req_1"), String::from("req_2")];
+        let mut script = format!(
+                                 r#"{API_VERSION_DIRECTIVE} {API_VERSION}
+let project = new_java_project();
+
+let req_1 = new_api_grader()
+    .project(project)
+    .spec_file({spec_file:?})
+    .out_of(10.0)
+    .req_name("1")
+    .run();
+
+let req_2 = new_docs_grader()
+    .project(project)
+    .files([{classes}])
+    .out_of(10.0)
+    .req_name("2")
+    .penalty(3.0)
+    .run();
+"#,
+                                 classes = classes.join(", ")
+        );
+
+        if !self.tests.is_empty() {
+            reqs.push(String::from("req_3"));
+            script.push_str(&format!(
+                r#"
+let req_3 = new_by_unit_test_grader()
+    .project(project)
+    .test_files([{test_files}])
+    .expected_tests([
+{expected_tests}
+    ])
+    .out_of(20.0)
+    .req_name("3")
+    .run();
+"#,
+                test_files = test_files.join(", "),
+                expected_tests = expected_tests.join("\n")
+            ));
+        }
+
+        script.push_str(&format!(
+            r#"
+let reqs = [{reqs}];
+
+show_results(reqs, #{{
+    source_files: [{classes}],
+    test_files: [{test_files}],
+    pass_threshold: 0.7,
+}});
+"#,
+            reqs = reqs.join(", "),
+            classes = classes.join(", "),
+            test_files = test_files.join(", ")
+        ));
+
+        script
+    }
+
+    /// Writes the spec as `<output>.json`, and a starter grading script that
+    /// uses it as `<output>.rhai`.
+    ///
+    /// * `output`: path to write to, without an extension
+    pub fn write(&self,
+                 output: &Path)
+                 -> Result<()> {
+        let json = output.with_extension("json");
+        let rhai = output.with_extension("rhai");
+        let spec_file = json.file_name()
+                            .map(|f| f.to_string_lossy().to_string())
+                            .unwrap_or_default();
+
+        std::fs::write(&json, serde_json::to_string_pretty(self)?).with_context(|| {
+                                                                      format!("Could not write {}",
+                                                                              json.display())
+                                                                  })?;
+        std::fs::write(&rhai, self.grading_script(&spec_file)).with_context(|| {
+                                                                  format!("Could not write {}",
+                                                                          rhai.display())
+                                                              })?;
+
+        eprintln!("API spec written to {} and grading script to {}",
+                  json.display(),
+                  rhai.display());
+        Ok(())
+    }
+}
+
+/// Normalizes whitespace in a type or signature fragment, so that
+/// `Map<String , Integer>` and `Map<String, Integer>` compare equal
+pub fn normalize_type(text: &str) -> String {
+    let collapsed = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let mut result = String::new();
+    let chars: Vec<char> = collapsed.chars().collect();
+
+    for (i, c) in chars.iter().enumerate() {
+        if *c == ' ' {
+            let prev = i.checked_sub(1).map(|i| chars[i]);
+            let next = chars.get(i + 1);
+            let tight = |c: char| matches!(c, '<' | '>' | ',' | '[' | ']' | '(' | ')' | '.');
+            if prev.is_some_and(tight) || next.is_some_and(|c| tight(*c)) {
+                continue;
+            }
+        }
+        result.push(*c);
+    }
+
+    result.replace(',', ", ")
+}
+
+/// Returns the erasure of a type, i.e. the type without type arguments
+fn erasure(text: &str) -> String {
+    let text = normalize_type(text);
+    match text.find('<') {
+        Some(i) => text[..i].to_string(),
+        None => text,
+    }
+}
+
+/// Splits `text` on commas that are not nested inside brackets
+fn split_top_level(text: &str) -> Vec<String> {
+    let mut parts = vec![];
+    let mut depth = 0i32;
+    let mut current = String::new();
+
+    for c in text.chars() {
+        match c {
+            '<' | '(' | '[' | '{' => depth += 1,
+            '>' | ')' | ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                parts.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+
+    if !current.trim().is_empty() {
+        parts.push(current.trim().to_string());
+    }
+    parts
+}
+
+/// Returns the Java modifiers present in a `modifiers` capture, ignoring
+/// annotations
+fn modifiers(text: Option<&String>) -> Vec<String> {
+    text.map(|t| {
+            t.split_whitespace()
+             .filter(|m| MODIFIERS.contains(m))
+             .map(String::from)
+             .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the list of types after a keyword such as `throws`, `implements`,
+/// or `extends`
+fn type_list(text: Option<&String>,
+             keyword: &str)
+             -> Vec<String> {
+    text.map(|t| {
+            split_top_level(t.trim().trim_start_matches(keyword)).iter()
+                                                                 .map(|t| normalize_type(t))
+                                                                 .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the parameter types from a `formal_parameters` capture, e.g.
+/// `(int a, List<String> b)` becomes `["int", "List<String>"]`
+fn parameter_types(text: Option<&String>) -> Vec<String> {
+    let text = match text {
+        Some(t) => t.trim().trim_start_matches('(').trim_end_matches(')'),
+        None => return vec![],
+    };
+
+    split_top_level(text).iter()
+                         .map(|param| {
+                             let param = param.split_whitespace()
+                                              .filter(|w| !w.starts_with('@') && *w != "final")
+                                              .collect::<Vec<&str>>()
+                                              .join(" ");
+                             let mut dims = String::new();
+                             let mut param = param.as_str();
+                             while let Some(p) = param.strip_suffix("[]") {
+                                 dims.push_str("[]");
+                                 param = p.trim_end();
+                             }
+                             let name_start =
+                                 param.rfind(|c: char| {
+                                          !(c.is_alphanumeric() || c == '_' || c == '$')
+                                      })
+                                      .map(|i| i + 1)
+                                      .unwrap_or_default();
+                             normalize_type(&format!("{}{}", &param[..name_start], dims))
+                         })
+                         .collect()
+}
+
+impl ClassSpec {
+    /// Extracts the actual surface of a class or interface from a file
+    ///
+    /// * `file`: the file to extract from
+    pub fn from_file(file: &File) -> Result<Self> {
+        let interface = *file.kind() == FileType::Interface;
+        let mut spec = ClassSpec { name: file.proper_name(),
+                                   interface,
+                                   ..Default::default() };
+
+        let optional = |t: Option<&String>| t.map(|t| normalize_type(t)).filter(|t| !t.is_empty());
+
+        let methods = if interface {
+            let declaration = file.query(INTERFACE_DECLARATION_QUERY)?;
+            if let Some(d) = declaration.first() {
+                spec.type_parameters = optional(d.get("parameters"));
+                spec.implements = type_list(d.get("extends"), "extends");
+            }
+            file.query(INTERFACE_METHOD_SIGNATURES_QUERY)?
+        } else {
+            let declaration = file.query(CLASS_DECLARATION_QUERY)?;
+            if let Some(d) = declaration.first() {
+                spec.type_parameters = optional(d.get("typeParameters"));
+                spec.extends = d.get("superclass")
+                                .map(|s| normalize_type(s.trim().trim_start_matches("extends")));
+                spec.implements = type_list(d.get("interfaces"), "implements");
+            }
+
+            spec.fields = file.query(CLASS_FIELD_SIGNATURES_QUERY)?
+                              .iter()
+                              .map(|f| FieldSpec { name:      f.get("identifier")
+                                                               .cloned()
+                                                               .unwrap_or_default(),
+                                                   type_name: normalize_type(f.get("type")
+                                                                              .map(String::as_str)
+                                                                              .unwrap_or_default()),
+                                                   modifiers: modifiers(f.get("modifier")), })
+                              .collect();
+
+            spec.constructors =
+                file.query(CLASS_CONSTRUCTOR_QUERY)?
+                    .iter()
+                    .filter(|c| c.contains_key("identifier"))
+                    .map(|c| ConstructorSpec { parameters: parameter_types(c.get("parameters")),
+                                               throws:     type_list(c.get("throws"), "throws"),
+                                               modifiers:  modifiers(c.get("modifier")), })
+                    .collect();
+
+            file.query(CLASS_METHOD_QUERY)?
+        };
+
+        spec.methods =
+            methods.iter()
+                   .map(|m| MethodSpec { name:            m.get("identifier")
+                                                           .cloned()
+                                                           .unwrap_or_default(),
+                                         type_parameters: optional(m.get("typeParameters")),
+                                         return_type:     normalize_type(m.get("returnType")
+                                                                          .map(String::as_str)
+                                                                          .unwrap_or_default()),
+                                         parameters:      parameter_types(m.get("parameters")),
+                                         throws:          type_list(m.get("throws"), "throws"),
+                                         modifiers:       modifiers(m.get("modifier")), })
+                   .collect();
+
+        Ok(spec)
+    }
+}
+
+#[derive(Tabled, Serialize, Deserialize, Clone, Debug)]
+/// The result of checking one expected member against the actual code
+pub struct ApiCheck {
+    /// * `class`: the class or interface being checked
+    #[tabled(rename = "Class")]
+    pub class:    String,
+    /// * `member`: what is being checked
+    #[tabled(rename = "Member")]
+    pub member:   String,
+    /// * `expected`: the expected signature
+    #[tabled(rename = "Expected")]
+    pub expected: String,
+    /// * `found`: the closest actual signature, if any
+    #[tabled(rename = "Found")]
+    pub found:    String,
+    /// * `passed`: whether the actual code matches the expectation
+    #[tabled(skip)]
+    pub passed:   bool,
+}
+
+/// Returns true if every modifier in `expected` is in `found`
+fn has_modifiers(expected: &[String],
+                 found: &[String])
+                 -> bool {
+    expected.iter().all(|m| found.contains(m))
+}
+
+/// Returns true if two lists of types are the same, ignoring order
+fn same_types(expected: &[String],
+              found: &[String])
+              -> bool {
+    expected.len() == found.len() && expected.iter().all(|e| found.contains(&normalize_type(e)))
+}
+
+/// Returns true if two optional types match. A missing expectation always
+/// matches.
+fn same_optional(expected: &Option<String>,
+                 found: &Option<String>)
+                 -> bool {
+    match (expected, found) {
+        (None, _) => true,
+        (Some(e), Some(f)) => normalize_type(e) == *f,
+        (Some(_), None) => false,
+    }
+}
+
+/// Returns true if a type matches its expectation. An expectation without
+/// type arguments matches any parameterization of the same type.
+fn same_type(expected: &str,
+             found: &str)
+             -> bool {
+    let normalized = normalize_type(expected);
+    normalized == found || (!normalized.contains('<') && normalized == erasure(found))
+}
+
+/// Compares the expected surface of a class against what was found, returning
+/// one check per expectation. A missing class fails its declaration and
+/// every expectation of it.
+///
+/// * `expected`: the expected surface
+/// * `found`: the actual surface, if the class was found at all
+pub fn check_class(expected: &ClassSpec,
+                   found: Option<&ClassSpec>)
+                   -> Vec<ApiCheck> {
+    let class = expected.name.clone();
+    let simple_name = class.rsplit('.').next().unwrap_or_default().to_string();
+    let mut checks = vec![];
+    let mut check = |member: String, expected: String, found: String, passed: bool| {
+        checks.push(ApiCheck { class: class.clone(),
+                               member,
+                               expected,
+                               found,
+                               passed });
+    };
+    let not_found = || String::from("[NOT FOUND]");
+
+    // When the class is missing, every expectation of it fails against an
+    // empty class of the expected kind, so each counts against the grade
+    let missing;
+    let found = match found {
+        Some(f) => f,
+        None => {
+            let kind = if expected.interface {
+                "interface"
+            } else {
+                "class"
+            };
+            check(String::from("declaration"),
+                  format!("{kind} {class}"),
+                  not_found(),
+                  false);
+            missing = ClassSpec { name: class.clone(),
+                                  interface: expected.interface,
+                                  ..Default::default() };
+            &missing
+        }
+    };
+
+    if expected.interface != found.interface {
+        let kind = |interface: bool| {
+            if interface {
+                String::from("interface")
+            } else {
+                String::from("class")
+            }
+        };
+        check(String::from("kind"),
+              kind(expected.interface),
+              kind(found.interface),
+              false);
+    }
+
+    if expected.type_parameters.is_some() {
+        check(String::from("type parameters"),
+              expected.type_parameters.clone().unwrap_or_default(),
+              found.type_parameters.clone().unwrap_or_else(not_found),
+              same_optional(&expected.type_parameters, &found.type_parameters));
+    }
+
+    if let Some(extends) = &expected.extends {
+        let passed = found.extends
+                          .as_ref()
+                          .is_some_and(|f| same_type(extends, f));
+        check(String::from("extends"),
+              extends.clone(),
+              found.extends.clone().unwrap_or_else(not_found),
+              passed);
+    }
+
+    for interface in &expected.implements {
+        let matched = found.implements.iter().find(|f| same_type(interface, f));
+        check(String::from("implements"),
+              interface.clone(),
+              matched.cloned().unwrap_or_else(|| {
+                                  if found.implements.is_empty() {
+                                      not_found()
+                                  } else {
+                                      found.implements.join(", ")
+                                  }
+                              }),
+              matched.is_some());
+    }
+
+    for field in &expected.fields {
+        match found.fields.iter().find(|f| f.name == field.name) {
+            Some(f) => check(format!("field {}", field.name),
+                             field.to_string(),
+                             f.to_string(),
+                             same_type(&field.type_name, &f.type_name)
+                             && has_modifiers(&field.modifiers, &f.modifiers)),
+            None => check(format!("field {}", field.name),
+                          field.to_string(),
+                          not_found(),
+                          false),
+        }
+    }
+
+    for constructor in &expected.constructors {
+        let matched = found.constructors.iter().find(|c| {
+                                                   c.parameters.len()
+                                                   == constructor.parameters.len()
+                                                   && constructor.parameters
+                                                                 .iter()
+                                                                 .zip(c.parameters.iter())
+                                                                 .all(|(e, f)| same_type(e, f))
+                                               });
+        let member = format!("constructor {}({})",
+                             simple_name,
+                             constructor.parameters.join(", "));
+        match matched {
+            Some(c) => check(member,
+                             constructor.signature(&simple_name),
+                             c.signature(&simple_name),
+                             same_types(&constructor.throws, &c.throws)
+                             && has_modifiers(&constructor.modifiers, &c.modifiers)),
+            None => {
+                let candidates = found.constructors
+                                      .iter()
+                                      .map(|c| c.signature(&simple_name))
+                                      .collect::<Vec<String>>();
+                let candidates = if candidates.is_empty() {
+                    not_found()
+                } else {
+                    candidates.join("\n")
+                };
+                check(member,
+                      constructor.signature(&simple_name),
+                      candidates,
+                      false)
+            }
+        }
+    }
+
+    for method in &expected.methods {
+        let candidates: Vec<&MethodSpec> = found.methods
+                                                .iter()
+                                                .filter(|m| m.name == method.name)
+                                                .collect();
+        let matched = candidates.iter().find(|m| {
+                                           m.parameters.len() == method.parameters.len()
+                                           && method.parameters
+                                                    .iter()
+                                                    .zip(m.parameters.iter())
+                                                    .all(|(e, f)| same_type(e, f))
+                                       });
+        let member = format!("method {}({})", method.name, method.parameters.join(", "));
+        match matched {
+            Some(m) => check(member,
+                             method.to_string(),
+                             m.to_string(),
+                             same_type(&method.return_type, &m.return_type)
+                             && same_optional(&method.type_parameters, &m.type_parameters)
+                             && same_types(&method.throws, &m.throws)
+                             && has_modifiers(&method.modifiers, &m.modifiers)),
+            None => {
+                let found = if candidates.is_empty() {
+                    not_found()
+                } else {
+                    candidates.iter()
+                              .map(|m| m.to_string())
+                              .collect::<Vec<String>>()
+                              .join("\n")
+                };
+                check(member, method.to_string(), found, false)
+            }
+        }
+    }
+
+    checks
+}
@@ -0,0 +1,437 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{collections::HashMap, fmt::Display, io::Read, path::Path};
+
+use anyhow::{Context, Result};
+use rhai::EvalAltResult;
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node;
+use umm_derive::generate_rhai_variant;
+use walkdir::WalkDir;
+
+use crate::{
+    constants::{PACKAGE_QUERY, ROOT_DIR, STARTER_CODE},
+    java::{File, Parser},
+};
+
+/// Node kinds that declare a type
+const TYPE_DECLARATION_KINDS: [&str; 4] = ["class_declaration",
+                                           "interface_declaration",
+                                           "enum_declaration",
+                                           "record_declaration"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// How a file or method compares to the starter code
+pub enum Change {
+    /// Identical to the starter code, ignoring whitespace
+    Unchanged,
+    /// Present in the starter code, but edited by the student
+    Modified,
+    /// Not present in the starter code
+    Added,
+}
+
+impl Display for Change {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        match self {
+            Change::Unchanged => write!(f, "unchanged"),
+            Change::Modified => write!(f, "modified"),
+            Change::Added => write!(f, "added"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A method or constructor in a student's file, and how it compares to the
+/// starter code
+pub struct MethodChange {
+    /// name of the method, as `Class#method(ParamType, ...)`
+    pub name:       String,
+    /// how the method compares to the starter code
+    pub change:     Change,
+    /// line the method starts on, starting at 1
+    pub start_line: usize,
+    /// line the method ends on, starting at 1
+    pub end_line:   usize,
+    /// byte offset the method starts at
+    pub start_byte: usize,
+    /// byte offset the method ends at
+    pub end_byte:   usize,
+    /// source code of the method
+    pub code:       String,
+}
+
+/// A method or constructor declaration found while walking a parse tree
+struct Method<'a> {
+    /// name of the method, as `Class#method(ParamType, ...)`
+    name: String,
+    /// the declaration node
+    node: Node<'a>,
+}
+
+#[derive(Debug, Clone, Default)]
+/// A single file from the starter code
+struct StarterFile {
+    /// normalized source code of the file
+    code:    String,
+    /// normalized source code of each method, keyed by name
+    methods: HashMap<String, String>,
+}
+
+#[derive(Debug, Clone, Default)]
+/// The starter code handed out to students, used to tell apart what a student
+/// wrote from the scaffolding they were given
+pub struct Starter {
+    /// files in the starter code, keyed by their proper name
+    files: HashMap<String, StarterFile>,
+}
+
+/// Returns `node`'s source code with all whitespace between tokens collapsed
+/// into a single space.
+///
+/// * `node`: the node to normalize
+/// * `code`: source code the node belongs to
+fn normalize(node: Node,
+             code: &str)
+             -> String {
+    /// Recursive helper that collects leaf tokens
+    fn leaves<'a>(node: Node,
+                  code: &'a str,
+                  tokens: &mut Vec<&'a str>) {
+        if node.child_count() == 0 {
+            tokens.push(node.utf8_text(code.as_bytes()).unwrap_or_default());
+            return;
+        }
+
+        let mut cursor = node.walk();
+        for child in node.children(&mut cursor) {
+            leaves(child, code, tokens);
+        }
+    }
+
+    let mut tokens = vec![];
+    leaves(node, code, &mut tokens);
+    tokens.join(" ")
+}
+
+/// Returns the parameter types of a method declaration, comma separated.
+///
+/// * `node`: a `method_declaration` or `constructor_declaration` node
+/// * `code`: source code the node belongs to
+fn parameter_types(node: Node,
+                   code: &str)
+                   -> String {
+    let parameters = match node.child_by_field_name("parameters") {
+        Some(p) => p,
+        None => return String::new(),
+    };
+
+    let mut cursor = parameters.walk();
+    parameters.named_children(&mut cursor)
+              .filter_map(|p| {
+                  let (t, suffix) = match p.kind() {
+                      "formal_parameter" => (p.child_by_field_name("type")?, ""),
+                      "spread_parameter" => (p.named_child(0)?, "..."),
+                      _ => return None,
+                  };
+                  let t = t.utf8_text(code.as_bytes())
+                           .unwrap_or_default()
+                           .split_whitespace()
+                           .collect::<String>();
+                  Some(format!("{t}{suffix}"))
+              })
+              .collect::<Vec<_>>()
+              .join(", ")
+}
+
+/// Walks the tree and collects every method and constructor declaration.
+///
+/// * `node`: the node to start walking from
+/// * `code`: source code the node belongs to
+/// * `class`: name of the enclosing class, if any
+/// * `methods`: where to push found methods
+fn collect_methods<'a>(node: Node<'a>,
+                       code: &str,
+                       class: &str,
+                       methods: &mut Vec<Method<'a>>) {
+    let name = || {
+        node.child_by_field_name("name")
+            .and_then(|n| n.utf8_text(code.as_bytes()).ok())
+            .unwrap_or_default()
+    };
+
+    let class = if TYPE_DECLARATION_KINDS.contains(&node.kind()) {
+        if class.is_empty() {
+            name().to_string()
+        } else {
+            format!("{class}.{}", name())
+        }
+    } else {
+        if matches!(node.kind(),
+                    "method_declaration" | "constructor_declaration")
+        {
+            methods.push(Method { name: format!("{class}#{}({})",
+                                                name(),
+                                                parameter_types(node, code)),
+                                  node });
+        }
+        class.to_string()
+    };
+
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        collect_methods(child, code, &class, methods);
+    }
+}
+
+/// Returns the proper name (package and top level type name) of a parsed java
+/// file, if it declares a type.
+///
+/// * `parser`: parser for the file
+fn proper_name(parser: &Parser) -> Option<String> {
+    let tree = parser.tree()?;
+    let root = tree.root_node();
+    let code = parser.source();
+
+    let mut cursor = root.walk();
+    let name = root.children(&mut cursor)
+                   .find(|n| TYPE_DECLARATION_KINDS.contains(&n.kind()))?
+                   .child_by_field_name("name")?
+                   .utf8_text(code.as_bytes())
+                   .ok()?
+                   .to_string();
+
+    let package = parser.query(PACKAGE_QUERY)
+                        .unwrap_or_default()
+                        .first()
+                        .and_then(|p| p.get("name").cloned());
+
+    Some(match package {
+             Some(package) => format!("{package}.{name}"),
+             None => name,
+         })
+}
+
+impl Starter {
+    /// Loads starter code from a folder or a zip file. Every `.java` file in
+    /// it is parsed, regardless of where it is.
+    ///
+    /// * `path`: path to a folder or a zip file containing the starter code
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let mut starter = Self::default();
+
+        if path.is_dir() {
+            for entry in WalkDir::new(path).into_iter()
+                                           .filter_map(|e| e.ok())
+                                           .filter(|e| {
+                                               e.file_type().is_file()
+                                               && e.path().extension().unwrap_or_default() == "java"
+                                           })
+            {
+                let source = std::fs::read_to_string(entry.path()).with_context(|| {
+                                                                      format!("Could not read \
+                                                                               starter file: {}",
+                                                                              entry.path()
+                                                                                   .display())
+                                                                  })?;
+                starter.add_source(source)?;
+            }
+        } else {
+            let file = std::fs::File::open(path).with_context(|| {
+                                                    format!("Could not open starter code: {}",
+                                                            path.display())
+                                                })?;
+            let mut archive = zip::ZipArchive::new(file).with_context(|| {
+                                                            format!("{} is neither a folder nor a \
+                                                                     zip file",
+                                                                    path.display())
+                                                        })?;
+
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i)?;
+                if !entry.is_file() || !entry.name().ends_with(".java") {
+                    continue;
+                }
+
+                let mut source = String::new();
+                entry.read_to_string(&mut source)
+                     .with_context(|| format!("Could not read starter file: {}", entry.name()))?;
+                starter.add_source(source)?;
+            }
+        }
+
+        anyhow::ensure!(!starter.files.is_empty(),
+                        "No java files found in starter code at {}",
+                        path.display());
+        Ok(starter)
+    }
+
+    /// Parses a java source file and adds it to the starter code.
+    ///
+    /// * `source`: source code of the file
+    fn add_source(&mut self,
+                  source: String)
+                  -> Result<()> {
+        let parser = Parser::new(source)?;
+        let (tree, name) = match (parser.tree(), proper_name(&parser)) {
+            (Some(tree), Some(name)) => (tree, name),
+            _ => return Ok(()),
+        };
+        let code = parser.source();
+
+        let mut methods = vec![];
+        collect_methods(tree.root_node(), code, "", &mut methods);
+
+        let mut file = StarterFile { code:    normalize(tree.root_node(), code),
+                                     methods: HashMap::new(), };
+        for method in methods {
+            file.methods
+                .entry(method.name)
+                .or_insert_with(|| normalize(method.node, code));
+        }
+
+        self.files.insert(name, file);
+        Ok(())
+    }
+
+    /// Returns how a student's file compares to the starter code.
+    ///
+    /// * `file`: the student's file
+    pub fn file_change(&self,
+                       file: &File)
+                       -> Change {
+        let starter_file = match self.files.get(&file.proper_name()) {
+            Some(f) => f,
+            None => return Change::Added,
+        };
+
+        let parser = file.parser();
+        match parser.tree() {
+            Some(tree) if normalize(tree.root_node(), parser.source()) == starter_file.code => {
+                Change::Unchanged
+            }
+            _ => Change::Modified,
+        }
+    }
+
+    /// Returns every method and constructor in a student's file, and how each
+    /// compares to the starter code.
+    ///
+    /// * `file`: the student's file
+    pub fn method_changes(&self,
+                          file: &File)
+                          -> Vec<MethodChange> {
+        let parser = file.parser();
+        let tree = match parser.tree() {
+            Some(t) => t,
+            None => return vec![],
+        };
+        let code = parser.source();
+        let starter_file = self.files.get(&file.proper_name());
+
+        let mut methods = vec![];
+        collect_methods(tree.root_node(), code, "", &mut methods);
+
+        methods.into_iter()
+               .map(|m| {
+                   let change = match starter_file.and_then(|f| f.methods.get(&m.name)) {
+                       Some(starter) if *starter == normalize(m.node, code) => Change::Unchanged,
+                       Some(_) => Change::Modified,
+                       None => Change::Added,
+                   };
+
+                   MethodChange { name: m.name,
+                                  change,
+                                  start_line: m.node.start_position().row + 1,
+                                  end_line: m.node.end_position().row + 1,
+                                  start_byte: m.node.start_byte(),
+                                  end_byte: m.node.end_byte(),
+                                  code: m.node
+                                         .utf8_text(code.as_bytes())
+                                         .unwrap_or_default()
+                                         .to_string() }
+               })
+               .collect()
+    }
+
+    /// Returns methods and constructors the student added or modified.
+    ///
+    /// * `file`: the student's file
+    pub fn modified_methods(&self,
+                            file: &File)
+                            -> Vec<MethodChange> {
+        self.method_changes(file)
+            .into_iter()
+            .filter(|m| m.change != Change::Unchanged)
+            .collect()
+    }
+
+    /// Returns the code the student wrote in a file: the entire file if it is
+    /// not part of the starter code, only the added or modified methods if it
+    /// is, and `None` if the file is unchanged.
+    ///
+    /// * `file`: the student's file
+    pub fn student_written(&self,
+                           file: &File)
+                           -> Option<String> {
+        match self.file_change(file) {
+            Change::Unchanged => None,
+            Change::Added => Some(file.parser().source().to_string()),
+            Change::Modified => {
+                let methods = self.modified_methods(file);
+                if methods.is_empty() {
+                    Some(file.parser().source().to_string())
+                } else {
+                    Some(methods.into_iter()
+                                .map(|m| m.code)
+                                .collect::<Vec<String>>()
+                                .join("\n\n"))
+                }
+            }
+        }
+    }
+
+    /// Returns the source code of a student's file with every method that is
+    /// unchanged from the starter code blanked out. Line and column numbers
+    /// are preserved.
+    ///
+    /// * `file`: the student's file
+    pub fn student_code(&self,
+                        file: &File)
+                        -> String {
+        let mut code = file.parser().source().as_bytes().to_vec();
+
+        for m in self.method_changes(file)
+                     .into_iter()
+                     .filter(|m| m.change == Change::Unchanged)
+        {
+            for byte in &mut code[m.start_byte..m.end_byte] {
+                if !byte.is_ascii_whitespace() {
+                    *byte = b' ';
+                }
+            }
+        }
+
+        String::from_utf8_lossy(&code).to_string()
+    }
+}
+
+/// Returns the registered starter code, if any.
+pub fn starter_code() -> Option<Starter> {
+    STARTER_CODE.lock().unwrap().clone()
+}
+
+#[generate_rhai_variant(Fallible)]
+/// Registers starter code so that graders and feedback prompts can focus on
+/// what the student wrote.
+///
+/// * `path`: path to a folder or a zip file containing the starter code,
+///   relative to the project root
+pub fn use_starter_code(path: String) -> Result<()> {
+    let starter = Starter::from_path(&ROOT_DIR.join(path))?;
+    *STARTER_CODE.lock().unwrap() = Some(starter);
+    Ok(())
+}
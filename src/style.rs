@@ -0,0 +1,459 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{collections::HashSet, fmt::Display, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use tabled::Tabled;
+use tree_sitter::Node;
+
+use crate::{grade::LineRef, java::File, metrics::nesting_depth};
+
+/// Numeric literals that are never considered magic numbers
+const ALLOWED_NUMBERS: [&str; 6] = ["0", "1", "2", "0.0", "1.0", "2.0"];
+
+/// Node kinds of primitive types, whose `static final` fields are constants
+const PRIMITIVE_KINDS: [&str; 3] = ["integral_type", "floating_point_type", "boolean_type"];
+
+/// Field names Java requires to be spelled a certain way
+const REQUIRED_NAMES: [&str; 1] = ["serialVersionUID"];
+
+/// Numeric literal node kinds
+const NUMBER_KINDS: [&str; 6] = ["decimal_integer_literal",
+                                 "hex_integer_literal",
+                                 "octal_integer_literal",
+                                 "binary_integer_literal",
+                                 "decimal_floating_point_literal",
+                                 "hex_floating_point_literal"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+/// A built-in style rule
+pub enum StyleRule {
+    /// camelCase methods and variables, PascalCase types, UPPER_SNAKE
+    /// primitive and `String` constants
+    Naming,
+    /// numeric literals other than 0, 1 and 2 outside of constant
+    /// declarations
+    MagicNumbers,
+    /// methods and constructors longer than the configured number of lines
+    MethodLength,
+    /// control flow nested deeper than the configured depth
+    NestingDepth,
+    /// imports that are never referred to
+    UnusedImports,
+    /// local variables that are never referred to
+    UnusedLocals,
+    /// `if`, `else`, and loop bodies without braces
+    MissingBraces,
+}
+
+impl StyleRule {
+    /// All built-in rules
+    pub const ALL: [StyleRule; 7] = [StyleRule::Naming,
+                                     StyleRule::MagicNumbers,
+                                     StyleRule::MethodLength,
+                                     StyleRule::NestingDepth,
+                                     StyleRule::UnusedImports,
+                                     StyleRule::UnusedLocals,
+                                     StyleRule::MissingBraces];
+}
+
+impl Display for StyleRule {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        let name = match self {
+            StyleRule::Naming => "naming",
+            StyleRule::MagicNumbers => "magic_numbers",
+            StyleRule::MethodLength => "method_length",
+            StyleRule::NestingDepth => "nesting_depth",
+            StyleRule::UnusedImports => "unused_imports",
+            StyleRule::UnusedLocals => "unused_locals",
+            StyleRule::MissingBraces => "missing_braces",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for StyleRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match StyleRule::ALL.iter().find(|r| r.to_string() == s) {
+            Some(rule) => Ok(*rule),
+            None => bail!("Unknown style rule: {}. Expected one of: {}",
+                          s,
+                          StyleRule::ALL.map(|r| r.to_string()).join(", ")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Thresholds used by the rules that need one
+pub struct StyleOptions {
+    /// maximum number of lines in a method or constructor
+    pub max_method_length: usize,
+    /// maximum depth of nested control flow inside a method
+    pub max_nesting_depth: usize,
+}
+
+impl Default for StyleOptions {
+    fn default() -> Self {
+        Self { max_method_length: 30,
+               max_nesting_depth: 3, }
+    }
+}
+
+#[derive(Tabled, Serialize, Deserialize, Clone, Debug)]
+/// A struct representing a single style violation
+pub struct StyleViolation {
+    /// * `path`: path to the file the violation is in
+    #[tabled(rename = "File")]
+    pub path:        String,
+    /// * `file_name`: name of the file the violation is in
+    #[tabled(skip)]
+    pub file_name:   String,
+    /// * `line_number`: line number, starting at 1
+    #[tabled(rename = "Line")]
+    pub line_number: usize,
+    /// * `rule`: the rule that was violated
+    #[tabled(rename = "Rule")]
+    pub rule:        StyleRule,
+    /// * `message`: a description of the violation
+    #[tabled(rename = "Message")]
+    pub message:     String,
+}
+
+impl From<StyleViolation> for LineRef {
+    /// Converts a StyleViolation to a LineRef
+    fn from(val: StyleViolation) -> Self {
+        LineRef { file_name:   val.file_name,
+                  line_number: val.line_number, }
+    }
+}
+
+/// Returns true if `name` looks like `camelCase`
+fn is_camel_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_lowercase())
+    && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Returns true if `name` looks like `PascalCase`
+fn is_pascal_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+    && name.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// Returns true if `name` looks like `UPPER_SNAKE_CASE`
+fn is_upper_snake_case(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase())
+    && name.chars()
+           .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit() || c == '_')
+}
+
+/// Collects every node in the subtree rooted at `node`, in pre-order
+fn descendants(node: Node) -> Vec<Node> {
+    let mut nodes = vec![node];
+    let mut cursor = node.walk();
+    for child in node.children(&mut cursor) {
+        nodes.extend(descendants(child));
+    }
+    nodes
+}
+
+/// Returns true if a declaration node has both the `static` and `final`
+/// modifiers
+fn is_constant(node: Node,
+               code: &str)
+               -> bool {
+    let mut cursor = node.walk();
+    let modifiers = node.children(&mut cursor)
+                        .find(|c| c.kind() == "modifiers")
+                        .and_then(|m| m.utf8_text(code.as_bytes()).ok())
+                        .unwrap_or_default();
+    let words: Vec<&str> = modifiers.split_whitespace().collect();
+    words.contains(&"static") && words.contains(&"final")
+}
+
+/// Returns true if a field declaration's type is a primitive or `String`,
+/// making its `static final` fields constants. Others, like loggers, refer to
+/// objects that can change.
+fn has_constant_type(node: Node,
+                     code: &str)
+                     -> bool {
+    node.child_by_field_name("type").is_some_and(|t| {
+                                        PRIMITIVE_KINDS.contains(&t.kind())
+                                        || t.utf8_text(code.as_bytes()).ok() == Some("String")
+                                    })
+}
+
+/// Lints a single java file.
+///
+/// * `file`: the file to lint
+/// * `rules`: the rules to check
+/// * `options`: thresholds for rules that need one
+pub fn lint(file: &File,
+            rules: &[StyleRule],
+            options: StyleOptions)
+            -> Result<Vec<StyleViolation>> {
+    let mut parser = file.parser();
+    let code = parser.code();
+    let tree = parser.tree().context("Treesitter could not parse code")?;
+    let root = tree.root_node();
+    let nodes = descendants(root);
+    let text = |n: Node| n.utf8_text(code.as_bytes()).unwrap_or_default().to_string();
+
+    let mut violations = vec![];
+    let mut report = |node: Node, rule: StyleRule, message: String| {
+        violations.push(StyleViolation { path: file.path().display().to_string(),
+                                         file_name: file.file_name().to_string(),
+                                         line_number: node.start_position().row + 1,
+                                         rule,
+                                         message });
+    };
+
+    for rule in rules {
+        match rule {
+            StyleRule::Naming => {
+                for &node in &nodes {
+                    let name = match node.child_by_field_name("name") {
+                        Some(n) => text(n),
+                        None => continue,
+                    };
+                    match node.kind() {
+                        "class_declaration"
+                        | "interface_declaration"
+                        | "enum_declaration"
+                        | "record_declaration"
+                            if !is_pascal_case(&name) =>
+                        {
+                            report(node, *rule, format!("Type `{name}` should be PascalCase"))
+                        }
+                        "method_declaration" if !is_camel_case(&name) => {
+                            report(node, *rule, format!("Method `{name}` should be camelCase"))
+                        }
+                        "formal_parameter" if !is_camel_case(&name) => {
+                            report(node,
+                                   *rule,
+                                   format!("Parameter `{name}` should be camelCase"))
+                        }
+                        "variable_declarator" => {
+                            let declaration = match node.parent() {
+                                Some(p) => p,
+                                None => continue,
+                            };
+                            let static_final = declaration.kind() == "field_declaration"
+                                               && is_constant(declaration, &code);
+                            let constant = static_final && has_constant_type(declaration, &code);
+                            if REQUIRED_NAMES.contains(&name.as_str()) {
+                                continue;
+                            } else if constant && !is_upper_snake_case(&name) {
+                                report(node,
+                                       *rule,
+                                       format!("Constant `{name}` should be UPPER_SNAKE_CASE"));
+                            } else if static_final
+                                      && !constant
+                                      && !is_upper_snake_case(&name)
+                                      && !is_camel_case(&name)
+                            {
+                                report(node,
+                                       *rule,
+                                       format!("Field `{name}` should be camelCase or \
+                                                UPPER_SNAKE_CASE"));
+                            } else if !static_final && !is_camel_case(&name) {
+                                report(node,
+                                       *rule,
+                                       format!("Variable `{name}` should be camelCase"));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            StyleRule::MagicNumbers => {
+                for &node in &nodes {
+                    if !NUMBER_KINDS.contains(&node.kind()) {
+                        continue;
+                    }
+                    let literal = text(node);
+                    let normalized = literal.trim_end_matches(['l', 'L', 'f', 'F', 'd', 'D']);
+                    if ALLOWED_NUMBERS.contains(&normalized) {
+                        continue;
+                    }
+
+                    let mut ancestor = node.parent();
+                    let mut exempt = false;
+                    while let Some(a) = ancestor {
+                        if (a.kind() == "field_declaration" && is_constant(a, &code))
+                           || a.kind().contains("annotation")
+                        {
+                            exempt = true;
+                            break;
+                        }
+                        ancestor = a.parent();
+                    }
+
+                    if !exempt {
+                        report(node,
+                               *rule,
+                               format!("Magic number `{literal}`, consider a named constant"));
+                    }
+                }
+            }
+            StyleRule::MethodLength => {
+                for &node in &nodes {
+                    if !matches!(node.kind(),
+                                 "method_declaration" | "constructor_declaration")
+                    {
+                        continue;
+                    }
+                    let body = match node.child_by_field_name("body") {
+                        Some(b) => b,
+                        None => continue,
+                    };
+                    let length = body.end_position().row - body.start_position().row + 1;
+                    if length > options.max_method_length {
+                        let name = node.child_by_field_name("name")
+                                       .map(text)
+                                       .unwrap_or_default();
+                        report(node,
+                               *rule,
+                               format!("`{name}` is {length} lines long, maximum is {}",
+                                       options.max_method_length));
+                    }
+                }
+            }
+            StyleRule::NestingDepth => {
+                for &node in &nodes {
+                    if !matches!(node.kind(),
+                                 "method_declaration" | "constructor_declaration")
+                    {
+                        continue;
+                    }
+                    let body = match node.child_by_field_name("body") {
+                        Some(b) => b,
+                        None => continue,
+                    };
+                    let deepest = nesting_depth(body);
+                    if deepest > options.max_nesting_depth {
+                        let name = node.child_by_field_name("name")
+                                       .map(text)
+                                       .unwrap_or_default();
+                        report(node,
+                               *rule,
+                               format!("`{name}` nests control flow {deepest} levels deep, \
+                                        maximum is {}",
+                                       options.max_nesting_depth));
+                    }
+                }
+            }
+            StyleRule::UnusedImports => {
+                let used: HashSet<String> =
+                    nodes.iter()
+                         .copied()
+                         .filter(|n| matches!(n.kind(), "identifier" | "type_identifier"))
+                         .filter(|n| {
+                             let mut ancestor = n.parent();
+                             while let Some(a) = ancestor {
+                                 if matches!(a.kind(), "import_declaration" | "package_declaration")
+                                 {
+                                     return false;
+                                 }
+                                 ancestor = a.parent();
+                             }
+                             true
+                         })
+                         .map(text)
+                         .collect();
+
+                for &node in &nodes {
+                    if node.kind() != "import_declaration" {
+                        continue;
+                    }
+                    let mut cursor = node.walk();
+                    let children: Vec<Node> = node.children(&mut cursor).collect();
+                    if children.iter().any(|c| c.kind() == "asterisk") {
+                        continue;
+                    }
+                    let imported =
+                        match children.iter()
+                                      .find(|c| {
+                                          matches!(c.kind(), "scoped_identifier" | "identifier")
+                                      }) {
+                            Some(i) => text(*i),
+                            None => continue,
+                        };
+                    let name = imported.rsplit('.').next().unwrap_or_default();
+                    if !used.contains(name) {
+                        report(node, *rule, format!("Unused import `{imported}`"));
+                    }
+                }
+            }
+            StyleRule::UnusedLocals => {
+                for &node in &nodes {
+                    if node.kind() != "local_variable_declaration" {
+                        continue;
+                    }
+                    let scope = match node.parent() {
+                        Some(p) => p,
+                        None => continue,
+                    };
+                    let mut cursor = node.walk();
+                    for declarator in node.children_by_field_name("declarator", &mut cursor) {
+                        let name_node = match declarator.child_by_field_name("name") {
+                            Some(n) => n,
+                            None => continue,
+                        };
+                        let name = text(name_node);
+                        let used = descendants(scope).into_iter().any(|n| {
+                                                                     n.kind() == "identifier"
+                                                                     && n.id() != name_node.id()
+                                                                     && text(n) == name
+                                                                 });
+                        if !used {
+                            report(declarator,
+                                   *rule,
+                                   format!("Local variable `{name}` is never used"));
+                        }
+                    }
+                }
+            }
+            StyleRule::MissingBraces => {
+                for &node in &nodes {
+                    let bodies: Vec<(&str, Option<Node>)> = match node.kind() {
+                        "if_statement" => {
+                            vec![("if", node.child_by_field_name("consequence")),
+                                 ("else", node.child_by_field_name("alternative"))]
+                        }
+                        "for_statement" | "enhanced_for_statement" => {
+                            vec![("for", node.child_by_field_name("body"))]
+                        }
+                        "while_statement" => vec![("while", node.child_by_field_name("body"))],
+                        "do_statement" => vec![("do", node.child_by_field_name("body"))],
+                        _ => continue,
+                    };
+
+                    for (keyword, body) in bodies {
+                        let body = match body {
+                            Some(b) => b,
+                            None => continue,
+                        };
+                        if keyword == "else" && body.kind() == "if_statement" {
+                            continue;
+                        }
+                        if body.kind() != "block" {
+                            report(body,
+                                   *rule,
+                                   format!("`{keyword}` body should be wrapped in braces"));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    violations.sort_by_key(|v| v.line_number);
+    Ok(violations)
+}
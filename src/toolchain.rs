@@ -0,0 +1,418 @@
+#![warn(missing_docs)]
+#![warn(clippy::missing_docs_in_private_items)]
+
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use tabled::{object::Rows, Modify, TableIteratorExt, Tabled, Width};
+use which::which;
+
+use crate::{
+    config::{JavaConfig, ProjectConfig},
+    constants::{JAVA_MIN_VERSION_ENV, JAVA_RELEASE_ENV, MIN_JAVA_VERSION, PROJECT_CONFIG},
+};
+
+/// Where a JDK was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JdkSource {
+    /// the `JAVA_HOME` environment variable
+    JavaHome,
+    /// the first `javac` on `PATH`
+    Path,
+    /// a JDK installed with sdkman
+    Sdkman,
+    /// a JDK installed with asdf
+    Asdf,
+    /// a folder JDK installers commonly use
+    Installed,
+}
+
+impl Display for JdkSource {
+    fn fmt(&self,
+           f: &mut std::fmt::Formatter<'_>)
+           -> std::fmt::Result {
+        let name = match self {
+            JdkSource::JavaHome => "JAVA_HOME",
+            JdkSource::Path => "PATH",
+            JdkSource::Sdkman => "sdkman",
+            JdkSource::Asdf => "asdf",
+            JdkSource::Installed => "installed",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Displays an optional version in a table cell
+fn display_version(version: &Option<u32>) -> String {
+    version.map(|v| v.to_string())
+           .unwrap_or_else(|| String::from("?"))
+}
+
+/// Displays why a JDK was skipped in a table cell
+fn display_status(skipped: &Option<String>) -> String {
+    match skipped {
+        Some(reason) => format!("skipped: {reason}"),
+        None => String::from("usable"),
+    }
+}
+
+#[derive(Tabled, Serialize, Deserialize, Clone, Debug)]
+/// A Java installation umm found, and whether it can be used
+pub struct Jdk {
+    /// * `source`: where it was found
+    #[tabled(rename = "Source")]
+    pub source:  JdkSource,
+    /// * `home`: the folder containing `bin/javac`
+    #[tabled(rename = "Home", display_with = "display_path")]
+    pub home:    PathBuf,
+    /// * `version`: the major version, such as 17, if it could be worked out
+    #[tabled(rename = "Version", display_with = "display_version")]
+    pub version: Option<u32>,
+    /// * `skipped`: why it can't be used, if it can't
+    #[tabled(rename = "Status", display_with = "display_status")]
+    pub skipped: Option<String>,
+}
+
+/// Displays a path in a table cell
+fn display_path(path: &Path) -> String {
+    path.display().to_string()
+}
+
+impl Jdk {
+    /// Looks at a Java installation, working out its version and whether it
+    /// has everything umm needs
+    ///
+    /// * `source`: where it was found
+    /// * `home`: the folder containing `bin/java`
+    /// * `minimum`: the oldest major version allowed
+    fn inspect(source: JdkSource,
+               home: PathBuf,
+               minimum: u32)
+               -> Self {
+        let version = release_version(&home).or_else(|| javac_version(&home));
+
+        let skipped = if !tool(&home, "java").is_file() {
+            Some(String::from("no java found in its bin folder"))
+        } else if !tool(&home, "javac").is_file() {
+            Some(String::from("no javac found, this looks like a JRE instead of a JDK"))
+        } else {
+            match version {
+                Some(v) if v < minimum => {
+                    Some(format!("Java {v} is older than the minimum, Java {minimum}"))
+                }
+                Some(_) => None,
+                None => Some(String::from("could not work out its version")),
+            }
+        };
+
+        Self { source,
+               home,
+               version,
+               skipped }
+    }
+
+    /// Path to `javac` in this JDK
+    pub fn javac(&self) -> PathBuf {
+        tool(&self.home, "javac")
+    }
+
+    /// Path to `java` in this JDK
+    pub fn java(&self) -> PathBuf {
+        tool(&self.home, "java")
+    }
+}
+
+/// Path to a tool in the `bin` folder of a Java installation
+///
+/// * `home`: the Java installation
+/// * `name`: name of the tool, without `.exe`
+fn tool(home: &Path,
+        name: &str)
+        -> PathBuf {
+    home.join("bin")
+        .join(format!("{name}{}", std::env::consts::EXE_SUFFIX))
+}
+
+/// Parses the major version out of the output of `javac -version`, or a
+/// version string like `17.0.2`, `21-ea` or `1.8.0_392`
+///
+/// * `output`: text containing a version
+pub fn parse_version(output: &str) -> Option<u32> {
+    let version = output.split(|c: char| c.is_whitespace() || c == '"')
+                        .find(|word| word.starts_with(|c: char| c.is_ascii_digit()))?;
+    let mut parts = version.split(|c: char| !c.is_ascii_digit());
+    match parts.next()?.parse().ok()? {
+        1 => parts.next()?.parse().ok(),
+        major => Some(major),
+    }
+}
+
+/// Reads the version from the `release` file at the root of a Java
+/// installation, which saves running `javac`
+///
+/// * `home`: the Java installation
+fn release_version(home: &Path) -> Option<u32> {
+    let release = std::fs::read_to_string(home.join("release")).ok()?;
+    release.lines()
+           .find_map(|line| line.strip_prefix("JAVA_VERSION="))
+           .and_then(parse_version)
+}
+
+/// Runs `javac -version` from a Java installation and parses its output.
+/// Older JDKs print the version to stderr, newer ones to stdout.
+///
+/// * `home`: the Java installation
+fn javac_version(home: &Path) -> Option<u32> {
+    let output = Command::new(tool(home, "javac")).arg("-version")
+                                                  .output()
+                                                  .ok()?;
+    parse_version(&format!("{}{}",
+                           String::from_utf8_lossy(&output.stdout),
+                           String::from_utf8_lossy(&output.stderr)))
+}
+
+/// The user's home folder
+fn home_dir() -> Option<PathBuf> {
+    std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE"))
+                            .map(PathBuf::from)
+}
+
+/// Java installations in sub-folders of `dir`, newest first
+///
+/// * `dir`: a folder containing one folder per installation
+/// * `suffix`: path from each sub-folder to its Java home, such as
+///   `Contents/Home` on macOS
+fn installs_in(dir: &Path,
+               suffix: &str)
+               -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return vec![];
+    };
+
+    let mut homes = entries.filter_map(|e| e.ok())
+                           .map(|e| {
+                               if suffix.is_empty() {
+                                   e.path()
+                               } else {
+                                   e.path().join(suffix)
+                               }
+                           })
+                           .filter(|p| !p.ends_with("current") && tool(p, "java").is_file())
+                           .collect::<Vec<_>>();
+    homes.sort_by_key(|home| std::cmp::Reverse(release_version(home)));
+    homes
+}
+
+/// The Java installation a `javac` or `java` binary belongs to, following
+/// symlinks such as `/usr/bin/javac`
+///
+/// * `binary`: path to the binary
+fn home_of(binary: &Path) -> Option<PathBuf> {
+    let binary = binary.canonicalize().ok()?;
+    Some(binary.parent()?.parent()?.to_path_buf())
+}
+
+/// The Java installations umm found, and the one it uses
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Toolchain {
+    /// the oldest major version of Java allowed
+    pub minimum:  u32,
+    /// the version passed to `javac --release`, if one was configured
+    pub release:  Option<u32>,
+    /// every installation found, in order of preference
+    pub jdks:     Vec<Jdk>,
+    /// index into `jdks` of the one in use
+    pub selected: Option<usize>,
+}
+
+impl Toolchain {
+    /// Looks for Java installations and picks the first usable one, checking
+    /// `JAVA_HOME`, then `PATH`, then JDKs installed with sdkman or asdf, and
+    /// finally the folders JDK installers commonly use, newest first.
+    ///
+    /// The oldest version allowed and the version passed to `javac --release`
+    /// are set with `min_version` and `release` in the `[java]` table of
+    /// `umm.toml`, or with `UMM_JAVA_MIN_VERSION` and `UMM_JAVA_RELEASE` in
+    /// the environment or a `.env` file. A release also requires at least
+    /// that version, as older JDKs can't compile for it.
+    pub fn detect() -> Self {
+        let config = ProjectConfig::load().unwrap_or_else(|e| {
+                                              tracing::warn!("Ignoring {PROJECT_CONFIG}: {e:#}");
+                                              ProjectConfig::default()
+                                          });
+        let JavaConfig { min_version,
+                         release, } = config.java;
+
+        let version = |name: &str, configured: Option<u32>| match std::env::var(name) {
+            Ok(value) => match value.trim().parse::<u32>() {
+                Ok(version) => Some(version),
+                Err(_) => {
+                    tracing::warn!("Ignoring {name}={value}, expected a major version like 17");
+                    configured
+                }
+            },
+            Err(_) => configured,
+        };
+        let release = version(JAVA_RELEASE_ENV, release);
+        let minimum = version(JAVA_MIN_VERSION_ENV, min_version).unwrap_or(MIN_JAVA_VERSION)
+                                                                .max(release.unwrap_or_default());
+
+        let mut homes = vec![];
+        if let Some(java_home) = std::env::var_os("JAVA_HOME").filter(|h| !h.is_empty()) {
+            homes.push((JdkSource::JavaHome, PathBuf::from(java_home)));
+        }
+        if let Some(home) = which("javac").or_else(|_| which("java"))
+                                          .ok()
+                                          .and_then(|p| home_of(&p))
+        {
+            homes.push((JdkSource::Path, home));
+        }
+
+        let user_home = home_dir().unwrap_or_default();
+        let sdkman =
+            std::env::var_os("SDKMAN_DIR").map_or_else(|| user_home.join(".sdkman"), PathBuf::from);
+        homes.extend(installs_in(&sdkman.join("candidates").join("java"), "").into_iter()
+                                                                             .map(|h| {
+                                                                                 (JdkSource::Sdkman,
+                                                                                  h)
+                                                                             }));
+        let asdf = std::env::var_os("ASDF_DATA_DIR").map_or_else(|| user_home.join(".asdf"),
+                                                                 PathBuf::from);
+        homes.extend(installs_in(&asdf.join("installs").join("java"), "").into_iter()
+                                                                         .map(|h| {
+                                                                             (JdkSource::Asdf, h)
+                                                                         }));
+
+        let installed: &[(&str, &str)] = if cfg!(windows) {
+            &[("C:\\Program Files\\Java", ""),
+              ("C:\\Program Files\\Eclipse Adoptium", ""),
+              ("C:\\Program Files\\Microsoft", ""),
+              ("C:\\Program Files\\Zulu", "")]
+        } else if cfg!(target_os = "macos") {
+            &[("/Library/Java/JavaVirtualMachines", "Contents/Home"),
+              ("/opt/homebrew/opt", "libexec/openjdk.jdk/Contents/Home")]
+        } else {
+            &[("/usr/lib/jvm", ""), ("/usr/java", ""), ("/opt/java", "")]
+        };
+        for (dir, suffix) in installed {
+            homes.extend(installs_in(Path::new(dir), suffix).into_iter()
+                                                            .map(|h| (JdkSource::Installed, h)));
+        }
+
+        let mut jdks: Vec<Jdk> = vec![];
+        for (source, home) in homes {
+            let canonical = home.canonicalize().unwrap_or_else(|_| home.clone());
+            if jdks.iter()
+                   .any(|j| j.home.canonicalize().unwrap_or_else(|_| j.home.clone()) == canonical)
+            {
+                continue;
+            }
+            jdks.push(Jdk::inspect(source, home, minimum));
+        }
+
+        let selected = jdks.iter().position(|j| j.skipped.is_none());
+        Self { minimum,
+               release,
+               jdks,
+               selected }
+    }
+
+    /// The JDK in use, if a usable one was found
+    pub fn jdk(&self) -> Option<&Jdk> {
+        self.selected.map(|i| &self.jdks[i])
+    }
+
+    /// The JDK in use, or an error explaining why none could be used
+    pub fn require_jdk(&self) -> Result<&Jdk> {
+        if let Some(jdk) = self.jdk() {
+            return Ok(jdk);
+        }
+
+        if self.jdks.is_empty() {
+            bail!("Cannot find a Java Development Kit (JDK). Please install Java {} or newer, or \
+                   set JAVA_HOME to where it is installed.",
+                  self.minimum);
+        }
+
+        let reasons = self.jdks
+                          .iter()
+                          .map(|j| {
+                              format!("  - {} ({}): {}",
+                                      j.home.display(),
+                                      j.source,
+                                      j.skipped.clone().unwrap_or_default())
+                          })
+                          .collect::<Vec<_>>()
+                          .join("\n");
+        bail!("None of the Java installations found can be used:\n{reasons}\nPlease install a \
+               JDK, Java {} or newer, or set JAVA_HOME to one. Run `umm toolchain` for details.",
+              self.minimum)
+    }
+
+    /// Arguments to pass to `javac` for the configured release, if any
+    pub fn javac_args(&self) -> Vec<String> {
+        match self.release {
+            Some(release) => vec![String::from("--release"), release.to_string()],
+            None => vec![],
+        }
+    }
+
+    /// Explains why the JDK in use was picked, for `umm toolchain`
+    fn reason(&self) -> String {
+        let Some(selected) = self.selected else {
+            return String::from("no usable JDK was found");
+        };
+        let jdk = &self.jdks[selected];
+
+        let why = match jdk.source {
+            JdkSource::JavaHome => String::from("JAVA_HOME points to it"),
+            JdkSource::Path => String::from("it provides the first javac on PATH"),
+            JdkSource::Sdkman => String::from("it is the newest usable JDK installed with sdkman"),
+            JdkSource::Asdf => String::from("it is the newest usable JDK installed with asdf"),
+            JdkSource::Installed => String::from("it is the newest usable JDK installed"),
+        };
+        if selected == 0 {
+            why
+        } else {
+            format!("{why}, and the ones before it were skipped")
+        }
+    }
+
+    /// Prints the Java installations found, and which one is in use and why
+    pub fn show(&self) -> Result<()> {
+        println!("Minimum Java version: {} (set min_version in {PROJECT_CONFIG} or \
+                  {JAVA_MIN_VERSION_ENV} to change it)",
+                 self.minimum);
+        match self.release {
+            Some(release) => println!("Compiling for Java {release} with javac --release"),
+            None => println!("Compiling for the JDK in use (set release in {PROJECT_CONFIG} or \
+                              {JAVA_RELEASE_ENV} to target an older Java)"),
+        }
+
+        if !self.jdks.is_empty() {
+            println!("{}",
+                     self.jdks
+                         .iter()
+                         .table()
+                         .with(Modify::new(Rows::new(1..)).with(Width::wrap(60).keep_words()))
+                         .with(tabled::Style::modern()));
+        }
+
+        match self.jdk() {
+            Some(jdk) => {
+                println!("Using {} (Java {}) because {}.",
+                         jdk.home.display(),
+                         display_version(&jdk.version),
+                         self.reason());
+                Ok(())
+            }
+            None => self.require_jdk().map(|_| ()),
+        }
+    }
+}
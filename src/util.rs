@@ -14,16 +14,16 @@ use which::which;
 
 use crate::constants::*;
 
-/// Finds and returns the path to javac binary
+/// Finds and returns the path to javac binary, from the JDK selected by
+/// [struct@TOOLCHAIN]
 pub fn javac_path() -> Result<OsString> {
-    which("javac").map(PathBuf::into_os_string)
-                  .context("Cannot find a Java Compiler on path (javac)")
+    Ok(TOOLCHAIN.require_jdk()?.javac().into_os_string())
 }
 
-/// Finds and returns the path to java binary
+/// Finds and returns the path to java binary, from the JDK selected by
+/// [struct@TOOLCHAIN]
 pub fn java_path() -> Result<OsString> {
-    which("java").map(PathBuf::into_os_string)
-                 .context("Cannot find a Java runtime on path (java)")
+    Ok(TOOLCHAIN.require_jdk()?.java().into_os_string())
 }
 
 /// Finds and returns the path to umm
@@ -1,7 +1,11 @@
 #![warn(missing_docs)]
 #![warn(clippy::missing_docs_in_private_items)]
 
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use typed_builder::TypedBuilder;
 
 /// Enum for VSCode task's type.
@@ -134,7 +138,7 @@ pub struct ProblemMatcher {
     /// task.
     #[builder(default, setter(into))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    background:    Option<String>,
+    background:    Option<Background>,
     /// The name of a base problem matcher to use.
     #[builder(default, setter(into))]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -169,6 +173,18 @@ pub struct ProblemMatcher {
     source:        Option<String>,
 }
 
+/// Struct for VSCode task's problem matcher's background patterns.
+#[derive(Serialize, Deserialize, TypedBuilder)]
+#[serde(rename_all = "camelCase")]
+pub struct Background {
+    /// If matched in the output, the start of a background task is signaled.
+    #[builder(setter(into))]
+    active_begin: String,
+    /// If matched in the output, the end of a background task is signaled.
+    #[builder(setter(into))]
+    active_end:   String,
+}
+
 /// Struct for VSCode task's problem matcher's pattern.
 #[derive(Serialize, Deserialize, TypedBuilder)]
 #[serde(rename_all = "camelCase")]
@@ -229,6 +245,18 @@ pub struct Pattern {
     severity:   Option<usize>,
 }
 
+/// Struct for VSCode task's options.
+#[derive(Serialize, Deserialize, TypedBuilder)]
+#[builder(field_defaults(default, setter(into)))]
+pub struct TaskOptions {
+    /// The current working directory of the executed program or script.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cwd: Option<String>,
+    /// The environment of the executed program or shell.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    env: Option<HashMap<String, String>>,
+}
+
 /// Struct to represent a VSCode task as JSON.
 #[derive(Serialize, Deserialize, TypedBuilder)]
 #[serde(rename_all = "camelCase")]
@@ -265,7 +293,7 @@ pub struct Task {
     /// or other task attributes.
     #[builder(default, setter(into))]
     #[serde(skip_serializing_if = "Option::is_none")]
-    options:         Option<String>,
+    options:         Option<TaskOptions>,
     /// Arguments passed to the command when this task is invoked.
     #[builder(default, setter(into))]
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -350,13 +378,13 @@ pub struct KeyBindings {
     key:     String,
     /// The command to execute, defaults to `workbench.action.tasks.runTask`
     #[serde(default = "run_task_action")]
-    #[builder(default, setter(into))]
+    #[builder(default = run_task_action(), setter(into))]
     command: Option<String>,
     /// The command's arguments - name of task, etc.
     args:    String,
     /// when to activate keybinding
     #[serde(default = "when_keybindings")]
-    #[builder(default, setter(into))]
+    #[builder(default = when_keybindings(), setter(into))]
     when:    Option<String>,
 }
 
@@ -434,3 +462,343 @@ pub struct SettingsFile<'a> {
     #[builder(default = false)]
     minimap:              bool,
 }
+
+/// Enum for VSCode launch configuration's request type.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Request {
+    /// Launch a program with the debugger attached.
+    Launch,
+    /// Attach the debugger to an already running program.
+    Attach,
+}
+
+/// default presentation of debug configurations, grouping umm's own
+fn umm_presentation() -> Option<LaunchPresentation> {
+    Some(LaunchPresentation { group: "umm".to_string(), })
+}
+
+/// How a debug configuration is shown in the Run and Debug view.
+#[derive(Serialize, Deserialize)]
+pub struct LaunchPresentation {
+    /// The group the configuration is listed under. umm's are in `umm`, which
+    /// is how they are told apart from the user's.
+    group: String,
+}
+
+/// Struct to represent a Java debug configuration in launch.json.
+#[derive(Serialize, Deserialize, TypedBuilder)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchConfiguration {
+    /// The type of debugger to use.
+    #[builder(default = "java".to_string(), setter(into))]
+    r#type:          String,
+    /// The configuration's name, shown in the Run and Debug view.
+    #[builder(setter(into))]
+    name:            String,
+    /// Whether to launch a program or attach to a running one.
+    request:         Request,
+    /// The fully qualified name of the class with the main method to launch.
+    #[builder(default, setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    main_class:      Option<String>,
+    /// Where to launch the program, e.g. `integratedTerminal`.
+    #[builder(default, setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    console:         Option<String>,
+    /// The host name of the program to attach to.
+    #[builder(default, setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    host_name:       Option<String>,
+    /// The JDWP port of the program to attach to.
+    #[builder(default, setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    port:            Option<u16>,
+    /// The label of a task in tasks.json to run before debugging.
+    #[builder(default, setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pre_launch_task: Option<String>,
+    /// How the configuration is shown, defaults to the `umm` group
+    #[builder(default = umm_presentation(), setter(into))]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presentation:    Option<LaunchPresentation>,
+}
+
+/// Struct representing a launch.json file
+#[derive(Serialize, Deserialize, TypedBuilder)]
+pub struct LaunchFile {
+    /// The launch.json version.
+    #[builder(default = "0.2.0".to_string())]
+    version:        String,
+    /// The debug configurations.
+    #[builder(default = vec![])]
+    configurations: Vec<LaunchConfiguration>,
+}
+
+/// Strips `//` and `/* */` comments and trailing commas from a JSONC file
+/// (the flavour of JSON VSCode uses for its settings), and parses what's left.
+///
+/// * `source`: contents of the file
+pub fn parse_jsonc(source: &str) -> Result<Value> {
+    let mut json = String::with_capacity(source.len());
+    let mut chars = source.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            json.push(c);
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        json.push(escaped);
+                    }
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                json.push(c);
+            }
+            ('/', Some('/')) => {
+                for c in chars.by_ref() {
+                    if c == '\n' {
+                        json.push(c);
+                        break;
+                    }
+                }
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut previous = ' ';
+                for c in chars.by_ref() {
+                    if previous == '*' && c == '/' {
+                        break;
+                    }
+                    previous = c;
+                }
+            }
+            ('}' | ']', _) => {
+                let end = json.trim_end().len();
+                if json[..end].ends_with(',') {
+                    json.remove(end - 1);
+                }
+                json.push(c);
+            }
+            _ => json.push(c),
+        }
+    }
+
+    serde_json::from_str(&json).context("Could not parse JSONC")
+}
+
+/// Merges the top level keys umm generates into an existing settings object.
+/// Keys umm generates are overwritten, everything else is kept as is.
+///
+/// * `existing`: the object currently on disk, if any
+/// * `generated`: the object umm would write
+pub fn merge_objects(existing: Option<Value>,
+                     generated: Value)
+                     -> Value {
+    match (existing, generated) {
+        (Some(Value::Object(mut existing)), Value::Object(generated)) => {
+            existing.extend(generated);
+            Value::Object(existing)
+        }
+        (_, generated) => generated,
+    }
+}
+
+/// Merges a list of entries umm generates (tasks, launch configurations,
+/// keybindings, etc.) into an existing list. Existing entries that umm owns,
+/// or that share a key with a generated entry, are replaced. All other
+/// entries are kept, in their original order, ahead of the generated ones.
+///
+/// * `existing`: the list currently on disk, if any
+/// * `generated`: the entries umm would write
+/// * `key`: identifies an entry, e.g. a task's label
+/// * `owned`: whether an existing entry was written by umm, so that stale
+///   entries get removed
+pub fn merge_entries(existing: Option<&Value>,
+                     generated: Vec<Value>,
+                     key: impl Fn(&Value) -> Option<String>,
+                     owned: impl Fn(&Value) -> bool)
+                     -> Vec<Value> {
+    let generated_keys = generated.iter().filter_map(&key).collect::<Vec<_>>();
+
+    let mut merged = existing.and_then(Value::as_array)
+                             .into_iter()
+                             .flatten()
+                             .filter(|entry| {
+                                 !owned(entry)
+                                 && !key(entry).is_some_and(|k| generated_keys.contains(&k))
+                             })
+                             .cloned()
+                             .collect::<Vec<_>>();
+    merged.extend(generated);
+    merged
+}
+
+/// Returns a string field of an entry, for use as a key in [`merge_entries`]
+///
+/// * `entry`: a task, launch configuration, etc.
+/// * `field`: name of the field, e.g. `label`
+pub fn string_field(entry: &Value,
+                    field: &str)
+                    -> Option<String> {
+    entry.get(field).and_then(Value::as_str).map(String::from)
+}
+
+/// Whether a task in tasks.json runs the umm binary, which means umm wrote it
+///
+/// * `task`: the task as found in tasks.json
+pub fn is_umm_task(task: &Value) -> bool {
+    let runs_umm = |v: &Value| {
+        v.as_str()
+         .or_else(|| v.get("value").and_then(Value::as_str))
+         .is_some_and(|s| s.contains("config:ummBinaryPath"))
+    };
+
+    task.get("command").is_some_and(runs_umm)
+    || task.get("args")
+           .and_then(Value::as_array)
+           .is_some_and(|args| args.iter().any(runs_umm))
+}
+
+/// Whether a keybinding in keybindings.json was written by umm
+///
+/// * `keybinding`: the keybinding as found in keybindings.json
+pub fn is_umm_keybinding(keybinding: &Value) -> bool {
+    keybinding.get("when").and_then(Value::as_str) == when_keybindings().as_deref()
+}
+
+/// Whether a debug configuration in launch.json was written by umm, which
+/// lists all of its configurations in the `umm` group
+///
+/// * `configuration`: the configuration as found in launch.json
+pub fn is_umm_launch_configuration(configuration: &Value) -> bool {
+    let group = configuration.pointer("/presentation/group")
+                             .and_then(Value::as_str);
+    group.is_some() && group == umm_presentation().as_ref().map(|p| p.group.as_str())
+}
+
+/// Reads and parses a JSONC file, or returns `None` if it doesn't exist.
+///
+/// * `path`: path to the file
+pub async fn read_jsonc(path: &Path) -> Result<Option<Value>> {
+    match tokio::fs::read_to_string(path).await {
+        Ok(source) => parse_jsonc(&source).with_context(|| {
+                                              format!("Could not parse {}, please fix or delete it",
+                                                      path.display())
+                                          })
+                                          .map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Could not read {}", path.display())),
+    }
+}
+
+/// Writes `merged` to `path` as pretty printed JSON. Nothing is written when
+/// it is the same as what is already on disk, so that comments in files umm
+/// has nothing to change in are left alone.
+///
+/// * `path`: path to the file
+/// * `existing`: what [`read_jsonc`] returned for the file
+/// * `merged`: the merged contents
+pub async fn write_json(path: &Path,
+                        existing: Option<&Value>,
+                        merged: &Value)
+                        -> Result<()> {
+    if existing == Some(merged) {
+        return Ok(());
+    }
+
+    tokio::fs::write(path, serde_json::to_string_pretty(merged)?).await
+                                                                 .with_context(|| {
+                                                                     format!("Could not write {}",
+                                                                             path.display())
+                                                                 })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// Comments are dropped, but `//` and `/*` inside strings are kept
+    #[test]
+    fn jsonc_comments() {
+        let source = r#"{
+            // a line comment
+            "url": "https://example.com", /* a block
+            comment */ "glob": "src/*/**"
+        }"#;
+        assert_eq!(parse_jsonc(source).unwrap(),
+                   json!({ "url": "https://example.com", "glob": "src/*/**" }));
+    }
+
+    /// Trailing commas in objects and arrays are dropped, even before a comment
+    #[test]
+    fn jsonc_trailing_commas() {
+        let source = r#"{ "a": [1, 2,], "b": { "c": true, // done
+        }, }"#;
+        assert_eq!(parse_jsonc(source).unwrap(),
+                   json!({ "a": [1, 2], "b": { "c": true } }));
+        assert!(parse_jsonc(r#"{ "a": , }"#).is_err());
+    }
+
+    /// The user's settings are kept, umm's are overwritten
+    #[test]
+    fn merge_settings() {
+        let existing = json!({ "editor.fontSize": 16, "ummBinaryPath": "old" });
+        let generated = json!({ "ummBinaryPath": "umm", "editor.wordWrap": "on" });
+        assert_eq!(merge_objects(Some(existing), generated),
+                   json!({ "editor.fontSize": 16,
+                           "ummBinaryPath": "umm",
+                           "editor.wordWrap": "on" }));
+    }
+
+    /// The user's tasks are kept in order, while umm's stale tasks and tasks
+    /// with the same label as a generated one are replaced
+    #[test]
+    fn merge_tasks() {
+        let user = json!({ "label": "Check formatting", "command": "./gradlew" });
+        let clashing = json!({ "label": "Run tests", "command": "make" });
+        let stale = json!({ "label": "Run Old", "command": "${config:ummBinaryPath}" });
+        let existing = json!([user, stale, clashing]);
+        let generated = vec![json!({ "label": "Run tests",
+                                     "command": "${config:ummBinaryPath}" }),];
+
+        let merged = merge_entries(Some(&existing),
+                                   generated.clone(),
+                                   |e| string_field(e, "label"),
+                                   is_umm_task);
+        assert_eq!(merged, vec![user, generated[0].clone()]);
+    }
+
+    /// Tasks count as umm's when their command or arguments run umm
+    #[test]
+    fn umm_tasks() {
+        assert!(is_umm_task(&json!({ "command": "${config:ummBinaryPath}" })));
+        assert!(is_umm_task(&json!({ "command": "chmod",
+                                     "args": ["+x", { "value": "${config:ummBinaryPath}" }] })));
+        assert!(!is_umm_task(&json!({ "label": "Check formatting", "command": "./gradlew" })));
+    }
+
+    /// Only configurations in umm's group are umm's, whatever task they run
+    #[test]
+    fn umm_launch_configurations() {
+        let generated = LaunchConfiguration::builder().name("Debug Main")
+                                                      .request(Request::Launch)
+                                                      .pre_launch_task("Check Main".to_string())
+                                                      .build();
+        assert!(is_umm_launch_configuration(&serde_json::to_value(generated).unwrap()));
+        assert!(!is_umm_launch_configuration(&json!({ "type": "java",
+                                                      "name": "Format",
+                                                      "preLaunchTask": "Check formatting" })));
+    }
+}